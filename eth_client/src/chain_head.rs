@@ -0,0 +1,284 @@
+//! Latest Ethereum block shared between the workers
+//!
+//! The service subscribes to `newHeads` over WebSocket and falls back to polling the latest block
+//! over HTTP while the subscription is unavailable. The head is published through a `watch` channel,
+//! so the workers read it without RPC requests and can wait for the next block.
+
+use crate::methods::EthClient;
+use std::time::Duration;
+use tokio::sync::watch;
+use web3::futures::StreamExt;
+use web3::types::{Block, BlockHeader, H256, U256};
+use web3::Transport;
+
+/// The subscription is considered stale if no header is received during this time
+const WS_HEAD_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long to poll over HTTP before the next subscription attempt
+const WS_RECONNECT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChainHead {
+    pub number: u64,
+    pub hash: H256,
+    pub base_fee_per_gas: Option<U256>,
+    /// Block timestamp in seconds
+    pub timestamp: u64,
+}
+
+impl ChainHead {
+    pub fn from_header(header: &BlockHeader) -> Option<Self> {
+        Some(ChainHead {
+            number: header.number?.as_u64(),
+            hash: header.hash?,
+            base_fee_per_gas: header.base_fee_per_gas,
+            timestamp: header.timestamp.as_u64(),
+        })
+    }
+
+    /// The block is older than `max_age` at `now` (UNIX time in seconds), e.g. both the subscription
+    /// and the polling fail
+    pub fn is_stale(&self, now: u64, max_age: Duration) -> bool {
+        now.saturating_sub(self.timestamp) > max_age.as_secs()
+    }
+
+    pub fn from_block<TX>(block: &Block<TX>) -> Option<Self> {
+        Some(ChainHead {
+            number: block.number?.as_u64(),
+            hash: block.hash?,
+            base_fee_per_gas: block.base_fee_per_gas,
+            timestamp: block.timestamp.as_u64(),
+        })
+    }
+}
+
+pub type ChainHeadReceiver = watch::Receiver<ChainHead>;
+
+pub struct ChainHeadService<T = web3::transports::Http> {
+    eth_client: EthClient<T>,
+    ws_url: Option<url::Url>,
+    poll_interval: Duration,
+    sender: watch::Sender<ChainHead>,
+}
+
+impl<T> ChainHeadService<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    /// Fetches the latest block over HTTP, so the receivers always have a head
+    pub async fn new(
+        eth_client: EthClient<T>,
+        ws_url: Option<url::Url>,
+        poll_interval: Duration,
+    ) -> web3::contract::Result<(Self, ChainHeadReceiver)> {
+        let head = latest_head(&eth_client).await?;
+        let (sender, receiver) = watch::channel(head);
+
+        Ok((
+            ChainHeadService {
+                eth_client,
+                ws_url,
+                poll_interval,
+                sender,
+            },
+            receiver,
+        ))
+    }
+
+    pub async fn run(self) {
+        loop {
+            let poll_duration = match &self.ws_url {
+                Some(ws_url) => {
+                    if let Err(err) = self.follow_new_heads(ws_url).await {
+                        tracing::warn!(
+                            "The newHeads subscription failed: {:?}, poll the latest block over HTTP",
+                            err
+                        );
+                    }
+                    Some(WS_RECONNECT_INTERVAL)
+                }
+                None => None,
+            };
+
+            self.poll(poll_duration).await;
+        }
+    }
+
+    async fn follow_new_heads(&self, ws_url: &url::Url) -> web3::Result<()> {
+        let transport = web3::transports::WebSocket::new(ws_url.as_str()).await?;
+        let mut heads = web3::Web3::new(transport)
+            .eth_subscribe()
+            .subscribe_new_heads()
+            .await?;
+        tracing::info!("Subscribed to newHeads on {}", ws_url);
+
+        loop {
+            let header = match tokio::time::timeout(WS_HEAD_TIMEOUT, heads.next()).await {
+                Ok(Some(header)) => header?,
+                Ok(None) => return Err(transport_error("the subscription is closed".to_string())),
+                Err(_) => {
+                    return Err(transport_error(format!(
+                        "no new heads for {} seconds",
+                        WS_HEAD_TIMEOUT.as_secs()
+                    )))
+                }
+            };
+
+            match ChainHead::from_header(&header) {
+                Some(head) => self.publish(head),
+                None => tracing::warn!("Received the pending block header: {:?}", header),
+            }
+        }
+    }
+
+    /// Polls the latest block over HTTP for `duration` or forever
+    async fn poll(&self, duration: Option<Duration>) {
+        let started_at = tokio::time::Instant::now();
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        while duration.map_or(true, |duration| started_at.elapsed() < duration) {
+            interval.tick().await;
+            match latest_head(&self.eth_client).await {
+                Ok(head) => self.publish(head),
+                Err(err) => tracing::warn!("Failed to get the latest block: {:?}", err),
+            }
+        }
+    }
+
+    fn publish(&self, head: ChainHead) {
+        self.sender
+            .send_if_modified(|current| update_head(current, head));
+    }
+}
+
+fn transport_error(message: String) -> web3::Error {
+    web3::Error::Transport(web3::error::TransportError::Message(message))
+}
+
+async fn latest_head<T>(eth_client: &EthClient<T>) -> web3::contract::Result<ChainHead>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    let block = eth_client.get_latest_block().await?;
+    ChainHead::from_block(&block).ok_or_else(|| {
+        web3::contract::Error::InvalidOutputType("The latest block is pending".to_string())
+    })
+}
+
+/// Replaces the head by a newer block or by another block of the same height after a reorg
+fn update_head(current: &mut ChainHead, head: ChainHead) -> bool {
+    if head.number > current.number || (head.number == current.number && head.hash != current.hash)
+    {
+        *current = head;
+        return true;
+    }
+
+    false
+}
+
+/// Waits for the next head at most `timeout`, returns the latest head
+pub async fn wait_for_new_head(receiver: &mut ChainHeadReceiver, timeout: Duration) -> ChainHead {
+    match tokio::time::timeout(timeout, receiver.changed()).await {
+        // The service is stopped, keep the previous pace
+        Ok(Err(_)) => tokio::time::sleep(timeout).await,
+        Ok(Ok(())) | Err(_) => {}
+    }
+
+    *receiver.borrow_and_update()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::chain_head::{update_head, wait_for_new_head, ChainHead, ChainHeadService};
+    use crate::methods::{EthClient, RetryPolicy};
+    use crate::provider::RpcProvider;
+    use crate::test_utils::RecordedTransport;
+    use std::time::Duration;
+    use web3::types::{Block, H256, U256};
+
+    fn block(number: u64) -> serde_json::Value {
+        serde_json::to_value(Block::<H256> {
+            number: Some(number.into()),
+            hash: Some(H256::from_low_u64_be(number)),
+            base_fee_per_gas: Some(U256::from(number * 10)),
+            timestamp: U256::from(1_000 + number * 12),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn head(number: u64, hash: u64) -> ChainHead {
+        ChainHead {
+            number,
+            hash: H256::from_low_u64_be(hash),
+            base_fee_per_gas: None,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn is_stale_test() {
+        let head = ChainHead {
+            timestamp: 1_000,
+            ..head(10, 10)
+        };
+        let max_age = Duration::from_secs(60);
+
+        assert!(!head.is_stale(1_000, max_age));
+        assert!(!head.is_stale(1_060, max_age));
+        assert!(head.is_stale(1_061, max_age));
+        // The local clock is behind the block
+        assert!(!head.is_stale(900, max_age));
+    }
+
+    #[test]
+    fn update_head_test() {
+        let mut current = head(10, 10);
+
+        assert!(!update_head(&mut current, head(10, 10)));
+        assert!(!update_head(&mut current, head(9, 9)));
+
+        assert!(update_head(&mut current, head(11, 11)));
+        assert_eq!(current, head(11, 11));
+
+        // Reorg
+        assert!(update_head(&mut current, head(11, 12)));
+        assert_eq!(current, head(11, 12));
+    }
+
+    #[tokio::test]
+    async fn poll_test() {
+        let transport = RecordedTransport::default();
+        for number in [16, 16, 17] {
+            transport.push_response("eth_getBlockByNumber", block(number));
+        }
+
+        let eth_client = EthClient::with_provider(
+            RpcProvider::from_transports(vec![("recorded".to_string(), transport.clone())])
+                .unwrap(),
+            Duration::from_secs(30),
+            RetryPolicy::default(),
+        );
+        let (service, mut receiver) =
+            ChainHeadService::new(eth_client, None, Duration::from_millis(10))
+                .await
+                .unwrap();
+        assert_eq!(receiver.borrow().number, 16);
+
+        tokio::spawn(service.run());
+
+        // The second poll returns the same block, it isn't published
+        let head = wait_for_new_head(&mut receiver, Duration::from_secs(5)).await;
+        assert_eq!(
+            head,
+            ChainHead {
+                number: 17,
+                hash: H256::from_low_u64_be(17),
+                base_fee_per_gas: Some(U256::from(170)),
+                timestamp: 1_204,
+            }
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Typed bindings for the `EthErc20FastBridge` contract
+//!
+//! The ABI is embedded in the crate, so the relayer doesn't depend on Etherscan at startup.
+//! It contains only the functions and the events used by the relayer.
+
+use web3::ethabi::{self, Token};
+use web3::types::{Address, Log, H256, U256};
+
+/// ABI of the `EthErc20FastBridge` implementation contract
+pub const FAST_BRIDGE_ABI: &str = include_str!("../abi/EthErc20FastBridge.json");
+
+pub const TRANSFER_TOKENS_METHOD: &str = "transferTokens";
+pub const TRANSFER_TOKENS_EVENT: &str = "TransferTokens";
+
+pub fn load_abi(abi: &[u8]) -> ethabi::Result<ethabi::Contract> {
+    ethabi::Contract::load(abi)
+}
+
+/// `TransferTokens` event of the `abi`
+pub fn transfer_tokens_event(abi: &[u8]) -> ethabi::Result<ethabi::Event> {
+    load_abi(abi).and_then(|abi| abi.event(TRANSFER_TOKENS_EVENT).cloned())
+}
+
+/// Arguments of the `transferTokens` method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferTokensCall {
+    pub token: Address,
+    pub recipient: Address,
+    pub nonce: U256,
+    pub amount: U256,
+    /// NEAR account which receives the unlocked tokens
+    pub unlock_recipient: String,
+    pub valid_till_block_height: U256,
+}
+
+impl web3::contract::tokens::Tokenize for TransferTokensCall {
+    fn into_tokens(self) -> Vec<Token> {
+        vec![
+            Token::Address(self.token),
+            Token::Address(self.recipient),
+            Token::Uint(self.nonce),
+            Token::Uint(self.amount),
+            Token::String(self.unlock_recipient),
+            Token::Uint(self.valid_till_block_height),
+        ]
+    }
+}
+
+/// `TransferTokens` event emitted by `transferTokens`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferTokensEvent {
+    pub nonce: U256,
+    pub relayer: Address,
+    pub token: Address,
+    pub recipient: Address,
+    pub amount: U256,
+    pub unlock_recipient: String,
+    pub valid_till_block_height: U256,
+}
+
+/// The event is decoded with the `TransferTokens` event of the loaded ABI, see [`transfer_tokens_event`]
+impl TransferTokensEvent {
+    pub fn topic(event: &ethabi::Event) -> H256 {
+        event.signature()
+    }
+
+    pub fn from_log(event: &ethabi::Event, log: &Log) -> ethabi::Result<Self> {
+        Self::from_raw_log(
+            event,
+            ethabi::RawLog {
+                topics: log.topics.clone(),
+                data: log.data.0.clone(),
+            },
+        )
+    }
+
+    pub fn from_raw_log(event: &ethabi::Event, log: ethabi::RawLog) -> ethabi::Result<Self> {
+        let log = event.parse_log(log)?;
+
+        let mut params = log.params.into_iter().map(|param| param.value);
+        let mut next = || params.next().ok_or(ethabi::Error::InvalidData);
+
+        Ok(Self {
+            nonce: next()?.into_uint().ok_or(ethabi::Error::InvalidData)?,
+            relayer: next()?.into_address().ok_or(ethabi::Error::InvalidData)?,
+            token: next()?.into_address().ok_or(ethabi::Error::InvalidData)?,
+            recipient: next()?.into_address().ok_or(ethabi::Error::InvalidData)?,
+            amount: next()?.into_uint().ok_or(ethabi::Error::InvalidData)?,
+            unlock_recipient: next()?.into_string().ok_or(ethabi::Error::InvalidData)?,
+            valid_till_block_height: next()?.into_uint().ok_or(ethabi::Error::InvalidData)?,
+        })
+    }
+}
+
+/// Hash of the signatures of the `items` functions and events, `None` if an item is missing
+pub fn abi_hash(abi: &ethabi::Contract, items: &[String]) -> Option<H256> {
+    let mut signatures = items
+        .iter()
+        .map(|name| {
+            if let Ok(function) = abi.function(name) {
+                Some(function.signature())
+            } else if let Ok(event) = abi.event(name) {
+                let params: Vec<String> = event
+                    .inputs
+                    .iter()
+                    .map(|param| match param.indexed {
+                        true => format!("{} indexed", param.kind),
+                        false => param.kind.to_string(),
+                    })
+                    .collect();
+                Some(format!("event {}({})", event.name, params.join(",")))
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<String>>>()?;
+    signatures.sort();
+
+    Some(H256::from(web3::signing::keccak256(
+        signatures.join(";").as_bytes(),
+    )))
+}
+
+/// Compares the hashes of the items of the `expected` ABI in both ABIs
+pub fn verify_abi(expected: &[u8], actual: &[u8]) -> Result<H256, String> {
+    let expected = load_abi(expected).map_err(|err| format!("Invalid ABI: {}", err))?;
+    let actual = load_abi(actual).map_err(|err| format!("Invalid on-chain ABI: {}", err))?;
+
+    let items: Vec<String> = expected
+        .functions()
+        .map(|function| function.name.clone())
+        .chain(expected.events().map(|event| event.name.clone()))
+        .collect();
+
+    let expected_hash = abi_hash(&expected, &items).ok_or("Invalid ABI".to_string())?;
+    match abi_hash(&actual, &items) {
+        Some(actual_hash) if actual_hash == expected_hash => Ok(actual_hash),
+        Some(actual_hash) => Err(format!(
+            "ABI hash mismatch: expected {:?}, on-chain {:?}",
+            expected_hash, actual_hash
+        )),
+        None => Err(format!(
+            "On-chain ABI doesn't contain some of the items: {}",
+            items.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::fast_bridge::{
+        load_abi, transfer_tokens_event, verify_abi, TransferTokensCall, TransferTokensEvent,
+        FAST_BRIDGE_ABI, TRANSFER_TOKENS_METHOD,
+    };
+    use crate::test_utils::{get_eth_contract_abi, get_eth_erc20_fast_bridge_impl_address};
+    use web3::contract::tokens::Tokenize;
+    use web3::ethabi::{RawLog, Token};
+    use web3::types::{Address, U256};
+
+    fn transfer_tokens_call() -> TransferTokensCall {
+        TransferTokensCall {
+            token: Address::repeat_byte(1),
+            recipient: Address::repeat_byte(2),
+            nonce: U256::from(7),
+            amount: U256::from(1_000_000),
+            unlock_recipient: "relayer.testnet".to_string(),
+            valid_till_block_height: U256::from(9_000_000),
+        }
+    }
+
+    #[test]
+    fn transfer_tokens_call_test() {
+        let abi = load_abi(FAST_BRIDGE_ABI.as_bytes()).unwrap();
+        let function = abi.function(TRANSFER_TOKENS_METHOD).unwrap();
+
+        let input = function
+            .encode_input(&transfer_tokens_call().into_tokens())
+            .unwrap();
+        assert_eq!(
+            function.decode_input(&input[4..]).unwrap(),
+            transfer_tokens_call().into_tokens()
+        );
+    }
+
+    #[test]
+    fn transfer_tokens_event_test() {
+        let event = transfer_tokens_event(FAST_BRIDGE_ABI.as_bytes()).unwrap();
+        let call = transfer_tokens_call();
+        let relayer = Address::repeat_byte(3);
+
+        let log = RawLog {
+            topics: vec![
+                TransferTokensEvent::topic(&event),
+                web3::types::H256::from_low_u64_be(call.nonce.as_u64()),
+                web3::types::H256::from_low_u64_be(call.valid_till_block_height.as_u64()),
+            ],
+            data: web3::ethabi::encode(&[
+                Token::Address(relayer),
+                Token::Address(call.token),
+                Token::Address(call.recipient),
+                Token::Uint(call.amount),
+                Token::String(call.unlock_recipient.clone()),
+            ]),
+        };
+
+        assert_eq!(
+            TransferTokensEvent::from_raw_log(&event, log.clone()).unwrap(),
+            TransferTokensEvent {
+                nonce: call.nonce,
+                relayer,
+                token: call.token,
+                recipient: call.recipient,
+                amount: call.amount,
+                unlock_recipient: call.unlock_recipient,
+                valid_till_block_height: call.valid_till_block_height,
+            }
+        );
+
+        let mut other_event = log;
+        other_event.topics[0] = web3::types::H256::zero();
+        assert!(TransferTokensEvent::from_raw_log(&event, other_event).is_err());
+    }
+
+    #[test]
+    fn verify_abi_test() {
+        let abi = FAST_BRIDGE_ABI.as_bytes();
+        assert!(verify_abi(abi, abi).is_ok());
+
+        // The on-chain ABI may contain the items which are not used by the relayer
+        let mut on_chain: Vec<serde_json::Value> = serde_json::from_str(FAST_BRIDGE_ABI).unwrap();
+        on_chain.push(serde_json::json!({
+            "inputs": [],
+            "name": "pause",
+            "outputs": [],
+            "stateMutability": "nonpayable",
+            "type": "function"
+        }));
+        assert!(verify_abi(abi, serde_json::to_string(&on_chain).unwrap().as_bytes()).is_ok());
+
+        // `transferTokens` without `valid_till_block_height`
+        let transfer_tokens = on_chain
+            .iter()
+            .position(|item| item["name"] == TRANSFER_TOKENS_METHOD && item["type"] == "function")
+            .unwrap();
+        on_chain[transfer_tokens]["inputs"]
+            .as_array_mut()
+            .unwrap()
+            .pop();
+        assert!(verify_abi(abi, serde_json::to_string(&on_chain).unwrap().as_bytes()).is_err());
+
+        on_chain.remove(transfer_tokens);
+        assert!(verify_abi(abi, serde_json::to_string(&on_chain).unwrap().as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn smoke_verify_on_chain_abi_test() {
+        let on_chain_abi = get_eth_contract_abi(get_eth_erc20_fast_bridge_impl_address()).await;
+        verify_abi(FAST_BRIDGE_ABI.as_bytes(), on_chain_abi.as_bytes()).unwrap();
+    }
+}
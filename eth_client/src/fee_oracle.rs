@@ -0,0 +1,266 @@
+//! EIP-1559 fee oracle
+//!
+//! Derives the priority fee and the max fee per gas from `eth_feeHistory`:
+//! * the tip is the median over the sampled blocks of the requested reward percentile
+//! * the max fee is the next block base fee scaled by a multiplier plus the tip
+
+use crate::methods::FeeData;
+use web3::types::{BlockNumber, FeeHistory, U256};
+
+const MULTIPLIER_PRECISION: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeOracleLevel {
+    /// Percentile of the effective priority fees in a block, from 0 to 100
+    pub reward_percentile: f64,
+    /// Headroom for the base fee growth until the transaction is included
+    pub base_fee_multiplier: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeeLimits {
+    pub min_priority_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+}
+
+fn invalid_fee_history(message: &str) -> web3::contract::Error {
+    web3::contract::Error::InvalidOutputType(format!("Invalid fee history: {}", message))
+}
+
+/// Number of the newest block of the fee history
+pub fn newest_block(fee_history: &FeeHistory) -> web3::contract::Result<u64> {
+    let oldest_block = match fee_history.oldest_block {
+        BlockNumber::Number(number) => number.as_u64(),
+        _ => return Err(invalid_fee_history("`oldestBlock` is not a number")),
+    };
+    let blocks_count = fee_history.gas_used_ratio.len() as u64;
+    if blocks_count == 0 {
+        return Err(invalid_fee_history("no blocks"));
+    }
+
+    Ok(oldest_block + blocks_count - 1)
+}
+
+/// Median over the non-empty blocks of the reward with `reward_index`,
+/// zero if all the sampled blocks are empty
+pub fn priority_fee_percentile(
+    fee_history: &FeeHistory,
+    reward_index: usize,
+) -> web3::contract::Result<U256> {
+    let rewards = fee_history
+        .reward
+        .as_ref()
+        .ok_or_else(|| invalid_fee_history("no rewards"))?;
+
+    let mut block_rewards = rewards
+        .iter()
+        .zip(fee_history.gas_used_ratio.iter())
+        .filter(|(_, gas_used_ratio)| **gas_used_ratio > 0.)
+        .map(|(block_rewards, _)| {
+            block_rewards
+                .get(reward_index)
+                .copied()
+                .ok_or_else(|| invalid_fee_history("no reward for the percentile"))
+        })
+        .collect::<web3::contract::Result<Vec<U256>>>()?;
+
+    if block_rewards.is_empty() {
+        return Ok(U256::zero());
+    }
+
+    block_rewards.sort();
+    let middle = block_rewards.len() / 2;
+    if block_rewards.len() % 2 == 0 {
+        Ok((block_rewards[middle - 1] + block_rewards[middle]) / 2)
+    } else {
+        Ok(block_rewards[middle])
+    }
+}
+
+/// Fee data for the level, the reward with `reward_index` must be sampled
+/// with the `level.reward_percentile`
+pub fn estimate_fee_data(
+    fee_history: &FeeHistory,
+    reward_index: usize,
+    level: &FeeOracleLevel,
+    limits: &FeeLimits,
+) -> web3::contract::Result<FeeData> {
+    let base_fee_per_gas = *fee_history
+        .base_fee_per_gas
+        .last()
+        .ok_or_else(|| invalid_fee_history("no base fee"))?;
+
+    let mut max_priority_fee_per_gas = priority_fee_percentile(fee_history, reward_index)?;
+    if let Some(min_priority_fee_per_gas) = limits.min_priority_fee_per_gas {
+        max_priority_fee_per_gas =
+            std::cmp::max(max_priority_fee_per_gas, min_priority_fee_per_gas);
+    }
+    if let Some(limit) = limits.max_priority_fee_per_gas {
+        max_priority_fee_per_gas = std::cmp::min(max_priority_fee_per_gas, limit);
+    }
+
+    if !level.base_fee_multiplier.is_finite() || level.base_fee_multiplier < 0. {
+        return Err(web3::contract::Error::InvalidOutputType(format!(
+            "Invalid base fee multiplier: {}",
+            level.base_fee_multiplier
+        )));
+    }
+    let multiplier = (level.base_fee_multiplier * MULTIPLIER_PRECISION as f64).round() as u64;
+    let mut max_fee_per_gas = base_fee_per_gas
+        .checked_mul(multiplier.into())
+        .map(|fee| fee / MULTIPLIER_PRECISION)
+        .and_then(|fee| fee.checked_add(max_priority_fee_per_gas))
+        .ok_or("Failed to calculate `max_fee_per_gas`".to_string())?;
+    if let Some(limit) = limits.max_fee_per_gas {
+        // The transaction with the max fee below the base fee can't be included
+        if limit < base_fee_per_gas {
+            return Err(web3::contract::Error::InvalidOutputType(format!(
+                "The `max_fee_per_gas` limit {} is below the base fee {}",
+                limit, base_fee_per_gas
+            )));
+        }
+        max_fee_per_gas = std::cmp::min(max_fee_per_gas, limit);
+    }
+
+    // The priority fee can't exceed the max fee
+    max_priority_fee_per_gas = std::cmp::min(max_priority_fee_per_gas, max_fee_per_gas);
+
+    Ok(FeeData {
+        base_fee_per_gas,
+        max_priority_fee_per_gas,
+        max_fee_per_gas,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::fee_oracle::{
+        estimate_fee_data, newest_block, priority_fee_percentile, FeeLimits, FeeOracleLevel,
+    };
+    use crate::methods::FeeData;
+    use web3::types::{FeeHistory, U256};
+
+    const GWEI: u64 = 1_000_000_000;
+
+    // `eth_feeHistory` response for 5 blocks with the 10th, 50th and 90th reward percentiles
+    fn recorded_fee_history() -> FeeHistory {
+        serde_json::from_value(serde_json::json!({
+            "oldestBlock": "0x10d4e5c",
+            "baseFeePerGas": [
+                "0x4a817c800",
+                "0x4d7c6d000",
+                "0x4e3b29200",
+                "0x0",
+                "0x4a817c800",
+                "0x4c5e52d00"
+            ],
+            "gasUsedRatio": [0.62, 0.54, 0.0, 0.48, 0.57],
+            "reward": [
+                ["0x3b9aca00", "0x77359400", "0x12a05f200"],
+                ["0x5f5e100", "0x59682f00", "0xb2d05e00"],
+                ["0x0", "0x0", "0x0"],
+                ["0x3b9aca00", "0x9502f900", "0x165a0bc00"],
+                ["0x1dcd6500", "0x3b9aca00", "0x77359400"]
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn level(reward_percentile: f64, base_fee_multiplier: f64) -> FeeOracleLevel {
+        FeeOracleLevel {
+            reward_percentile,
+            base_fee_multiplier,
+        }
+    }
+
+    #[test]
+    fn newest_block_test() {
+        assert_eq!(newest_block(&recorded_fee_history()).unwrap(), 0x10d4e60);
+    }
+
+    #[test]
+    fn priority_fee_percentile_test() {
+        let fee_history = recorded_fee_history();
+
+        // The empty block is ignored: [1, 0.1, 1, 0.5] gwei
+        assert_eq!(
+            priority_fee_percentile(&fee_history, 0).unwrap(),
+            U256::from(GWEI * 3 / 4)
+        );
+        // [2, 1.5, 2.5, 1] gwei
+        assert_eq!(
+            priority_fee_percentile(&fee_history, 1).unwrap(),
+            U256::from(GWEI * 7 / 4)
+        );
+        // [5, 3, 6, 2] gwei
+        assert_eq!(
+            priority_fee_percentile(&fee_history, 2).unwrap(),
+            U256::from(GWEI * 4)
+        );
+        assert!(priority_fee_percentile(&fee_history, 3).is_err());
+
+        let mut empty_blocks = fee_history;
+        empty_blocks.gas_used_ratio = vec![0.; 5];
+        assert_eq!(
+            priority_fee_percentile(&empty_blocks, 1).unwrap(),
+            U256::zero()
+        );
+
+        empty_blocks.reward = None;
+        assert!(priority_fee_percentile(&empty_blocks, 1).is_err());
+    }
+
+    #[test]
+    fn estimate_fee_data_test() {
+        let fee_history = recorded_fee_history();
+
+        assert_eq!(
+            estimate_fee_data(&fee_history, 1, &level(50., 2.), &FeeLimits::default()).unwrap(),
+            FeeData {
+                base_fee_per_gas: U256::from(20_500_000_000u64),
+                max_priority_fee_per_gas: U256::from(GWEI * 7 / 4),
+                max_fee_per_gas: U256::from(41_000_000_000u64 + GWEI * 7 / 4),
+            }
+        );
+
+        assert_eq!(
+            estimate_fee_data(&fee_history, 0, &level(10., 1.125), &FeeLimits::default())
+                .unwrap()
+                .max_fee_per_gas,
+            U256::from(23_062_500_000u64 + GWEI * 3 / 4)
+        );
+    }
+
+    #[test]
+    fn estimate_fee_data_limits_test() {
+        let fee_history = recorded_fee_history();
+
+        let limits = FeeLimits {
+            min_priority_fee_per_gas: Some(U256::from(GWEI)),
+            max_priority_fee_per_gas: Some(U256::from(3 * GWEI)),
+            max_fee_per_gas: None,
+        };
+        let low = estimate_fee_data(&fee_history, 0, &level(10., 2.), &limits).unwrap();
+        assert_eq!(low.max_priority_fee_per_gas, U256::from(GWEI));
+        let high = estimate_fee_data(&fee_history, 2, &level(90., 2.), &limits).unwrap();
+        assert_eq!(high.max_priority_fee_per_gas, U256::from(3 * GWEI));
+
+        let limits = FeeLimits {
+            max_fee_per_gas: Some(U256::from(22 * GWEI)),
+            ..FeeLimits::default()
+        };
+        let capped = estimate_fee_data(&fee_history, 2, &level(90., 2.), &limits).unwrap();
+        assert_eq!(capped.max_fee_per_gas, U256::from(22 * GWEI));
+        assert_eq!(capped.max_priority_fee_per_gas, U256::from(4 * GWEI));
+
+        assert!(estimate_fee_data(&fee_history, 1, &level(50., -1.), &limits).is_err());
+
+        // The limit below the base fee of 20.5 gwei
+        let limits = FeeLimits {
+            max_fee_per_gas: Some(U256::from(GWEI)),
+            ..FeeLimits::default()
+        };
+        assert!(estimate_fee_data(&fee_history, 2, &level(90., 2.), &limits).is_err());
+    }
+}
@@ -1,2 +1,7 @@
+pub mod chain_head;
+pub mod fast_bridge;
+pub mod fee_oracle;
 pub mod methods;
+pub mod price_feeds;
+pub mod provider;
 pub mod test_utils;
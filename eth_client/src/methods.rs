@@ -1,7 +1,19 @@
-use web3::{contract::Options, types::BlockNumber};
+use crate::provider::{classify_error, RpcProvider};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web3::contract::tokens::Tokenize;
+use web3::contract::{Contract, Options};
+use web3::types::{Address, BlockId, BlockNumber, H256, U256};
+use web3::{BatchTransport, Transport};
 
 const EIP_1559_TRANSACTION_TYPE: u64 = 2;
 
+const ERC20_DECIMALS_ABI: &str = r#"[
+  {"inputs":[],"name":"decimals","outputs":[{"internalType":"uint8","name":"","type":"uint8"}],"stateMutability":"view","type":"function"}
+]"#;
+
 pub fn new_eth_rpc_client(timeout: Option<std::time::Duration>) -> web3::Result<reqwest::Client> {
     let mut builder = reqwest::Client::builder();
     builder = builder.user_agent(reqwest::header::HeaderValue::from_static("web3.rs"));
@@ -18,24 +30,6 @@ pub fn new_eth_rpc_client(timeout: Option<std::time::Duration>) -> web3::Result<
     })?)
 }
 
-pub fn construct_contract_interface(
-    eth_endpoint: reqwest::Url,
-    contract_addr: web3::types::Address,
-    contract_abi: &[u8],
-    rpc_timeout_secs: u64,
-) -> web3::contract::Result<web3::contract::Contract<web3::transports::Http>> {
-    let transport = web3::transports::Http::with_client(
-        new_eth_rpc_client(Some(std::time::Duration::from_secs(rpc_timeout_secs)))?,
-        eth_endpoint,
-    );
-    let client = web3::Web3::new(transport);
-    Ok(web3::contract::Contract::from_json(
-        client.eth(),
-        contract_addr,
-        contract_abi,
-    )?)
-}
-
 // Alternative to this feature: include_bytes!("./<PATH>/<FILENAME.abi>")
 pub async fn get_contract_abi(
     endpoint_url: &str,
@@ -68,134 +62,53 @@ pub async fn get_contract_abi(
     Ok(response)
 }
 
-struct FeeData {
-    #[allow(dead_code)]
-    base_fee_per_gas: web3::types::U256,
-    max_priority_fee_per_gas: web3::types::U256,
-    max_fee_per_gas: web3::types::U256,
-}
-
-async fn get_fee_data(
-    server_address: reqwest::Url,
-    max_priority_fee_per_gas: Option<web3::types::U256>,
-    rpc_timeout_secs: u64,
-) -> web3::contract::Result<FeeData> {
-    let transport = web3::transports::Http::with_client(
-        new_eth_rpc_client(Some(std::time::Duration::from_secs(rpc_timeout_secs)))?,
-        server_address,
-    );
-    let client = web3::Web3::new(transport);
-
-    let last_block = client
-        .eth()
-        .block(web3::types::BlockId::Number(
-            web3::types::BlockNumber::Latest,
-        ))
-        .await?
-        .ok_or("Failed to get last block".to_string())?;
-
-    let base_fee_per_gas = last_block
-        .base_fee_per_gas
-        .ok_or("Failed to get `base_fee_per_gas`".to_string())?;
-    let max_priority_fee_per_gas: web3::types::U256 =
-        max_priority_fee_per_gas.unwrap_or(1500000000.into());
-    let max_fee_per_gas = base_fee_per_gas
-        .checked_mul(2.into())
-        .ok_or("Failed to calculate `max_fee_per_gas`".to_string())?
-        .checked_add(max_priority_fee_per_gas)
-        .ok_or("Failed to calculate `max_fee_per_gas`".to_string())?;
-
-    Ok(FeeData {
-        base_fee_per_gas,
-        max_priority_fee_per_gas,
-        max_fee_per_gas,
-    })
-}
-
-pub async fn get_transaction_count(
-    server_address: &str,
-    account_address: web3::types::Address,
-) -> web3::error::Result<web3::types::U256> {
-    let transport = web3::transports::Http::new(server_address)?;
-    let client = web3::Web3::new(transport);
-    client
-        .eth()
-        .transaction_count(account_address, Some(BlockNumber::Pending))
-        .await
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeData {
+    pub base_fee_per_gas: web3::types::U256,
+    pub max_priority_fee_per_gas: web3::types::U256,
+    pub max_fee_per_gas: web3::types::U256,
 }
 
-pub async fn change(
-    server_addr: reqwest::Url,
-    contract_addr: web3::types::Address,
-    contract_abi: &[u8],
-    method_name: &str,
-    args: impl web3::contract::tokens::Tokenize,
-    key: impl web3::signing::Key,
-    use_eip_1559: bool,
-    transaction_count: Option<web3::types::U256>,
-    gas: Option<web3::types::U256>,
-    max_priority_fee_per_gas: Option<web3::types::U256>,
-    rpc_timeout_secs: u64,
-) -> web3::contract::Result<web3::types::H256> {
-    let mut options = web3::contract::Options::default();
-    options.nonce = transaction_count;
-
-    if use_eip_1559 {
-        let fee_data = get_fee_data(
-            server_addr.clone(),
-            max_priority_fee_per_gas,
-            rpc_timeout_secs,
+impl FeeData {
+    /// Fee per gas paid in case the base fee doesn't change before the inclusion
+    pub fn expected_fee_per_gas(&self) -> web3::types::U256 {
+        std::cmp::min(
+            self.max_fee_per_gas,
+            self.base_fee_per_gas
+                .saturating_add(self.max_priority_fee_per_gas),
         )
-        .await?;
-        options.max_fee_per_gas = Some(fee_data.max_fee_per_gas);
-        options.max_priority_fee_per_gas = Some(fee_data.max_priority_fee_per_gas);
-        options.transaction_type = Some(EIP_1559_TRANSACTION_TYPE.into());
-        options.gas = gas;
     }
 
-    let abi =
-        construct_contract_interface(server_addr, contract_addr, contract_abi, rpc_timeout_secs)?;
-    Ok(abi.signed_call(method_name, args, options, key).await?)
+    /// The max fee per gas the transaction can pay
+    pub fn worst_case_fee_per_gas(&self) -> web3::types::U256 {
+        self.max_fee_per_gas
+    }
 }
 
-pub async fn gas_price_wei(server_addr: &str) -> web3::contract::Result<web3::types::U256> {
-    let transport = web3::transports::Http::new(server_addr)?;
-    let client = web3::Web3::new(transport);
-    Ok(client.eth().gas_price().await?)
+/// Base fee per gas forecast derived from `eth_feeHistory`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseFeeForecast {
+    /// Base fee of the next block, it is known from the newest block of the history
+    pub next_block: web3::types::U256,
+    /// The lowest base fee over the history window
+    pub lowest: web3::types::U256,
+    /// The median base fee over the history window
+    pub median: web3::types::U256,
 }
 
-pub async fn estimate_gas(
-    eth_endpoint: reqwest::Url,
-    signer_eth_addr: web3::types::Address,
-    contract_addr: web3::types::Address,
-    contract_abi: &[u8],
-    method_name: &str,
-    args: impl web3::contract::tokens::Tokenize,
-    rpc_timeout_secs: u64,
-) -> web3::contract::Result<web3::types::U256> {
-    let abi =
-        construct_contract_interface(eth_endpoint, contract_addr, contract_abi, rpc_timeout_secs)?;
-    abi.estimate_gas(method_name, args, signer_eth_addr, Options::default())
-        .await
-}
+impl BaseFeeForecast {
+    pub fn from_fee_history(fee_history: &web3::types::FeeHistory) -> Option<Self> {
+        let next_block = *fee_history.base_fee_per_gas.last()?;
 
-pub fn estimate_transfer_execution_usd(
-    estimated_gas: web3::types::U256,
-    gas_price_wei: web3::types::U256,
-    ether_price_usd: f64,
-) -> f64 {
-    let precision = f64::powf(10.0, 4.0);
-    let ether_in_wei = web3::types::U256::from(1_000_000_000_000_000_000u64);
-    let ether_price = web3::types::U256::from((ether_price_usd * precision) as u128);
-    estimated_gas
-        .checked_mul(gas_price_wei)
-        .unwrap()
-        .checked_mul(ether_price)
-        .unwrap()
-        .checked_div(ether_in_wei)
-        .unwrap()
-        .as_u64() as f64
-        / precision
+        let mut base_fees = fee_history.base_fee_per_gas.clone();
+        base_fees.sort();
+
+        Some(Self {
+            next_block,
+            lowest: base_fees[0],
+            median: base_fees[base_fees.len() / 2],
+        })
+    }
 }
 
 pub async fn eth_price_usd() -> Result<Option<f64>, reqwest::Error> {
@@ -215,32 +128,410 @@ pub async fn token_price_usd(coin_id: String) -> Result<Option<f64>, reqwest::Er
     }
 }
 
-pub async fn get_last_block_number(server_addr: &str) -> web3::contract::Result<u64> {
-    let transport = web3::transports::Http::new(server_addr)?;
-    let client = web3::Web3::new(transport);
-    Ok(client.eth().block_number().await?.as_u64())
+/// EIP-1967 implementation slot: `bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`
+const EIP_1967_IMPLEMENTATION_SLOT: &str =
+    "360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+
+/// Retries of the read requests failed with a transport error or a rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// The delay before the first retry, it doubles with every next one
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 2,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1 << std::cmp::min(retry, 16))
+    }
+}
+
+trait Retriable {
+    fn is_retriable(&self) -> bool;
+}
+
+impl Retriable for web3::Error {
+    fn is_retriable(&self) -> bool {
+        classify_error(self).is_some()
+    }
+}
+
+impl Retriable for web3::contract::Error {
+    fn is_retriable(&self) -> bool {
+        match self {
+            web3::contract::Error::Api(err) => err.is_retriable(),
+            _ => false,
+        }
+    }
+}
+
+/// Ethereum JSON-RPC client shared by the workers
+///
+/// The endpoints share one pooled HTTP client with `rpc_timeout`, so the connections are reused
+/// between the requests. The parsed contract handles are cached by the address and the ABI.
+#[derive(Debug)]
+pub struct EthClient<T = web3::transports::Http> {
+    provider: RpcProvider<T>,
+    rpc_timeout: Duration,
+    retry_policy: RetryPolicy,
+    contracts: Arc<Mutex<HashMap<(Address, H256), Contract<RpcProvider<T>>>>>,
+}
+
+impl<T> Clone for EthClient<T> {
+    fn clone(&self) -> Self {
+        EthClient {
+            provider: self.provider.clone(),
+            rpc_timeout: self.rpc_timeout,
+            retry_policy: self.retry_policy,
+            contracts: self.contracts.clone(),
+        }
+    }
+}
+
+impl EthClient<web3::transports::Http> {
+    pub fn new(
+        urls: &[url::Url],
+        rpc_timeout_secs: u64,
+        retry_policy: RetryPolicy,
+    ) -> web3::Result<Self> {
+        Ok(Self::with_provider(
+            RpcProvider::new(urls, rpc_timeout_secs)?,
+            Duration::from_secs(rpc_timeout_secs),
+            retry_policy,
+        ))
+    }
+}
+
+impl<T> EthClient<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    pub fn with_provider(
+        provider: RpcProvider<T>,
+        rpc_timeout: Duration,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        EthClient {
+            provider,
+            rpc_timeout,
+            retry_policy,
+            contracts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn provider(&self) -> &RpcProvider<T> {
+        &self.provider
+    }
+
+    pub fn rpc_timeout(&self) -> Duration {
+        self.rpc_timeout
+    }
+
+    pub fn eth(&self) -> web3::api::Eth<RpcProvider<T>> {
+        web3::Web3::new(self.provider.clone()).eth()
+    }
+
+    async fn with_retries<R, E, F, Fut>(&self, mut request: F) -> Result<R, E>
+    where
+        E: Retriable + std::fmt::Debug,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            match request().await {
+                Err(err) if retry < self.retry_policy.max_retries && err.is_retriable() => {
+                    tracing::warn!(
+                        "Ethereum RPC request failed: {:?}, retry {}/{}",
+                        err,
+                        retry + 1,
+                        self.retry_policy.max_retries
+                    );
+                    tokio::time::sleep(self.retry_policy.delay(retry)).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn contract(
+        &self,
+        contract_addr: Address,
+        contract_abi: &[u8],
+    ) -> web3::contract::Result<Contract<RpcProvider<T>>> {
+        let key = (
+            contract_addr,
+            H256::from(web3::signing::keccak256(contract_abi)),
+        );
+
+        let mut contracts = self.contracts.lock().unwrap();
+        if let Some(contract) = contracts.get(&key) {
+            return Ok(contract.clone());
+        }
+
+        let contract = Contract::from_json(self.eth(), contract_addr, contract_abi)?;
+        contracts.insert(key, contract.clone());
+        Ok(contract)
+    }
+
+    pub async fn erc20_decimals(&self, token: Address) -> web3::contract::Result<u8> {
+        let contract = self.contract(token, ERC20_DECIMALS_ABI.as_bytes())?;
+
+        self.with_retries(|| contract.query("decimals", (), None, Options::default(), None))
+            .await
+    }
+
+    pub async fn get_fee_history(
+        &self,
+        block_count: u64,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> web3::contract::Result<web3::types::FeeHistory> {
+        let eth = self.eth();
+        Ok(self
+            .with_retries(|| {
+                eth.fee_history(
+                    block_count.into(),
+                    BlockNumber::Latest,
+                    reward_percentiles.clone(),
+                )
+            })
+            .await?)
+    }
+
+    pub async fn get_base_fee_forecast(
+        &self,
+        block_count: u64,
+    ) -> web3::contract::Result<BaseFeeForecast> {
+        let fee_history = self.get_fee_history(block_count, None).await?;
+
+        Ok(BaseFeeForecast::from_fee_history(&fee_history)
+            .ok_or("Failed to get `base_fee_per_gas` from the fee history".to_string())?)
+    }
+
+    pub async fn get_fee_data(
+        &self,
+        max_priority_fee_per_gas: Option<U256>,
+    ) -> web3::contract::Result<FeeData> {
+        // The transaction is included in one of the next blocks, so the next block base fee
+        // is a better estimation than the base fee of the last block
+        let base_fee_per_gas = self.get_base_fee_forecast(1).await?.next_block;
+
+        let max_priority_fee_per_gas: U256 = max_priority_fee_per_gas.unwrap_or(1500000000.into());
+        let max_fee_per_gas = base_fee_per_gas
+            .checked_mul(2.into())
+            .ok_or("Failed to calculate `max_fee_per_gas`".to_string())?
+            .checked_add(max_priority_fee_per_gas)
+            .ok_or("Failed to calculate `max_fee_per_gas`".to_string())?;
+
+        Ok(FeeData {
+            base_fee_per_gas,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+        })
+    }
+
+    pub async fn get_transaction_count(
+        &self,
+        account_address: Address,
+    ) -> web3::error::Result<U256> {
+        let eth = self.eth();
+        self.with_retries(|| eth.transaction_count(account_address, Some(BlockNumber::Pending)))
+            .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn change(
+        &self,
+        contract_addr: Address,
+        contract_abi: &[u8],
+        method_name: &str,
+        args: impl web3::contract::tokens::Tokenize,
+        key: impl web3::signing::Key,
+        use_eip_1559: bool,
+        transaction_count: Option<U256>,
+        gas: Option<U256>,
+        max_priority_fee_per_gas: Option<U256>,
+    ) -> web3::contract::Result<H256> {
+        let fee_data = if use_eip_1559 {
+            Some(self.get_fee_data(max_priority_fee_per_gas).await?)
+        } else {
+            None
+        };
+
+        self.change_with_fee_data(
+            contract_addr,
+            contract_abi,
+            method_name,
+            args,
+            key,
+            fee_data,
+            transaction_count,
+            gas,
+        )
+        .await
+    }
+
+    /// Sends the transaction with the given EIP-1559 fee parameters (legacy transaction if `None`).
+    /// The transaction isn't retried, the caller decides whether to resend it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn change_with_fee_data(
+        &self,
+        contract_addr: Address,
+        contract_abi: &[u8],
+        method_name: &str,
+        args: impl web3::contract::tokens::Tokenize,
+        key: impl web3::signing::Key,
+        fee_data: Option<FeeData>,
+        transaction_count: Option<U256>,
+        gas: Option<U256>,
+    ) -> web3::contract::Result<H256> {
+        let mut options = web3::contract::Options::default();
+        options.nonce = transaction_count;
+
+        if let Some(fee_data) = fee_data {
+            options.max_fee_per_gas = Some(fee_data.max_fee_per_gas);
+            options.max_priority_fee_per_gas = Some(fee_data.max_priority_fee_per_gas);
+            options.transaction_type = Some(EIP_1559_TRANSACTION_TYPE.into());
+            options.gas = gas;
+        }
+
+        let contract = self.contract(contract_addr, contract_abi)?;
+        Ok(contract
+            .signed_call(method_name, args, options, key)
+            .await?)
+    }
+
+    pub async fn gas_price_wei(&self) -> web3::contract::Result<U256> {
+        let eth = self.eth();
+        Ok(self.with_retries(|| eth.gas_price()).await?)
+    }
+
+    pub async fn estimate_gas(
+        &self,
+        signer_eth_addr: Address,
+        contract_addr: Address,
+        contract_abi: &[u8],
+        method_name: &str,
+        args: impl web3::contract::tokens::Tokenize,
+    ) -> web3::contract::Result<U256> {
+        let contract = self.contract(contract_addr, contract_abi)?;
+        let tokens = args.into_tokens();
+        self.with_retries(|| {
+            contract.estimate_gas(
+                method_name,
+                tokens.as_slice(),
+                signer_eth_addr,
+                Options::default(),
+            )
+        })
+        .await
+    }
+
+    pub async fn get_chain_id(&self) -> web3::contract::Result<u64> {
+        let eth = self.eth();
+        Ok(self.with_retries(|| eth.chain_id()).await?.as_u64())
+    }
+
+    /// Address of the implementation contract behind the EIP-1967 proxy
+    pub async fn get_proxy_implementation(
+        &self,
+        proxy_address: Address,
+    ) -> web3::contract::Result<Address> {
+        let slot = U256::from_str_radix(EIP_1967_IMPLEMENTATION_SLOT, 16)
+            .map_err(|err| err.to_string())?;
+        let eth = self.eth();
+        let value = self
+            .with_retries(|| eth.storage(proxy_address, slot, Some(BlockNumber::Latest)))
+            .await?;
+
+        let implementation = Address::from_slice(&value.as_bytes()[12..]);
+        if implementation.is_zero() {
+            return Err(web3::contract::Error::InvalidOutputType(format!(
+                "The EIP-1967 implementation slot of {:?} is empty",
+                proxy_address
+            )));
+        }
+
+        Ok(implementation)
+    }
+
+    pub async fn get_last_block_number(&self) -> web3::contract::Result<u64> {
+        let eth = self.eth();
+        Ok(self.with_retries(|| eth.block_number()).await?.as_u64())
+    }
+
+    pub async fn get_latest_block(&self) -> web3::contract::Result<web3::types::Block<H256>> {
+        let eth = self.eth();
+        self.with_retries(|| eth.block(BlockId::Number(BlockNumber::Latest)))
+            .await?
+            .ok_or_else(|| {
+                web3::contract::Error::InvalidOutputType(
+                    "The latest block is not found".to_string(),
+                )
+            })
+    }
+}
+
+impl<T> EthClient<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send,
+    T::Batch: Send,
+{
+    /// Client which queues the requests until `transport().submit_batch()`
+    /// and sends them in one JSON-RPC batch
+    pub fn batch(&self) -> web3::Web3<web3::transports::Batch<RpcProvider<T>>> {
+        web3::Web3::new(web3::transports::Batch::new(self.provider.clone()))
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use crate::methods::{
-        change, construct_contract_interface, estimate_gas, estimate_transfer_execution_usd,
-        eth_price_usd, gas_price_wei, get_fee_data, get_transaction_count, token_price_usd,
+        eth_price_usd, token_price_usd, BaseFeeForecast, EthClient, FeeData, RetryPolicy,
     };
+    use crate::provider::RpcProvider;
     use crate::test_utils;
+    use crate::test_utils::RecordedTransport;
     use crate::test_utils::{
-        get_eth_contract_abi, get_eth_erc20_fast_bridge_contract_abi,
-        get_eth_erc20_fast_bridge_proxy_contract_address, get_eth_rpc_url, get_eth_token,
-        get_recipient, get_relay_eth_key,
+        get_eth_client, get_eth_contract_abi, get_eth_erc20_fast_bridge_contract_abi,
+        get_eth_erc20_fast_bridge_proxy_contract_address, get_eth_token, get_recipient,
+        get_relay_eth_key, get_rpc_client,
     };
     use std::str::FromStr;
-    use url::Url;
+    use std::time::Duration;
     use web3::types::{Address, U256};
 
+    fn recorded_client(
+        transport: &RecordedTransport,
+        max_retries: u32,
+    ) -> EthClient<RecordedTransport> {
+        EthClient::with_provider(
+            RpcProvider::from_transports(vec![("recorded".to_string(), transport.clone())])
+                .unwrap(),
+            Duration::from_secs(30),
+            RetryPolicy {
+                max_retries,
+                backoff: Duration::from_millis(1),
+            },
+        )
+    }
+
     #[tokio::test]
     async fn smoke_estimate_gas_test() {
-        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi().await;
-        let eth1_endpoint = test_utils::get_eth_rpc_url();
+        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi();
+        let eth_client = test_utils::get_eth_client();
 
         let bridge_proxy_addres = test_utils::get_eth_erc20_fast_bridge_proxy_contract_address();
         let signer_addres = bridge_proxy_addres.clone();
@@ -249,29 +540,28 @@ pub mod tests {
         let token = test_utils::get_eth_token();
         let method_args = token;
 
-        let estimated_gas = estimate_gas(
-            eth1_endpoint,
-            signer_addres,
-            bridge_proxy_addres,
-            contract_abi.as_bytes(),
-            method_name,
-            method_args,
-            30,
-        )
-        .await
-        .unwrap();
+        let estimated_gas = eth_client
+            .estimate_gas(
+                signer_addres,
+                bridge_proxy_addres,
+                contract_abi.as_bytes(),
+                method_name,
+                method_args,
+            )
+            .await
+            .unwrap();
 
         println!("Estimated gas = {}", estimated_gas);
     }
 
     #[tokio::test]
     async fn smoke_gas_price_test() {
-        let eth1_endpoint = get_eth_rpc_url().to_string();
+        let eth_client = get_eth_client();
         const GWEI_IN_WEI: u64 = 1_000_000_000;
         const MAX_PRICE_IN_GWEI: u64 = 1_000_000_000;
         const MIN_PRICE_IN_WEI: u64 = 0;
 
-        if let Ok(gas_price) = gas_price_wei(&eth1_endpoint).await {
+        if let Ok(gas_price) = eth_client.gas_price_wei().await {
             println!("Current gas price = {}", gas_price);
 
             assert!(gas_price >= web3::types::U256::from(MIN_PRICE_IN_WEI));
@@ -281,21 +571,6 @@ pub mod tests {
         }
     }
 
-    #[tokio::test]
-    // data from some random transaction https://etherscan.io/tx/0xd4e7e8c91f43f13202f647efb726e867f2ae44a8d633fe5ad2549de03f1496c6
-    async fn smoke_estimate_transfer_execution_test() {
-        let estimated_gas: web3::types::U256 = web3::types::U256::from(116_855);
-        let gas_price: web3::types::U256 = web3::types::U256::from(13_088_907_561 as i64);
-        let ether_price: f64 = 1208.69;
-
-        let estimated_price =
-            estimate_transfer_execution_usd(estimated_gas, gas_price, ether_price);
-
-        println!("Estimated transfer execution = {}$", estimated_price);
-        assert!(estimated_price > 1.8);
-        assert!(estimated_price < 1.9);
-    }
-
     #[tokio::test]
     async fn smoke_token_price_test() {
         let token_name = "aurora-near";
@@ -321,10 +596,10 @@ pub mod tests {
 
     #[tokio::test]
     async fn smoke_change_test() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let bridge_proxy_addres = get_eth_erc20_fast_bridge_proxy_contract_address();
-        let contract_abi = get_eth_erc20_fast_bridge_contract_abi().await;
+        let contract_abi = get_eth_erc20_fast_bridge_contract_abi();
 
         let method_name = "transferTokens";
 
@@ -337,28 +612,27 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        let res = change(
-            eth1_endpoint,
-            bridge_proxy_addres,
-            contract_abi.as_bytes(),
-            &method_name,
-            method_args,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        let res = eth_client
+            .change(
+                bridge_proxy_addres,
+                contract_abi.as_bytes(),
+                &method_name,
+                method_args,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
 
         println!("transaction hash: {:?}", res);
     }
 
     #[tokio::test]
     async fn mint_token() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
         let token = get_eth_token();
 
         let contract_abi = get_eth_contract_abi(token).await;
@@ -368,97 +642,87 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        let res = change(
-            eth1_endpoint,
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        let res = eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
 
         println!("transaction hash: {:?}", res);
     }
 
     #[tokio::test]
-    async fn test_construct_contract_interface_not_eth_endpoint_url() {
-        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi().await;
+    async fn test_contract_not_eth_endpoint_url() {
+        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi();
         let bridge_proxy_addres = test_utils::get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        construct_contract_interface(
-            Url::from_str("https://www.google.com/").unwrap(),
-            bridge_proxy_addres,
-            contract_abi.as_bytes(),
-            30,
-        )
-        .unwrap();
+        get_rpc_client("https://www.google.com/")
+            .contract(bridge_proxy_addres, contract_abi.as_bytes())
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Abi(SerdeJson(Error(\"invalid type: map, expected valid abi spec file\", line: 1, column: 1)))"]
-    async fn test_construct_contract_interface_incorrect_json() {
-        let eth1_endpoint = test_utils::get_eth_rpc_url();
+    async fn test_contract_incorrect_json() {
+        let eth_client = test_utils::get_eth_client();
         let bridge_proxy_addres = test_utils::get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        construct_contract_interface(eth1_endpoint, bridge_proxy_addres, "{".as_bytes(), 30)
+        eth_client
+            .contract(bridge_proxy_addres, "{".as_bytes())
             .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Abi(SerdeJson(Error(\"invalid type: map, expected valid abi spec file\", line: 1, column: 2)))"]
-    async fn test_construct_contract_interface_not_abi_json() {
-        let eth1_endpoint = test_utils::get_eth_rpc_url();
+    async fn test_contract_not_abi_json() {
+        let eth_client = test_utils::get_eth_client();
         let bridge_proxy_addres = test_utils::get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        construct_contract_interface(eth1_endpoint, bridge_proxy_addres, "{}".as_bytes(), 30)
+        eth_client
+            .contract(bridge_proxy_addres, "{}".as_bytes())
             .unwrap();
     }
 
     #[tokio::test]
-    async fn test_construct_contract_interface_non_existing_address() {
-        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi().await;
-        let eth1_endpoint = test_utils::get_eth_rpc_url();
+    async fn test_contract_non_existing_address() {
+        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi();
+        let eth_client = test_utils::get_eth_client();
         let bridge_proxy_addres = web3::types::Address::from_slice(
             hex::decode("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA")
                 .unwrap()
                 .as_slice(),
         );
 
-        construct_contract_interface(
-            eth1_endpoint,
-            bridge_proxy_addres,
-            contract_abi.as_bytes(),
-            30,
-        )
-        .unwrap();
+        eth_client
+            .contract(bridge_proxy_addres, contract_abi.as_bytes())
+            .unwrap();
     }
 
     #[tokio::test]
-    async fn test_construct_contract_interface_unreachable_url() {
-        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi().await;
+    async fn test_contract_unreachable_url() {
+        let contract_abi = test_utils::get_eth_erc20_fast_bridge_contract_abi();
 
         let bridge_proxy_addres = test_utils::get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        construct_contract_interface(
-            Url::from_str("http://httpstat.us/404").unwrap(),
-            bridge_proxy_addres,
-            contract_abi.as_bytes(),
-            30,
-        )
-        .unwrap();
+        get_rpc_client("http://httpstat.us/404")
+            .contract(bridge_proxy_addres, contract_abi.as_bytes())
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Transport(Code(404)))"]
     async fn test_get_fee_data_bad_server() {
-        get_fee_data(Url::from_str("http://httpstat.us/404").unwrap(), None, 30)
+        get_rpc_client("http://httpstat.us/404")
+            .get_fee_data(None)
             .await
             .unwrap();
     }
@@ -466,11 +730,9 @@ pub mod tests {
     #[tokio::test]
     #[should_panic = "InvalidOutputType(\"Failed to calculate `max_fee_per_gas`\")"]
     async fn test_get_fee_data_big_max_priority() {
-        let eth1_endpoint = test_utils::get_eth_rpc_url();
+        let eth_client = test_utils::get_eth_client();
 
-        get_fee_data(eth1_endpoint, Some(U256::MAX), 30)
-            .await
-            .unwrap();
+        eth_client.get_fee_data(Some(U256::MAX)).await.unwrap();
     }
 
     #[tokio::test]
@@ -478,18 +740,20 @@ pub mod tests {
     async fn test_transaction_count_bad_server() {
         let eth_addres = test_utils::get_eth_token();
 
-        get_transaction_count("http://httpstat.us/404", eth_addres)
+        get_rpc_client("http://httpstat.us/404")
+            .get_transaction_count(eth_addres)
             .await
             .unwrap();
     }
 
     #[tokio::test]
     async fn test_transaction_count_bad_account() {
-        let eth1_endpoint = test_utils::get_eth_rpc_url().to_string();
+        let eth_client = test_utils::get_eth_client();
 
         assert_eq!(
             U256::zero(),
-            get_transaction_count(&eth1_endpoint, Address::zero())
+            eth_client
+                .get_transaction_count(Address::zero())
                 .await
                 .unwrap()
         );
@@ -507,21 +771,20 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        change(
-            Url::from_str("http://httpstat.us/404").unwrap(),
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            false,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        get_rpc_client("http://httpstat.us/404")
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                false,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -536,26 +799,25 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        change(
-            Url::from_str("http://httpstat.us/404").unwrap(),
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        get_rpc_client("http://httpstat.us/404")
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     async fn test_change_invalid_address() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -570,27 +832,26 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        change(
-            eth1_endpoint,
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Decoder(\"InvalidName(\\\"min\\\")\"))"]
     async fn test_change_wrong_method_name() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -600,27 +861,26 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        change(
-            eth1_endpoint,
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Decoder(\"InvalidData\"))"]
     async fn test_change_wrong_method_args() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -630,27 +890,26 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        change(
-            eth1_endpoint,
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Rpc(Error { code: ServerError(-32000), message: \"insufficient funds for gas * price + value\", data: None }))"]
     async fn test_change_wrong_private_key() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -663,27 +922,26 @@ pub mod tests {
         )
         .unwrap();
 
-        change(
-            eth1_endpoint,
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Rpc(Error { code: ServerError(-32000), message: \"replacement transaction underpriced\", data: None }))"]
     async fn test_change_wrong_nonce() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -693,45 +951,46 @@ pub mod tests {
 
         let priv_key = get_relay_eth_key();
 
-        change(
-            eth1_endpoint.clone(),
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
 
         let amount = web3::types::U256::from(200);
 
-        change(
-            eth1_endpoint,
-            token,
-            contract_abi.as_bytes(),
-            &method_name,
-            amount,
-            &priv_key,
-            true,
-            None,
-            None,
-            None,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .change(
+                token,
+                contract_abi.as_bytes(),
+                &method_name,
+                amount,
+                &priv_key,
+                true,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Transport(Code(404)))"]
     async fn test_get_price_wei_incorrect_server_address() {
-        gas_price_wei("http://httpstat.us/404").await.unwrap();
+        get_rpc_client("http://httpstat.us/404")
+            .gas_price_wei()
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -745,23 +1004,22 @@ pub mod tests {
 
         let priv_key = get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        estimate_gas(
-            Url::from_str("http://httpstat.us/404").unwrap(),
-            priv_key,
-            token,
-            contract_abi.as_bytes(),
-            method_name,
-            amount,
-            30,
-        )
-        .await
-        .unwrap();
+        get_rpc_client("http://httpstat.us/404")
+            .estimate_gas(
+                priv_key,
+                token,
+                contract_abi.as_bytes(),
+                method_name,
+                amount,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Abi(InvalidName(\"min\"))"]
     async fn test_gas_estimation_wrong_method_name() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -771,23 +1029,22 @@ pub mod tests {
 
         let priv_key = get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        estimate_gas(
-            eth1_endpoint,
-            priv_key,
-            token,
-            contract_abi.as_bytes(),
-            method_name,
-            amount,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .estimate_gas(
+                priv_key,
+                token,
+                contract_abi.as_bytes(),
+                method_name,
+                amount,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Abi(InvalidData)"]
     async fn test_gas_estimation_wrong_args() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -797,23 +1054,22 @@ pub mod tests {
 
         let priv_key = get_eth_erc20_fast_bridge_proxy_contract_address();
 
-        estimate_gas(
-            eth1_endpoint,
-            priv_key,
-            token,
-            contract_abi.as_bytes(),
-            method_name,
-            amount,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .estimate_gas(
+                priv_key,
+                token,
+                contract_abi.as_bytes(),
+                method_name,
+                amount,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[should_panic = "Api(Rpc(Error { code: ServerError(3), message: \"execution reverted: ERC20: mint to the zero address\""]
     async fn test_gas_estimation_wrong_eth_address() {
-        let eth1_endpoint = get_eth_rpc_url();
+        let eth_client = get_eth_client();
 
         let token = get_eth_token();
         let contract_abi = get_eth_contract_abi(token).await;
@@ -823,17 +1079,16 @@ pub mod tests {
 
         let priv_key = Address::zero();
 
-        estimate_gas(
-            eth1_endpoint,
-            priv_key,
-            token,
-            contract_abi.as_bytes(),
-            method_name,
-            amount,
-            30,
-        )
-        .await
-        .unwrap();
+        eth_client
+            .estimate_gas(
+                priv_key,
+                token,
+                contract_abi.as_bytes(),
+                method_name,
+                amount,
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -844,17 +1099,132 @@ pub mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn test_estimate_transfer_execution_usd_max() {
-        estimate_transfer_execution_usd(U256::MAX, U256::MAX, f64::MAX);
+    fn test_fee_data_fee_per_gas() {
+        let fee_data = FeeData {
+            base_fee_per_gas: U256::from(10),
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(22),
+        };
+        assert_eq!(fee_data.expected_fee_per_gas(), U256::from(12));
+        assert_eq!(fee_data.worst_case_fee_per_gas(), U256::from(22));
+
+        let fee_data = FeeData {
+            base_fee_per_gas: U256::from(10),
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(11),
+        };
+        assert_eq!(fee_data.expected_fee_per_gas(), U256::from(11));
+
+        let fee_data = FeeData {
+            base_fee_per_gas: U256::MAX,
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::MAX,
+        };
+        assert_eq!(fee_data.expected_fee_per_gas(), U256::MAX);
     }
 
-    #[test]
-    fn test_estimate_transfer_execution_usd_max_possible() {
-        estimate_transfer_execution_usd(
-            U256::from(100_000_000),
-            U256::from(40_000_000_u128 * 1_000_000_000_u128),
-            10_000.,
+    #[tokio::test]
+    async fn test_base_fee_forecast() {
+        let transport = RecordedTransport::default();
+        transport.push_response(
+            "eth_feeHistory",
+            serde_json::json!({
+                "oldestBlock": "0x10",
+                "baseFeePerGas": ["0x64", "0x5a", "0x6e", "0x78", "0x50"],
+                "gasUsedRatio": [0.3, 0.9, 0.8, 0.1],
+            }),
+        );
+
+        let fee_history = recorded_client(&transport, 0)
+            .get_fee_history(4, None)
+            .await
+            .unwrap();
+        assert_eq!(transport.requests_count("eth_feeHistory"), 1);
+
+        assert_eq!(
+            BaseFeeForecast::from_fee_history(&fee_history),
+            Some(BaseFeeForecast {
+                next_block: U256::from(80),
+                lowest: U256::from(80),
+                median: U256::from(100),
+            })
+        );
+
+        let mut empty_fee_history = fee_history;
+        empty_fee_history.base_fee_per_gas.clear();
+        assert_eq!(BaseFeeForecast::from_fee_history(&empty_fee_history), None);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_implementation() {
+        let transport = RecordedTransport::default();
+        let eth_client = recorded_client(&transport, 0);
+        transport.push_response(
+            "eth_getStorageAt",
+            serde_json::json!("0x000000000000000000000000b6b5739c390648a0121502ab3c3f4112f3feac1a"),
+        );
+        transport.push_response(
+            "eth_getStorageAt",
+            serde_json::json!("0x0000000000000000000000000000000000000000000000000000000000000000"),
+        );
+
+        let proxy = test_utils::get_eth_erc20_fast_bridge_proxy_contract_address();
+        let implementation = eth_client.get_proxy_implementation(proxy).await.unwrap();
+        assert_eq!(
+            implementation,
+            test_utils::get_eth_erc20_fast_bridge_impl_address()
+        );
+
+        let (_, params) = &transport.requests()[0];
+        assert_eq!(
+            params[1],
+            serde_json::json!("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc")
         );
+
+        assert!(eth_client.get_proxy_implementation(proxy).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retries() {
+        let transport = RecordedTransport::default();
+        let eth_client = recorded_client(&transport, 2);
+
+        transport.push_response("eth_blockNumber", serde_json::json!("0x10"));
+        assert_eq!(eth_client.get_last_block_number().await.unwrap(), 0x10);
+        assert_eq!(transport.requests_count("eth_blockNumber"), 1);
+
+        // No response is recorded, the transport error is retried
+        assert!(eth_client.get_last_block_number().await.is_err());
+        assert_eq!(transport.requests_count("eth_blockNumber"), 4);
+
+        let eth_client = recorded_client(&transport, 0);
+        assert!(eth_client.get_last_block_number().await.is_err());
+        assert_eq!(transport.requests_count("eth_blockNumber"), 5);
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let transport = RecordedTransport::default();
+        transport.push_response("eth_blockNumber", serde_json::json!("0x10"));
+        transport.push_response("eth_chainId", serde_json::json!("0x5"));
+
+        let batch = recorded_client(&transport, 0).batch();
+        let block_number = batch.eth().block_number();
+        let chain_id = batch.eth().chain_id();
+        batch.transport().submit_batch().await.unwrap();
+
+        assert_eq!(block_number.await.unwrap().as_u64(), 0x10);
+        assert_eq!(chain_id.await.unwrap().as_u64(), 5);
+        assert_eq!(transport.batches_count(), 1);
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let retry_policy = RetryPolicy {
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+        };
+        assert_eq!(retry_policy.delay(0), Duration::from_millis(100));
+        assert_eq!(retry_policy.delay(2), Duration::from_millis(400));
     }
 }
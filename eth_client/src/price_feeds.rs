@@ -0,0 +1,340 @@
+//! On-chain price sources
+//!
+//! Reads token prices directly from Ethereum instead of relying on an off-chain API:
+//! * Chainlink aggregators (`latestRoundData`)
+//! * Uniswap V3 pools time-weighted average price (`observe`)
+
+use crate::methods::EthClient;
+use web3::contract::{Contract, Options};
+use web3::types::{Address, U256};
+
+const CHAINLINK_AGGREGATOR_ABI: &str = r#"[
+  {"inputs":[],"name":"decimals","outputs":[{"internalType":"uint8","name":"","type":"uint8"}],"stateMutability":"view","type":"function"},
+  {"inputs":[],"name":"latestRoundData","outputs":[{"internalType":"uint80","name":"roundId","type":"uint80"},{"internalType":"int256","name":"answer","type":"int256"},{"internalType":"uint256","name":"startedAt","type":"uint256"},{"internalType":"uint256","name":"updatedAt","type":"uint256"},{"internalType":"uint80","name":"answeredInRound","type":"uint80"}],"stateMutability":"view","type":"function"}
+]"#;
+
+const UNISWAP_V3_POOL_ABI: &str = r#"[
+  {"inputs":[{"internalType":"uint32[]","name":"secondsAgos","type":"uint32[]"}],"name":"observe","outputs":[{"internalType":"int56[]","name":"tickCumulatives","type":"int56[]"},{"internalType":"uint160[]","name":"secondsPerLiquidityCumulativeX128s","type":"uint160[]"}],"stateMutability":"view","type":"function"}
+]"#;
+
+const UNISWAP_V3_TICK_BASE: f64 = 1.0001;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainlinkRoundData {
+    pub round_id: U256,
+    pub answer: i128,
+    pub started_at: U256,
+    pub updated_at: u64,
+    pub answered_in_round: U256,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniswapV3TwapParams {
+    pub pool: Address,
+    pub twap_interval_secs: u32,
+    pub base_is_token0: bool,
+    pub token0_decimals: u32,
+    pub token1_decimals: u32,
+}
+
+pub async fn chainlink_latest_round_data<T: web3::Transport>(
+    eth: web3::api::Eth<T>,
+    aggregator: Address,
+) -> web3::contract::Result<(ChainlinkRoundData, u8)> {
+    let contract = Contract::from_json(eth, aggregator, CHAINLINK_AGGREGATOR_ABI.as_bytes())?;
+
+    let decimals: u8 = contract
+        .query("decimals", (), None, Options::default(), None)
+        .await?;
+    let (round_id, answer, started_at, updated_at, answered_in_round): (
+        U256,
+        i128,
+        U256,
+        u64,
+        U256,
+    ) = contract
+        .query("latestRoundData", (), None, Options::default(), None)
+        .await?;
+
+    Ok((
+        ChainlinkRoundData {
+            round_id,
+            answer,
+            started_at,
+            updated_at,
+            answered_in_round,
+        },
+        decimals,
+    ))
+}
+
+/// Converts the aggregator answer to a price and rejects invalid or stale rounds
+pub fn chainlink_round_price(
+    round: &ChainlinkRoundData,
+    decimals: u8,
+    max_staleness_secs: Option<u64>,
+    now_secs: u64,
+) -> web3::contract::Result<f64> {
+    if round.answer <= 0 {
+        return Err(web3::contract::Error::InvalidOutputType(format!(
+            "Chainlink answer is not positive: {}",
+            round.answer
+        )));
+    }
+
+    if round.answered_in_round < round.round_id {
+        return Err(web3::contract::Error::InvalidOutputType(format!(
+            "Chainlink round {} is not complete (answered in round {})",
+            round.round_id, round.answered_in_round
+        )));
+    }
+
+    if let Some(max_staleness_secs) = max_staleness_secs {
+        if round.updated_at + max_staleness_secs < now_secs {
+            return Err(web3::contract::Error::InvalidOutputType(format!(
+                "Chainlink price is stale: updated at {}, now {}, max staleness {} sec",
+                round.updated_at, now_secs, max_staleness_secs
+            )));
+        }
+    }
+
+    Ok(round.answer as f64 / 10f64.powi(decimals.into()))
+}
+
+pub async fn chainlink_price<T: web3::Transport>(
+    eth: web3::api::Eth<T>,
+    aggregator: Address,
+    max_staleness_secs: Option<u64>,
+) -> web3::contract::Result<f64> {
+    let (round, decimals) = chainlink_latest_round_data(eth, aggregator).await?;
+    let now_secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    chainlink_round_price(&round, decimals, max_staleness_secs, now_secs)
+}
+
+pub async fn chainlink_price_usd(
+    eth_client: &EthClient,
+    aggregator: Address,
+    max_staleness_secs: Option<u64>,
+) -> web3::contract::Result<f64> {
+    chainlink_price(eth_client.eth(), aggregator, max_staleness_secs).await
+}
+
+pub async fn uniswap_v3_average_tick<T: web3::Transport>(
+    eth: web3::api::Eth<T>,
+    pool: Address,
+    twap_interval_secs: u32,
+) -> web3::contract::Result<f64> {
+    if twap_interval_secs == 0 {
+        return Err(web3::contract::Error::InvalidOutputType(
+            "TWAP interval should be greater than zero".to_string(),
+        ));
+    }
+
+    let contract = Contract::from_json(eth, pool, UNISWAP_V3_POOL_ABI.as_bytes())?;
+    let (tick_cumulatives, _seconds_per_liquidity): (Vec<i64>, Vec<U256>) = contract
+        .query(
+            "observe",
+            vec![twap_interval_secs, 0u32],
+            None,
+            Options::default(),
+            None,
+        )
+        .await?;
+
+    if tick_cumulatives.len() != 2 {
+        return Err(web3::contract::Error::InvalidOutputType(format!(
+            "Expected 2 tick cumulatives, found {}",
+            tick_cumulatives.len()
+        )));
+    }
+
+    Ok((tick_cumulatives[1] - tick_cumulatives[0]) as f64 / twap_interval_secs as f64)
+}
+
+/// Price of the base token expressed in the quote token (the other token of the pool)
+pub fn uniswap_v3_tick_to_price(
+    tick: f64,
+    base_is_token0: bool,
+    token0_decimals: u32,
+    token1_decimals: u32,
+) -> f64 {
+    // price of token0 in token1 units, adjusted by the token decimals
+    let token0_price = UNISWAP_V3_TICK_BASE.powf(tick)
+        * 10f64.powi(token0_decimals as i32 - token1_decimals as i32);
+
+    if base_is_token0 {
+        token0_price
+    } else {
+        1. / token0_price
+    }
+}
+
+pub async fn uniswap_v3_twap_price<T: web3::Transport>(
+    eth: web3::api::Eth<T>,
+    params: &UniswapV3TwapParams,
+) -> web3::contract::Result<f64> {
+    let tick = uniswap_v3_average_tick(eth, params.pool, params.twap_interval_secs).await?;
+    Ok(uniswap_v3_tick_to_price(
+        tick,
+        params.base_is_token0,
+        params.token0_decimals,
+        params.token1_decimals,
+    ))
+}
+
+pub async fn uniswap_v3_twap(
+    eth_client: &EthClient,
+    params: &UniswapV3TwapParams,
+) -> web3::contract::Result<f64> {
+    uniswap_v3_twap_price(eth_client.eth(), params).await
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::price_feeds::{
+        chainlink_latest_round_data, chainlink_round_price, uniswap_v3_average_tick,
+        uniswap_v3_tick_to_price, uniswap_v3_twap_price, ChainlinkRoundData, UniswapV3TwapParams,
+    };
+    use crate::test_utils::{abi_encoded, RecordedTransport};
+    use web3::api::Namespace;
+    use web3::ethabi::Token;
+    use web3::types::{Address, U256};
+
+    const EPS: f64 = 0.0001;
+
+    fn int_token(value: i128) -> Token {
+        let mut data = U256::from(value.unsigned_abs());
+        if value < 0 {
+            data = (!data).overflowing_add(U256::one()).0;
+        }
+        Token::Int(data)
+    }
+
+    fn round(answer: i128, updated_at: u64) -> ChainlinkRoundData {
+        ChainlinkRoundData {
+            round_id: 10.into(),
+            answer,
+            started_at: updated_at.into(),
+            updated_at,
+            answered_in_round: 10.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn chainlink_latest_round_data_test() {
+        let transport = RecordedTransport::default();
+        transport.push_response("eth_call", abi_encoded(&[Token::Uint(8.into())]));
+        transport.push_response(
+            "eth_call",
+            abi_encoded(&[
+                Token::Uint(18446744073709562301u128.into()),
+                int_token(185_012_345_678),
+                Token::Uint(1_690_000_000.into()),
+                Token::Uint(1_690_000_012.into()),
+                Token::Uint(18446744073709562301u128.into()),
+            ]),
+        );
+
+        let (round_data, decimals) = chainlink_latest_round_data(
+            web3::api::Eth::new(transport.clone()),
+            Address::repeat_byte(1),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(decimals, 8);
+        assert_eq!(round_data.answer, 185_012_345_678);
+        assert_eq!(round_data.updated_at, 1_690_000_012);
+        assert_eq!(transport.requests_count("eth_call"), 2);
+
+        let price = chainlink_round_price(&round_data, decimals, None, 0).unwrap();
+        assert!((price - 1850.12345678).abs() < EPS);
+    }
+
+    #[test]
+    fn chainlink_round_price_rejects_invalid_rounds() {
+        assert!(chainlink_round_price(&round(0, 100), 8, None, 100).is_err());
+        assert!(chainlink_round_price(&round(-1, 100), 8, None, 100).is_err());
+        assert!(chainlink_round_price(&round(100_000_000, 100), 8, Some(60), 161).is_err());
+        assert!(chainlink_round_price(&round(100_000_000, 100), 8, Some(60), 160).is_ok());
+
+        let mut incomplete_round = round(100_000_000, 100);
+        incomplete_round.answered_in_round = 9.into();
+        assert!(chainlink_round_price(&incomplete_round, 8, None, 100).is_err());
+    }
+
+    #[tokio::test]
+    async fn uniswap_v3_average_tick_test() {
+        let transport = RecordedTransport::default();
+        transport.push_response(
+            "eth_call",
+            abi_encoded(&[
+                Token::Array(vec![int_token(-1_000_000), int_token(-1_600_000)]),
+                Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]),
+            ]),
+        );
+
+        let tick =
+            uniswap_v3_average_tick(web3::api::Eth::new(transport), Address::repeat_byte(2), 600)
+                .await
+                .unwrap();
+
+        assert!((tick + 1000.).abs() < EPS);
+    }
+
+    #[test]
+    fn uniswap_v3_tick_to_price_test() {
+        assert!((uniswap_v3_tick_to_price(0., true, 18, 18) - 1.).abs() < EPS);
+        assert!((uniswap_v3_tick_to_price(0., true, 6, 6) - 1.).abs() < EPS);
+
+        // USDC(token0, 6 decimals) / WETH(token1, 18 decimals) pool with ETH at ~2000 USDC
+        let tick = 200311.;
+        let eth_in_usdc = uniswap_v3_tick_to_price(tick, false, 6, 18);
+        assert!(eth_in_usdc > 1999. && eth_in_usdc < 2001.);
+
+        let usdc_in_eth = uniswap_v3_tick_to_price(tick, true, 6, 18);
+        assert!((usdc_in_eth * eth_in_usdc - 1.).abs() < EPS);
+    }
+
+    #[tokio::test]
+    async fn uniswap_v3_twap_price_test() {
+        let transport = RecordedTransport::default();
+        transport.push_response(
+            "eth_call",
+            abi_encoded(&[
+                Token::Array(vec![int_token(0), int_token(200311 * 1800)]),
+                Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]),
+            ]),
+        );
+
+        let price = uniswap_v3_twap_price(
+            web3::api::Eth::new(transport),
+            &UniswapV3TwapParams {
+                pool: Address::repeat_byte(3),
+                twap_interval_secs: 1800,
+                base_is_token0: false,
+                token0_decimals: 6,
+                token1_decimals: 18,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(price > 1999. && price < 2001.);
+    }
+
+    #[tokio::test]
+    async fn uniswap_v3_average_tick_zero_interval_test() {
+        let transport = RecordedTransport::default();
+        assert!(uniswap_v3_average_tick(
+            web3::api::Eth::new(transport),
+            Address::repeat_byte(2),
+            0
+        )
+        .await
+        .is_err());
+    }
+}
@@ -0,0 +1,905 @@
+//! Ethereum JSON-RPC provider with several endpoints
+//!
+//! * a request goes to the healthiest endpoint and fails over to the next one on transport errors
+//!   and rate limits
+//! * transactions are sent to the same endpoint while it works, so the pending nonce stays consistent;
+//!   a transaction fails over only if it surely didn't reach the node, after a timeout the provider
+//!   looks for the transaction hash instead of sending it again
+//! * [`QuorumTransport`] sends a request to all the endpoints and returns the response only if
+//!   `quorum` of them agree on it
+//! * JSON-RPC batches are sent to one endpoint in a single HTTP request and fail over as a whole
+
+use crate::methods::new_eth_rpc_client;
+use jsonrpc_core::{Call, ErrorCode, Params, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use web3::error::TransportError;
+use web3::futures::future::{join_all, BoxFuture, FutureExt};
+use web3::signing::keccak256;
+use web3::types::H256;
+use web3::{BatchTransport, RequestId, Transport};
+
+// The pending nonce and the sent transactions are known only to the node which received them
+const STICKY_METHODS: &[&str] = &[
+    "eth_sendRawTransaction",
+    "eth_sendTransaction",
+    "eth_getTransactionCount",
+];
+
+// The node can accept the transaction even if the response is lost
+const SEND_TRANSACTION_METHODS: &[&str] = &["eth_sendRawTransaction", "eth_sendTransaction"];
+
+// The message of the HTTP transport error when the connection to the node isn't established
+const CONNECT_ERROR_MESSAGE: &str = "error trying to connect";
+
+const RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(30);
+const MAX_FAILURE_COOLDOWN: Duration = Duration::from_secs(60);
+const SCORE_DECAY: f64 = 0.8;
+const TOO_MANY_REQUESTS_STATUS: u16 = 429;
+// "Limit exceeded" JSON-RPC error returned by Infura and Alchemy on rate limiting
+const LIMIT_EXCEEDED_ERROR_CODE: i64 = -32005;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointHealth {
+    /// Exponentially weighted success rate from 0 to 1
+    pub score: f64,
+    pub consecutive_failures: u32,
+    /// The endpoint is used only as the last resort until this moment
+    pub cooldown_until: Option<Instant>,
+}
+
+impl Default for EndpointHealth {
+    fn default() -> Self {
+        EndpointHealth {
+            score: 1.,
+            consecutive_failures: 0,
+            cooldown_until: None,
+        }
+    }
+}
+
+impl EndpointHealth {
+    pub fn is_available(&self, now: Instant) -> bool {
+        self.cooldown_until.map_or(true, |until| until <= now)
+    }
+
+    fn record_success(&mut self) {
+        self.score = self.score * SCORE_DECAY + (1. - SCORE_DECAY);
+        self.consecutive_failures = 0;
+        self.cooldown_until = None;
+    }
+
+    fn record_failure(&mut self, failure: Failure, now: Instant) {
+        self.score *= SCORE_DECAY;
+        self.consecutive_failures += 1;
+
+        let cooldown = match failure {
+            Failure::RateLimited => RATE_LIMIT_COOLDOWN,
+            Failure::Unavailable => std::cmp::min(
+                Duration::from_secs(1 << std::cmp::min(self.consecutive_failures - 1, 6)),
+                MAX_FAILURE_COOLDOWN,
+            ),
+        };
+        self.cooldown_until = Some(now + cooldown);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    RateLimited,
+    Unavailable,
+}
+
+/// The errors after which the request is retried on another endpoint,
+/// `None` if the endpoint is healthy and the error is the response to the request
+pub fn classify_error(error: &web3::Error) -> Option<Failure> {
+    match error {
+        web3::Error::Transport(TransportError::Code(TOO_MANY_REQUESTS_STATUS)) => {
+            Some(Failure::RateLimited)
+        }
+        web3::Error::Rpc(rpc_error)
+            if rpc_error.code == ErrorCode::ServerError(LIMIT_EXCEEDED_ERROR_CODE) =>
+        {
+            Some(Failure::RateLimited)
+        }
+        web3::Error::Transport(_)
+        | web3::Error::Unreachable
+        | web3::Error::InvalidResponse(_)
+        | web3::Error::Decoder(_) => Some(Failure::Unavailable),
+        _ => None,
+    }
+}
+
+/// The request surely didn't reach the node: the connection isn't established or the request is
+/// rate limited. After any other error a sent transaction can be accepted by the node.
+pub fn is_undelivered(error: &web3::Error) -> bool {
+    match error {
+        web3::Error::Transport(TransportError::Message(message)) => {
+            message.contains(CONNECT_ERROR_MESSAGE)
+        }
+        error => classify_error(error) == Some(Failure::RateLimited),
+    }
+}
+
+/// The order in which the endpoints are tried: the sticky one, the available ones from
+/// the best score and the ones in cooldown from the earliest end of the cooldown
+pub fn endpoints_order(
+    healths: &[EndpointHealth],
+    sticky: Option<usize>,
+    now: Instant,
+) -> Vec<usize> {
+    let (mut available, mut cooling_down): (Vec<usize>, Vec<usize>) =
+        (0..healths.len()).partition(|index| healths[*index].is_available(now));
+
+    available.sort_by(|a, b| healths[*b].score.total_cmp(&healths[*a].score));
+    cooling_down.sort_by_key(|index| healths[*index].cooldown_until);
+
+    if let Some(sticky) = sticky {
+        if let Some(position) = available.iter().position(|index| *index == sticky) {
+            available.remove(position);
+            available.insert(0, sticky);
+        }
+    }
+
+    available.into_iter().chain(cooling_down).collect()
+}
+
+type BatchResult = web3::Result<Vec<web3::Result<Value>>>;
+
+/// A batch fails over if the whole request fails or all the calls are rate limited
+fn classify_batch_result(result: &BatchResult) -> Option<Failure> {
+    match result {
+        Err(error) => classify_error(error),
+        Ok(outputs) if !outputs.is_empty() => outputs
+            .iter()
+            .map(|output| output.as_ref().err().and_then(classify_error))
+            .reduce(|a, b| if a == b { a } else { None })
+            .flatten()
+            .filter(|failure| *failure == Failure::RateLimited),
+        Ok(_) => None,
+    }
+}
+
+fn is_sticky(request: &Call) -> bool {
+    method_name(request).map_or(false, |method| STICKY_METHODS.contains(&method))
+}
+
+fn is_send_transaction(request: &Call) -> bool {
+    method_name(request).map_or(false, |method| SEND_TRANSACTION_METHODS.contains(&method))
+}
+
+/// The hash of the transaction sent by `eth_sendRawTransaction`
+fn raw_transaction_hash(request: &Call) -> Option<H256> {
+    match request {
+        Call::MethodCall(call) if call.method == "eth_sendRawTransaction" => match &call.params {
+            Params::Array(params) => {
+                let raw_transaction = params.first()?.as_str()?;
+                let raw_transaction = hex::decode(raw_transaction.trim_start_matches("0x")).ok()?;
+                Some(H256::from(keccak256(&raw_transaction)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn method_name(request: &Call) -> Option<&str> {
+    match request {
+        Call::MethodCall(call) => Some(call.method.as_str()),
+        Call::Notification(notification) => Some(notification.method.as_str()),
+        Call::Invalid { .. } => None,
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint<T> {
+    url: String,
+    transport: T,
+}
+
+#[derive(Debug)]
+struct ProviderState {
+    healths: Vec<EndpointHealth>,
+    sticky: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    endpoints: Vec<Endpoint<T>>,
+    state: Mutex<ProviderState>,
+    next_id: AtomicUsize,
+}
+
+/// JSON-RPC transport over several endpoints with health scoring and failover
+#[derive(Debug)]
+pub struct RpcProvider<T = web3::transports::Http> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for RpcProvider<T> {
+    fn clone(&self) -> Self {
+        RpcProvider {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl RpcProvider<web3::transports::Http> {
+    pub fn new(urls: &[reqwest::Url], rpc_timeout_secs: u64) -> web3::Result<Self> {
+        let client = new_eth_rpc_client(Some(Duration::from_secs(rpc_timeout_secs)))?;
+        Self::from_transports(
+            urls.iter()
+                .map(|url| {
+                    (
+                        url.to_string(),
+                        web3::transports::Http::with_client(client.clone(), url.clone()),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<T: Transport> RpcProvider<T> {
+    pub fn from_transports(endpoints: Vec<(String, T)>) -> web3::Result<Self> {
+        if endpoints.is_empty() {
+            return Err(web3::Error::Transport(TransportError::Message(
+                "No Ethereum RPC endpoints".to_string(),
+            )));
+        }
+
+        let healths = vec![EndpointHealth::default(); endpoints.len()];
+        Ok(RpcProvider {
+            inner: Arc::new(Inner {
+                endpoints: endpoints
+                    .into_iter()
+                    .map(|(url, transport)| Endpoint { url, transport })
+                    .collect(),
+                state: Mutex::new(ProviderState {
+                    healths,
+                    sticky: None,
+                }),
+                next_id: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    /// The endpoint which would be used for the next request
+    pub fn best_url(&self) -> String {
+        let order = self.order(false);
+        self.inner.endpoints[order[0]].url.clone()
+    }
+
+    pub fn health(&self) -> Vec<(String, EndpointHealth)> {
+        let state = self.inner.state.lock().unwrap();
+        self.inner
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.url.clone())
+            .zip(state.healths.iter().cloned())
+            .collect()
+    }
+
+    pub fn endpoints_count(&self) -> usize {
+        self.inner.endpoints.len()
+    }
+
+    pub fn with_quorum(&self, quorum: usize) -> QuorumTransport<T> {
+        QuorumTransport {
+            provider: self.clone(),
+            quorum,
+        }
+    }
+
+    fn order(&self, sticky: bool) -> Vec<usize> {
+        let state = self.inner.state.lock().unwrap();
+        endpoints_order(
+            &state.healths,
+            if sticky { state.sticky } else { None },
+            Instant::now(),
+        )
+    }
+
+    fn record_result(&self, index: usize, failure: Option<Failure>, sticky: bool) {
+        let mut state = self.inner.state.lock().unwrap();
+        match failure {
+            Some(failure) => {
+                state.healths[index].record_failure(failure, Instant::now());
+                if sticky && state.sticky == Some(index) {
+                    state.sticky = None;
+                }
+            }
+            None => {
+                state.healths[index].record_success();
+                if sticky {
+                    state.sticky = Some(index);
+                }
+            }
+        }
+    }
+
+    async fn send_with_failover(&self, id: RequestId, request: Call) -> web3::Result<Value> {
+        let sticky = is_sticky(&request);
+        let send_transaction = is_send_transaction(&request);
+
+        let mut last_error = web3::Error::Unreachable;
+        for index in self.order(sticky) {
+            let endpoint = &self.inner.endpoints[index];
+            let result = endpoint.transport.send(id, request.clone()).await;
+            let failure = result.as_ref().err().and_then(classify_error);
+            self.record_result(index, failure, sticky);
+
+            match result {
+                // The next endpoint would reject the accepted transaction with `nonce too low`
+                // or `already known`
+                Err(error) if failure.is_some() && send_transaction && !is_undelivered(&error) => {
+                    tracing::warn!(
+                        "Ethereum RPC endpoint {} failed to send the transaction: {:?}, look for it",
+                        endpoint.url,
+                        error
+                    );
+                    return match self.find_transaction(index, &request).await {
+                        Some(tx_hash) => {
+                            tracing::info!("Transaction {:?} is found after the error", tx_hash);
+                            Ok(serde_json::json!(tx_hash))
+                        }
+                        None => Err(error),
+                    };
+                }
+                Err(error) if failure.is_some() => {
+                    tracing::warn!(
+                        "Ethereum RPC endpoint {} failed: {:?}, try the next one",
+                        endpoint.url,
+                        error
+                    );
+                    last_error = error;
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Looks for the transaction of the `eth_sendRawTransaction` request starting from the endpoint
+    /// which received it
+    async fn find_transaction(&self, first: usize, request: &Call) -> Option<H256> {
+        let tx_hash = raw_transaction_hash(request)?;
+
+        let mut order = self.order(false);
+        order.retain(|index| *index != first);
+        order.insert(0, first);
+
+        for index in order {
+            let transport = &self.inner.endpoints[index].transport;
+            let (id, call) =
+                transport.prepare("eth_getTransactionByHash", vec![serde_json::json!(tx_hash)]);
+            match transport.send(id, call).await {
+                Ok(Value::Null) | Err(_) => continue,
+                Ok(_) => return Some(tx_hash),
+            }
+        }
+        None
+    }
+}
+
+impl<T> RpcProvider<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Batch: Send,
+{
+    async fn send_batch_with_failover(&self, requests: Vec<(RequestId, Call)>) -> BatchResult {
+        let sticky = requests.iter().any(|(_, request)| is_sticky(request));
+        let send_transaction = requests
+            .iter()
+            .any(|(_, request)| is_send_transaction(request));
+
+        let mut last_error = web3::Error::Unreachable;
+        for index in self.order(sticky) {
+            let endpoint = &self.inner.endpoints[index];
+            let result = endpoint.transport.send_batch(requests.clone()).await;
+            let failure = classify_batch_result(&result);
+            self.record_result(index, failure, sticky);
+
+            match result {
+                Ok(_) if failure.is_some() => {
+                    tracing::warn!(
+                        "Ethereum RPC endpoint {} rate limited the batch of {} requests, try the next one",
+                        endpoint.url,
+                        requests.len()
+                    );
+                    last_error =
+                        web3::Error::Transport(TransportError::Code(TOO_MANY_REQUESTS_STATUS));
+                }
+                Err(error) if failure.is_some() && send_transaction && !is_undelivered(&error) => {
+                    return Err(error);
+                }
+                Err(error) if failure.is_some() => {
+                    tracing::warn!(
+                        "Ethereum RPC endpoint {} failed the batch of {} requests: {:?}, try the next one",
+                        endpoint.url,
+                        requests.len(),
+                        error
+                    );
+                    last_error = error;
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+impl<T> Transport for RpcProvider<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    type Out = BoxFuture<'static, web3::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.inner.next_id.fetch_add(1, Ordering::AcqRel);
+        (id, web3::helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let provider = self.clone();
+        async move { provider.send_with_failover(id, request).await }.boxed()
+    }
+}
+
+impl<T> BatchTransport for RpcProvider<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send,
+    T::Batch: Send,
+{
+    type Batch = BoxFuture<'static, BatchResult>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let provider = self.clone();
+        let requests: Vec<(RequestId, Call)> = requests.into_iter().collect();
+        async move { provider.send_batch_with_failover(requests).await }.boxed()
+    }
+}
+
+/// Transport which sends every request to all the endpoints of the provider
+/// and requires `quorum` identical responses
+#[derive(Debug)]
+pub struct QuorumTransport<T = web3::transports::Http> {
+    provider: RpcProvider<T>,
+    quorum: usize,
+}
+
+impl<T> Clone for QuorumTransport<T> {
+    fn clone(&self) -> Self {
+        QuorumTransport {
+            provider: self.provider.clone(),
+            quorum: self.quorum,
+        }
+    }
+}
+
+impl<T> QuorumTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    async fn send_to_all(&self, id: RequestId, request: Call) -> web3::Result<Value> {
+        let endpoints = &self.provider.inner.endpoints;
+        let results = join_all(
+            endpoints
+                .iter()
+                .map(|endpoint| endpoint.transport.send(id, request.clone())),
+        )
+        .await;
+
+        let mut values = vec![];
+        for (index, result) in results.into_iter().enumerate() {
+            let failure = result.as_ref().err().and_then(classify_error);
+            self.provider.record_result(index, failure, false);
+
+            if let Ok(value) = result {
+                values.push(value);
+            }
+        }
+
+        self.quorum_response(values, &request)
+    }
+
+    fn quorum_response(&self, values: Vec<Value>, request: &Call) -> web3::Result<Value> {
+        let mut responses: Vec<(Value, usize)> = vec![];
+        for value in values {
+            match responses
+                .iter_mut()
+                .find(|(response, _)| *response == value)
+            {
+                Some((_, count)) => *count += 1,
+                None => responses.push((value, 1)),
+            }
+        }
+
+        responses
+            .into_iter()
+            .find(|(_, count)| *count >= self.quorum)
+            .map(|(value, _)| value)
+            .ok_or_else(|| {
+                web3::Error::Transport(TransportError::Message(format!(
+                    "No quorum of {} out of {} Ethereum RPC endpoints for {}",
+                    self.quorum,
+                    self.provider.endpoints_count(),
+                    method_name(request).unwrap_or_default()
+                )))
+            })
+    }
+}
+
+impl<T> QuorumTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send,
+    T::Batch: Send,
+{
+    /// Every call of the batch requires the quorum separately
+    async fn send_batch_to_all(&self, requests: Vec<(RequestId, Call)>) -> BatchResult {
+        let endpoints = &self.provider.inner.endpoints;
+        let results = join_all(
+            endpoints
+                .iter()
+                .map(|endpoint| endpoint.transport.send_batch(requests.clone())),
+        )
+        .await;
+
+        let mut batches = vec![];
+        let mut last_error = web3::Error::Unreachable;
+        for (index, result) in results.into_iter().enumerate() {
+            self.provider
+                .record_result(index, classify_batch_result(&result), false);
+
+            match result {
+                Ok(outputs) if outputs.len() == requests.len() => batches.push(outputs),
+                Ok(_) => {
+                    last_error = web3::Error::InvalidResponse("Batch size mismatch".to_string())
+                }
+                Err(error) => last_error = error,
+            }
+        }
+
+        if batches.len() < self.quorum {
+            return Err(last_error);
+        }
+
+        Ok(requests
+            .iter()
+            .enumerate()
+            .map(|(item, (_, request))| {
+                let values = batches
+                    .iter()
+                    .filter_map(|outputs| outputs[item].as_ref().ok().cloned())
+                    .collect();
+                self.quorum_response(values, request)
+            })
+            .collect())
+    }
+}
+
+impl<T> Transport for QuorumTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    type Out = BoxFuture<'static, web3::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.provider.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let transport = self.clone();
+        async move { transport.send_to_all(id, request).await }.boxed()
+    }
+}
+
+impl<T> BatchTransport for QuorumTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send,
+    T::Batch: Send,
+{
+    type Batch = BoxFuture<'static, BatchResult>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let transport = self.clone();
+        let requests: Vec<(RequestId, Call)> = requests.into_iter().collect();
+        async move { transport.send_batch_to_all(requests).await }.boxed()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::provider::{
+        classify_batch_result, classify_error, endpoints_order, is_undelivered, EndpointHealth,
+        Failure, RpcProvider,
+    };
+    use crate::test_utils::RecordedTransport;
+    use jsonrpc_core::Value;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+    use web3::api::Namespace;
+    use web3::error::TransportError;
+    use web3::signing::keccak256;
+    use web3::types::H256;
+    use web3::Transport;
+
+    fn provider(transports: &[RecordedTransport]) -> RpcProvider<RecordedTransport> {
+        RpcProvider::from_transports(
+            transports
+                .iter()
+                .enumerate()
+                .map(|(index, transport)| (format!("endpoint-{}", index), transport.clone()))
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn classify_error_test() {
+        assert_eq!(
+            classify_error(&web3::Error::Transport(TransportError::Code(429))),
+            Some(Failure::RateLimited)
+        );
+        assert_eq!(
+            classify_error(&web3::Error::Transport(TransportError::Code(502))),
+            Some(Failure::Unavailable)
+        );
+
+        let mut rpc_error = jsonrpc_core::Error::new(jsonrpc_core::ErrorCode::ServerError(-32005));
+        assert_eq!(
+            classify_error(&web3::Error::Rpc(rpc_error.clone())),
+            Some(Failure::RateLimited)
+        );
+        rpc_error.code = jsonrpc_core::ErrorCode::ServerError(-32000);
+        assert_eq!(classify_error(&web3::Error::Rpc(rpc_error)), None);
+    }
+
+    #[test]
+    fn endpoints_order_test() {
+        let now = Instant::now();
+        let health = |score: f64, cooldown_secs: Option<u64>| EndpointHealth {
+            score,
+            consecutive_failures: 0,
+            cooldown_until: cooldown_secs.map(|secs| now + Duration::from_secs(secs)),
+        };
+        let healths = vec![
+            health(0.5, None),
+            health(1., Some(20)),
+            health(0.9, None),
+            health(1., Some(10)),
+            health(0.5, None),
+        ];
+
+        assert_eq!(endpoints_order(&healths, None, now), vec![2, 0, 4, 3, 1]);
+        assert_eq!(endpoints_order(&healths, Some(4), now), vec![4, 2, 0, 3, 1]);
+        // The sticky endpoint in cooldown isn't preferred
+        assert_eq!(endpoints_order(&healths, Some(1), now), vec![2, 0, 4, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn failover_test() {
+        let endpoints = [RecordedTransport::default(), RecordedTransport::default()];
+        endpoints[1].push_response("eth_blockNumber", json!("0x10"));
+        endpoints[1].push_response("eth_blockNumber", json!("0x11"));
+        let provider = provider(&endpoints);
+
+        let eth = web3::api::Eth::new(provider.clone());
+        assert_eq!(eth.block_number().await.unwrap().as_u64(), 0x10);
+        assert_eq!(endpoints[0].requests_count("eth_blockNumber"), 1);
+
+        // The failed endpoint is in cooldown
+        assert_eq!(provider.best_url(), "endpoint-1");
+        assert_eq!(eth.block_number().await.unwrap().as_u64(), 0x11);
+        assert_eq!(endpoints[0].requests_count("eth_blockNumber"), 1);
+
+        let health = provider.health();
+        assert_eq!(health[0].1.consecutive_failures, 1);
+        assert!(health[0].1.score < health[1].1.score);
+
+        // No endpoint has the response, the error of the last one is returned
+        assert!(matches!(
+            eth.block_number().await,
+            Err(web3::Error::Transport(TransportError::Message(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sticky_send_test() {
+        let endpoints = [RecordedTransport::default(), RecordedTransport::default()];
+        endpoints[0].push_response("eth_getTransactionCount", json!("0x1"));
+        endpoints[1].push_response("eth_sendRawTransaction", json!(format!("0x{:064x}", 1)));
+        endpoints[1].push_response("eth_sendRawTransaction", json!(format!("0x{:064x}", 2)));
+        endpoints[1].push_response("eth_blockNumber", json!("0x10"));
+        let provider = provider(&endpoints);
+
+        let (id, request) = provider.prepare("eth_sendRawTransaction", vec![json!("0x00")]);
+        provider.send(id, request).await.unwrap();
+        assert_eq!(endpoints[0].requests_count("eth_sendRawTransaction"), 1);
+
+        // The first endpoint recovers, but the transactions are still sent to the second one
+        endpoints[0].push_response("eth_sendRawTransaction", json!(format!("0x{:064x}", 3)));
+        provider.inner.state.lock().unwrap().healths[0] = EndpointHealth::default();
+
+        let (id, request) = provider.prepare("eth_sendRawTransaction", vec![json!("0x01")]);
+        assert_eq!(
+            provider.send(id, request).await.unwrap(),
+            json!(format!("0x{:064x}", 2))
+        );
+        assert_eq!(endpoints[0].requests_count("eth_sendRawTransaction"), 1);
+
+        // The reads go to the healthiest endpoint
+        let (id, request) = provider.prepare("eth_blockNumber", vec![]);
+        assert!(provider.send(id, request).await.is_ok());
+    }
+
+    #[test]
+    fn is_undelivered_test() {
+        let message =
+            |message: &str| web3::Error::Transport(TransportError::Message(message.to_string()));
+
+        assert!(is_undelivered(&web3::Error::Transport(
+            TransportError::Code(429)
+        )));
+        assert!(is_undelivered(&message(
+            "failed to send request: error sending request for url (http://127.0.0.1:8545/): \
+             error trying to connect: tcp connect error: Connection refused (os error 111)"
+        )));
+        assert!(!is_undelivered(&message(
+            "failed to send request: error sending request for url (http://127.0.0.1:8545/): \
+             operation timed out"
+        )));
+        assert!(!is_undelivered(&web3::Error::Transport(
+            TransportError::Code(502)
+        )));
+    }
+
+    #[tokio::test]
+    async fn send_transaction_timeout_test() {
+        let endpoints = [RecordedTransport::default(), RecordedTransport::default()];
+        let provider = provider(&endpoints);
+        let timeout = || {
+            web3::Error::Transport(TransportError::Message(
+                "failed to send request: operation timed out".to_string(),
+            ))
+        };
+        let tx_hash = H256::from(keccak256(&[0x00]));
+
+        // The transaction is accepted by the node which didn't respond
+        endpoints[0].push_error("eth_sendRawTransaction", timeout());
+        endpoints[0].push_response("eth_getTransactionByHash", json!({ "hash": tx_hash }));
+        let (id, request) = provider.prepare("eth_sendRawTransaction", vec![json!("0x00")]);
+        assert_eq!(provider.send(id, request).await.unwrap(), json!(tx_hash));
+        assert_eq!(endpoints[1].requests_count("eth_sendRawTransaction"), 0);
+
+        // The transaction isn't known to any node, it isn't sent again
+        provider.inner.state.lock().unwrap().healths[0] = EndpointHealth::default();
+        endpoints[0].push_error("eth_sendRawTransaction", timeout());
+        endpoints[0].push_response("eth_getTransactionByHash", Value::Null);
+        endpoints[1].push_response("eth_getTransactionByHash", Value::Null);
+        let (id, request) = provider.prepare("eth_sendRawTransaction", vec![json!("0x00")]);
+        assert!(matches!(
+            provider.send(id, request).await,
+            Err(web3::Error::Transport(TransportError::Message(_)))
+        ));
+        assert_eq!(endpoints[1].requests_count("eth_getTransactionByHash"), 1);
+        assert_eq!(endpoints[1].requests_count("eth_sendRawTransaction"), 0);
+
+        // The rate limited transaction fails over
+        provider.inner.state.lock().unwrap().healths[0] = EndpointHealth::default();
+        endpoints[0].push_error(
+            "eth_sendRawTransaction",
+            web3::Error::Transport(TransportError::Code(429)),
+        );
+        endpoints[1].push_response("eth_sendRawTransaction", json!(tx_hash));
+        let (id, request) = provider.prepare("eth_sendRawTransaction", vec![json!("0x00")]);
+        assert_eq!(provider.send(id, request).await.unwrap(), json!(tx_hash));
+    }
+
+    #[tokio::test]
+    async fn quorum_test() {
+        let endpoints = [
+            RecordedTransport::default(),
+            RecordedTransport::default(),
+            RecordedTransport::default(),
+        ];
+        endpoints[0].push_response("eth_blockNumber", json!("0x10"));
+        endpoints[1].push_response("eth_blockNumber", json!("0x11"));
+        endpoints[2].push_response("eth_blockNumber", json!("0x10"));
+        let provider = provider(&endpoints);
+
+        let eth = web3::api::Eth::new(provider.with_quorum(2));
+        assert_eq!(eth.block_number().await.unwrap().as_u64(), 0x10);
+
+        endpoints[0].push_response("eth_blockNumber", json!("0x10"));
+        endpoints[1].push_response("eth_blockNumber", json!("0x11"));
+        assert!(eth.block_number().await.is_err());
+    }
+
+    #[test]
+    fn classify_batch_result_test() {
+        let rate_limited = || {
+            Err(web3::Error::Rpc(jsonrpc_core::Error::new(
+                jsonrpc_core::ErrorCode::ServerError(-32005),
+            )))
+        };
+
+        assert_eq!(
+            classify_batch_result(&Err(web3::Error::Transport(TransportError::Code(502)))),
+            Some(Failure::Unavailable)
+        );
+        assert_eq!(
+            classify_batch_result(&Ok(vec![rate_limited(), rate_limited()])),
+            Some(Failure::RateLimited)
+        );
+        assert_eq!(
+            classify_batch_result(&Ok(vec![rate_limited(), Ok(json!("0x1"))])),
+            None
+        );
+        assert_eq!(classify_batch_result(&Ok(vec![])), None);
+    }
+
+    #[tokio::test]
+    async fn batch_test() {
+        let endpoints = [RecordedTransport::default(), RecordedTransport::default()];
+        endpoints[0].push_response("eth_blockNumber", json!("0x10"));
+        endpoints[0].push_response("eth_chainId", json!("0x5"));
+        let provider = provider(&endpoints);
+
+        let batch = web3::transports::Batch::new(provider);
+        let eth = web3::api::Eth::new(batch.clone());
+        let block_number = eth.block_number();
+        let chain_id = eth.chain_id();
+        let gas_price = eth.gas_price();
+        batch.submit_batch().await.unwrap();
+
+        assert_eq!(block_number.await.unwrap().as_u64(), 0x10);
+        assert_eq!(chain_id.await.unwrap().as_u64(), 5);
+        // The error of one call doesn't fail the batch
+        assert!(gas_price.await.is_err());
+        assert_eq!(endpoints[0].batches_count(), 1);
+        assert_eq!(endpoints[1].batches_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn quorum_batch_test() {
+        let endpoints = [
+            RecordedTransport::default(),
+            RecordedTransport::default(),
+            RecordedTransport::default(),
+        ];
+        endpoints[0].push_response("eth_blockNumber", json!("0x10"));
+        endpoints[0].push_response("eth_chainId", json!("0x5"));
+        endpoints[1].push_response("eth_blockNumber", json!("0x11"));
+        endpoints[1].push_response("eth_chainId", json!("0x5"));
+        endpoints[2].push_response("eth_blockNumber", json!("0x12"));
+        let provider = provider(&endpoints);
+
+        let batch = web3::transports::Batch::new(provider.with_quorum(2));
+        let eth = web3::api::Eth::new(batch.clone());
+        let block_number = eth.block_number();
+        let chain_id = eth.chain_id();
+        batch.submit_batch().await.unwrap();
+
+        assert!(block_number.await.is_err());
+        assert_eq!(chain_id.await.unwrap().as_u64(), 5);
+        assert!(endpoints
+            .iter()
+            .all(|endpoint| endpoint.batches_count() == 1));
+    }
+}
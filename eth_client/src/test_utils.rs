@@ -1,7 +1,11 @@
-use crate::methods::get_contract_abi;
+use crate::fast_bridge::FAST_BRIDGE_ABI;
+use crate::methods::{get_contract_abi, EthClient, RetryPolicy};
 use dotenv::dotenv;
+use jsonrpc_core::{Call, Value};
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use url::Url;
 use web3::types::Address;
 
@@ -26,9 +30,8 @@ pub fn get_eth_erc20_fast_bridge_impl_address() -> web3::types::Address {
     )
 }
 
-pub async fn get_eth_erc20_fast_bridge_contract_abi() -> String {
-    let eth_bridge_impl_address = get_eth_erc20_fast_bridge_impl_address();
-    get_eth_contract_abi(eth_bridge_impl_address).await
+pub fn get_eth_erc20_fast_bridge_contract_abi() -> String {
+    FAST_BRIDGE_ABI.to_string()
 }
 
 pub fn get_eth_erc20_fast_bridge_proxy_contract_address() -> web3::types::Address {
@@ -47,6 +50,14 @@ pub fn get_eth_rpc_url() -> Url {
     url::Url::parse(&format!("https://goerli.infura.io/v3/{}", &api_key_string)).unwrap()
 }
 
+pub fn get_eth_client() -> EthClient {
+    EthClient::new(&[get_eth_rpc_url()], 30, RetryPolicy::default()).unwrap()
+}
+
+pub fn get_rpc_client(url: &str) -> EthClient {
+    EthClient::new(&[Url::parse(url).unwrap()], 30, RetryPolicy::default()).unwrap()
+}
+
 pub fn get_eth_token() -> web3::types::Address {
     web3::types::Address::from_str(ETH_TOKEN_ADDRESS).unwrap()
 }
@@ -54,3 +65,111 @@ pub fn get_eth_token() -> web3::types::Address {
 pub fn get_recipient() -> web3::types::Address {
     web3::types::Address::from_str("2a23E0Fa3Afe77AFf5dc6c6a007E3A10c1450633").unwrap()
 }
+
+/// Transport which replays recorded JSON-RPC responses in the order they were pushed
+#[derive(Debug, Clone, Default)]
+pub struct RecordedTransport {
+    responses: Arc<Mutex<HashMap<String, VecDeque<web3::Result<Value>>>>>,
+    requests: Arc<Mutex<Vec<(String, Vec<Value>)>>>,
+    batches: Arc<Mutex<usize>>,
+}
+
+impl RecordedTransport {
+    pub fn push_response(&self, method: &str, response: Value) {
+        self.push_result(method, Ok(response));
+    }
+
+    pub fn push_error(&self, method: &str, error: web3::Error) {
+        self.push_result(method, Err(error));
+    }
+
+    fn push_result(&self, method: &str, result: web3::Result<Value>) {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push_back(result);
+    }
+
+    pub fn requests_count(&self, method: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(request_method, _)| request_method == method)
+            .count()
+    }
+
+    pub fn requests(&self) -> Vec<(String, Vec<Value>)> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn batches_count(&self) -> usize {
+        *self.batches.lock().unwrap()
+    }
+}
+
+impl web3::Transport for RecordedTransport {
+    type Out = web3::futures::future::Ready<web3::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (web3::RequestId, Call) {
+        let id = self.requests.lock().unwrap().len();
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method.to_string(), params.clone()));
+        (id, web3::helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, _id: web3::RequestId, request: Call) -> Self::Out {
+        let method = match request {
+            Call::MethodCall(call) => call.method,
+            _ => return web3::futures::future::ready(Err(web3::Error::Unreachable)),
+        };
+
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(&method)
+            .and_then(|responses| responses.pop_front())
+            .unwrap_or_else(|| {
+                // The endpoint without the response behaves as unreachable
+                Err(web3::Error::Transport(web3::error::TransportError::Message(
+                    format!(
+                        "failed to send request: error trying to connect: no recorded response for {}",
+                        method
+                    ),
+                )))
+            });
+        web3::futures::future::ready(response)
+    }
+}
+
+impl web3::BatchTransport for RecordedTransport {
+    type Batch = web3::futures::future::Ready<web3::Result<Vec<web3::Result<Value>>>>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (web3::RequestId, Call)>,
+    {
+        use web3::futures::FutureExt;
+        use web3::Transport;
+
+        *self.batches.lock().unwrap() += 1;
+        web3::futures::future::ready(Ok(requests
+            .into_iter()
+            .map(|(id, request)| {
+                self.send(id, request)
+                    .now_or_never()
+                    .expect("The recorded response is ready")
+            })
+            .collect()))
+    }
+}
+
+/// Encodes the tokens as the `eth_call` response
+pub fn abi_encoded(tokens: &[web3::ethabi::Token]) -> Value {
+    Value::String(format!("0x{}", hex::encode(web3::ethabi::encode(tokens))))
+}
@@ -0,0 +1,227 @@
+use near_jsonrpc_client::errors::{
+    JsonRpcError, JsonRpcServerError, JsonRpcTransportSendError, RpcTransportError,
+};
+use near_jsonrpc_primitives::types::blocks::RpcBlockError;
+use near_jsonrpc_primitives::types::gas_price::RpcGasPriceError;
+use near_jsonrpc_primitives::types::query::RpcQueryError;
+use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+use near_primitives::errors::{
+    ActionErrorKind, FunctionCallErrorSer, InvalidTxError, TxExecutionError,
+};
+use near_vm_errors::HostError;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum NearClientError {
+    #[error("NEAR RPC transport error: {0}")]
+    Transport(String),
+    #[error("NEAR RPC request timed out")]
+    Timeout,
+    #[error("NEAR RPC server error: {0}")]
+    Server(String),
+    #[error("NEAR RPC request failed: {0}")]
+    Rpc(String),
+    #[error("Invalid access key nonce {tx_nonce}, the current nonce is {ak_nonce}")]
+    InvalidNonce { tx_nonce: u64, ak_nonce: u64 },
+    #[error("The transaction is expired")]
+    Expired,
+    #[error("The transaction is not found")]
+    UnknownTransaction,
+    #[error("The contract panicked: {0}")]
+    ContractPanic(String),
+    #[error("The prepaid gas is exceeded")]
+    GasExceeded,
+    #[error("The transaction failed: {0}")]
+    ExecutionFailure(String),
+    #[error("Invalid account id: {0}")]
+    InvalidAccountId(String),
+    #[error("Unexpected NEAR RPC response: {0}")]
+    UnexpectedResponse(String),
+    #[error("Invalid signer: {0}")]
+    InvalidSigner(String),
+    #[error("No NEAR RPC endpoints")]
+    NoEndpoints,
+}
+
+impl NearClientError {
+    /// The request can succeed on another endpoint
+    pub fn is_retriable(&self) -> bool {
+        matches!(
+            self,
+            NearClientError::Transport(_) | NearClientError::Timeout | NearClientError::Server(_)
+        )
+    }
+
+    /// The transaction is rejected and can't be executed, a new one should be signed
+    pub fn is_rejected(&self) -> bool {
+        matches!(
+            self,
+            NearClientError::InvalidNonce { .. } | NearClientError::Expired
+        )
+    }
+}
+
+impl<E: Into<NearClientError>> From<JsonRpcError<E>> for NearClientError {
+    fn from(err: JsonRpcError<E>) -> Self {
+        match err {
+            JsonRpcError::TransportError(RpcTransportError::SendError(
+                JsonRpcTransportSendError::PayloadSendError(err),
+            )) if err.is_timeout() => NearClientError::Timeout,
+            JsonRpcError::TransportError(err) => NearClientError::Transport(err.to_string()),
+            JsonRpcError::ServerError(JsonRpcServerError::HandlerError(err)) => err.into(),
+            JsonRpcError::ServerError(JsonRpcServerError::RequestValidationError(err)) => {
+                NearClientError::Rpc(format!("{:?}", err))
+            }
+            JsonRpcError::ServerError(JsonRpcServerError::InternalError { info }) => {
+                NearClientError::Server(info.unwrap_or_else(|| "internal error".to_string()))
+            }
+            JsonRpcError::ServerError(JsonRpcServerError::NonContextualError(err)) => {
+                NearClientError::Server(format!("{:?}", err))
+            }
+            JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(err)) => {
+                NearClientError::Server(err.to_string())
+            }
+        }
+    }
+}
+
+impl From<RpcQueryError> for NearClientError {
+    fn from(err: RpcQueryError) -> Self {
+        match err {
+            RpcQueryError::ContractExecutionError { vm_error, .. } => {
+                NearClientError::ContractPanic(vm_error)
+            }
+            RpcQueryError::InternalError { .. }
+            | RpcQueryError::NoSyncedBlocks
+            | RpcQueryError::UnavailableShard { .. } => NearClientError::Server(err.to_string()),
+            err => NearClientError::Rpc(err.to_string()),
+        }
+    }
+}
+
+impl From<RpcBlockError> for NearClientError {
+    fn from(err: RpcBlockError) -> Self {
+        match err {
+            RpcBlockError::InternalError { .. } | RpcBlockError::NotSyncedYet => {
+                NearClientError::Server(err.to_string())
+            }
+            err => NearClientError::Rpc(err.to_string()),
+        }
+    }
+}
+
+impl From<RpcGasPriceError> for NearClientError {
+    fn from(err: RpcGasPriceError) -> Self {
+        match err {
+            RpcGasPriceError::InternalError { .. } => NearClientError::Server(err.to_string()),
+            err => NearClientError::Rpc(err.to_string()),
+        }
+    }
+}
+
+impl From<RpcTransactionError> for NearClientError {
+    fn from(err: RpcTransactionError) -> Self {
+        match err {
+            RpcTransactionError::InvalidTransaction {
+                context: InvalidTxError::InvalidNonce { tx_nonce, ak_nonce },
+            } => NearClientError::InvalidNonce { tx_nonce, ak_nonce },
+            RpcTransactionError::InvalidTransaction {
+                context: InvalidTxError::Expired,
+            } => NearClientError::Expired,
+            RpcTransactionError::TimeoutError => NearClientError::Timeout,
+            RpcTransactionError::UnknownTransaction { .. } => NearClientError::UnknownTransaction,
+            RpcTransactionError::InternalError { .. } | RpcTransactionError::DoesNotTrackShard => {
+                NearClientError::Server(err.to_string())
+            }
+            err => NearClientError::Rpc(err.to_string()),
+        }
+    }
+}
+
+impl From<TxExecutionError> for NearClientError {
+    fn from(err: TxExecutionError) -> Self {
+        match err {
+            TxExecutionError::ActionError(near_primitives::errors::ActionError {
+                kind:
+                    ActionErrorKind::FunctionCallError(FunctionCallErrorSer::ExecutionError(message)),
+                ..
+            }) => NearClientError::ContractPanic(message),
+            TxExecutionError::ActionError(near_primitives::errors::ActionError {
+                kind:
+                    ActionErrorKind::FunctionCallError(FunctionCallErrorSer::HostError(
+                        HostError::GasExceeded,
+                    )),
+                ..
+            }) => NearClientError::GasExceeded,
+            err => NearClientError::ExecutionFailure(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::errors::NearClientError;
+    use near_jsonrpc_primitives::types::transactions::RpcTransactionError;
+    use near_primitives::errors::{
+        ActionError, ActionErrorKind, FunctionCallErrorSer, InvalidTxError, TxExecutionError,
+    };
+    use near_vm_errors::HostError;
+
+    #[test]
+    fn transaction_error_test() {
+        let invalid_transaction = |context| RpcTransactionError::InvalidTransaction { context };
+
+        assert_eq!(
+            NearClientError::from(invalid_transaction(InvalidTxError::InvalidNonce {
+                tx_nonce: 5,
+                ak_nonce: 7
+            })),
+            NearClientError::InvalidNonce {
+                tx_nonce: 5,
+                ak_nonce: 7
+            }
+        );
+        assert_eq!(
+            NearClientError::from(invalid_transaction(InvalidTxError::Expired)),
+            NearClientError::Expired
+        );
+        assert_eq!(
+            NearClientError::from(RpcTransactionError::TimeoutError),
+            NearClientError::Timeout
+        );
+        assert!(NearClientError::from(RpcTransactionError::DoesNotTrackShard).is_retriable());
+        assert!(
+            !NearClientError::from(invalid_transaction(InvalidTxError::Expired)).is_retriable()
+        );
+    }
+
+    #[test]
+    fn execution_error_test() {
+        let action_error = |kind| {
+            TxExecutionError::ActionError(ActionError {
+                index: Some(0),
+                kind,
+            })
+        };
+
+        assert_eq!(
+            NearClientError::from(action_error(ActionErrorKind::FunctionCallError(
+                FunctionCallErrorSer::ExecutionError(
+                    "Smart contract panicked: Already unlocked".to_string()
+                )
+            ))),
+            NearClientError::ContractPanic("Smart contract panicked: Already unlocked".to_string())
+        );
+        assert_eq!(
+            NearClientError::from(action_error(ActionErrorKind::FunctionCallError(
+                FunctionCallErrorSer::HostError(HostError::GasExceeded)
+            ))),
+            NearClientError::GasExceeded
+        );
+        assert!(matches!(
+            NearClientError::from(action_error(ActionErrorKind::AccountDoesNotExist {
+                account_id: "relayer.testnet".parse().unwrap()
+            })),
+            NearClientError::ExecutionFailure(_)
+        ));
+    }
+}
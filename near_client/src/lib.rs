@@ -1,3 +1,5 @@
+pub mod errors;
 pub mod methods;
 pub mod read_private_key;
+pub mod signer;
 pub mod test_utils;
@@ -1,20 +1,26 @@
-use lazy_static::lazy_static;
-use near_jsonrpc_client::{methods, JsonRpcClient, JsonRpcClientConnector};
+use crate::errors::NearClientError;
+use near_jsonrpc_client::methods::RpcMethod;
+use near_jsonrpc_client::{methods, JsonRpcClient};
 use near_jsonrpc_primitives::types::query::{QueryResponseKind, RpcQueryResponse};
 use near_jsonrpc_primitives::types::transactions::TransactionInfo;
-use near_primitives::transaction::{Action, FunctionCallAction, Transaction};
-use near_primitives::types::{BlockReference, Finality, FunctionArgs};
-use near_primitives::views::{FinalExecutionOutcomeView, QueryRequest};
+use near_primitives::errors::{ActionError, TxExecutionError};
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{Action, FunctionCallAction, SignedTransaction, Transaction};
+use near_primitives::types::{AccountId, BlockReference, Finality, FunctionArgs};
+use near_primitives::views::{
+    ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionStatus, QueryRequest,
+};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::time;
 
-lazy_static! {
-    static ref DEFAULT_CONNECTOR: JsonRpcClientConnector = JsonRpcClient::with(
-        new_near_rpc_client(Some(std::time::Duration::from_secs(30)))
-    );
-}
+/// How long the transaction status is polled after `broadcast_tx_commit` times out
+const TX_STATUS_TIMEOUT: Duration = Duration::from_secs(60);
+const TX_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
-fn new_near_rpc_client(timeout: Option<std::time::Duration>) -> reqwest::Client {
+fn new_near_rpc_client(timeout: Option<Duration>) -> reqwest::Client {
     let mut headers = HeaderMap::with_capacity(2);
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
@@ -25,133 +31,568 @@ fn new_near_rpc_client(timeout: Option<std::time::Duration>) -> reqwest::Client
     builder.build().unwrap()
 }
 
-pub async fn view(
-    server_addr: url::Url,
-    contract_account_id: String,
-    method_name: String,
-    args: serde_json::Value,
-) -> Result<RpcQueryResponse, Box<dyn std::error::Error>> {
-    let client = DEFAULT_CONNECTOR.connect(server_addr);
-    let request = methods::query::RpcQueryRequest {
-        block_reference: BlockReference::Finality(Finality::Final),
-        request: QueryRequest::CallFunction {
-            account_id: contract_account_id.parse()?,
-            method_name,
-            args: FunctionArgs::from(args.to_string().into_bytes()),
-        },
-    };
-    Ok(client.call(request).await?)
+#[derive(Clone)]
+struct Endpoint {
+    url: url::Url,
+    client: JsonRpcClient,
 }
 
-pub async fn get_final_block_timestamp(
-    server_addr: url::Url,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    let client = DEFAULT_CONNECTOR.connect(server_addr);
-    let request = methods::block::RpcBlockRequest {
-        block_reference: BlockReference::Finality(Finality::Final),
-    };
-
-    let block_info = client.call(request).await?;
-    Ok(block_info.header.timestamp)
+/// NEAR JSON-RPC client over several endpoints. The request goes to the endpoint which served
+/// the last request and fails over to the next ones on the transport and server errors.
+#[derive(Clone)]
+pub struct NearClient {
+    endpoints: Arc<Vec<Endpoint>>,
+    preferred: Arc<AtomicUsize>,
+    rpc_timeout: Duration,
 }
 
-pub async fn get_last_near_block_height(
-    server_addr: url::Url,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    let client = DEFAULT_CONNECTOR.connect(server_addr);
-    let request = methods::block::RpcBlockRequest {
-        block_reference: BlockReference::latest(),
-    };
+impl NearClient {
+    pub fn new(urls: &[url::Url], rpc_timeout_secs: u64) -> Result<Self, NearClientError> {
+        if urls.is_empty() {
+            return Err(NearClientError::NoEndpoints);
+        }
 
-    let block_info = client.call(request).await?;
-    Ok(block_info.header.height as u64)
-}
+        let rpc_timeout = Duration::from_secs(rpc_timeout_secs);
+        let connector = JsonRpcClient::with(new_near_rpc_client(Some(rpc_timeout)));
+        let endpoints = urls
+            .iter()
+            .map(|url| Endpoint {
+                url: url.clone(),
+                client: connector.connect(url.clone()),
+            })
+            .collect();
 
-pub async fn change(
-    server_addr: url::Url,
-    signer: near_crypto::InMemorySigner,
-    receiver_id: String,
-    method_name: String,
-    args: serde_json::Value,
-    gas: u64,
-    deposit: u128,
-) -> Result<FinalExecutionOutcomeView, Box<dyn std::error::Error>> {
-    let client = DEFAULT_CONNECTOR.connect(server_addr);
-    let rpc_request = methods::query::RpcQueryRequest {
-        block_reference: BlockReference::latest(),
-        request: near_primitives::views::QueryRequest::ViewAccessKey {
-            account_id: signer.account_id.clone(),
-            public_key: signer.public_key.clone(),
-        },
-    };
-    let access_key_query_response = client.call(rpc_request).await?;
-    let current_nonce = match access_key_query_response.kind {
-        QueryResponseKind::AccessKey(access_key) => access_key.nonce,
-        _ => Err("failed to extract current nonce")?,
-    };
-    let transaction = Transaction {
-        signer_id: signer.account_id.clone(),
-        public_key: signer.public_key.clone(),
-        nonce: current_nonce + 1,
-        receiver_id: receiver_id.parse()?,
-        block_hash: access_key_query_response.block_hash,
-        actions: vec![Action::FunctionCall(FunctionCallAction {
+        Ok(NearClient {
+            endpoints: Arc::new(endpoints),
+            preferred: Arc::new(AtomicUsize::new(0)),
+            rpc_timeout,
+        })
+    }
+
+    pub fn rpc_timeout(&self) -> Duration {
+        self.rpc_timeout
+    }
+
+    /// The URL of the endpoint which served the last request
+    pub fn current_url(&self) -> &url::Url {
+        &self.endpoints[self.preferred.load(Ordering::Relaxed)].url
+    }
+
+    async fn call<M, F>(&self, request: F) -> Result<M::Response, NearClientError>
+    where
+        M: RpcMethod,
+        M::Error: Into<NearClientError>,
+        F: Fn() -> M,
+    {
+        let first = self.preferred.load(Ordering::Relaxed);
+        let mut last_error = NearClientError::NoEndpoints;
+
+        for offset in 0..self.endpoints.len() {
+            let index = (first + offset) % self.endpoints.len();
+            let endpoint = &self.endpoints[index];
+
+            let result =
+                match time::timeout(self.rpc_timeout, endpoint.client.call(request())).await {
+                    Ok(result) => result.map_err(NearClientError::from),
+                    Err(_) => Err(NearClientError::Timeout),
+                };
+
+            match result {
+                Err(err) if err.is_retriable() => {
+                    tracing::warn!("NEAR RPC request to {} failed: {}", endpoint.url, err);
+                    last_error = err;
+                }
+                result => {
+                    self.preferred.store(index, Ordering::Relaxed);
+                    return result;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    pub async fn view(
+        &self,
+        contract_account_id: String,
+        method_name: String,
+        args: serde_json::Value,
+    ) -> Result<RpcQueryResponse, NearClientError> {
+        self.view_raw(
+            contract_account_id,
             method_name,
-            args: args.to_string().into_bytes(),
-            gas,
-            deposit,
-        })],
-    };
-    let request = methods::broadcast_tx_async::RpcBroadcastTxAsyncRequest {
-        signed_transaction: transaction.sign(&signer),
-    };
-    let sent_at = time::Instant::now();
-    let tx_hash = client.call(request).await?;
-    loop {
-        let response = client
-            .call(methods::tx::RpcTransactionStatusRequest {
-                transaction_info: TransactionInfo::TransactionId {
-                    hash: tx_hash,
-                    account_id: signer.account_id.clone(),
+            args.to_string().into_bytes(),
+        )
+        .await
+    }
+
+    /// Calls the view method with the serialized arguments, e.g. borsh
+    pub async fn view_raw(
+        &self,
+        contract_account_id: String,
+        method_name: String,
+        args: Vec<u8>,
+    ) -> Result<RpcQueryResponse, NearClientError> {
+        let account_id = parse_account_id(&contract_account_id)?;
+        self.call(|| methods::query::RpcQueryRequest {
+            block_reference: BlockReference::Finality(Finality::Final),
+            request: QueryRequest::CallFunction {
+                account_id: account_id.clone(),
+                method_name: method_name.clone(),
+                args: FunctionArgs::from(args.clone()),
+            },
+        })
+        .await
+    }
+
+    pub async fn get_final_block_timestamp(&self) -> Result<u64, NearClientError> {
+        let block_info = self
+            .call(|| methods::block::RpcBlockRequest {
+                block_reference: BlockReference::Finality(Finality::Final),
+            })
+            .await?;
+        Ok(block_info.header.timestamp)
+    }
+
+    pub async fn get_last_near_block_height(&self) -> Result<u64, NearClientError> {
+        let block_info = self
+            .call(|| methods::block::RpcBlockRequest {
+                block_reference: BlockReference::latest(),
+            })
+            .await?;
+        Ok(block_info.header.height as u64)
+    }
+
+    pub async fn get_gas_price(&self) -> Result<u128, NearClientError> {
+        Ok(self
+            .call(|| methods::gas_price::RpcGasPriceRequest { block_id: None })
+            .await?
+            .gas_price)
+    }
+
+    /// The nonce of the access key and the hash of the block it is read at
+    pub async fn get_access_key_nonce(
+        &self,
+        account_id: &AccountId,
+        public_key: &near_crypto::PublicKey,
+    ) -> Result<(u64, CryptoHash), NearClientError> {
+        let response = self
+            .call(|| methods::query::RpcQueryRequest {
+                block_reference: BlockReference::latest(),
+                request: QueryRequest::ViewAccessKey {
+                    account_id: account_id.clone(),
+                    public_key: public_key.clone(),
                 },
             })
+            .await?;
+
+        match response.kind {
+            QueryResponseKind::AccessKey(access_key) => Ok((access_key.nonce, response.block_hash)),
+            kind => Err(NearClientError::UnexpectedResponse(format!("{:?}", kind))),
+        }
+    }
+
+    /// Signs the function call with the next nonce of the access key and waits for the outcome.
+    /// The nonce is read from the chain, `NearSigner` should be used for the concurrent transactions.
+    pub async fn change(
+        &self,
+        signer: &near_crypto::InMemorySigner,
+        receiver_id: String,
+        method_name: String,
+        args: serde_json::Value,
+        gas: u64,
+        deposit: u128,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        let (nonce, block_hash) = self
+            .get_access_key_nonce(&signer.account_id, &signer.public_key)
+            .await?;
+
+        let transaction = Transaction {
+            signer_id: signer.account_id.clone(),
+            public_key: signer.public_key.clone(),
+            nonce: nonce + 1,
+            receiver_id: parse_account_id(&receiver_id)?,
+            block_hash,
+            actions: vec![Action::FunctionCall(FunctionCallAction {
+                method_name,
+                args: args.to_string().into_bytes(),
+                gas,
+                deposit,
+            })],
+        };
+
+        self.send_transaction(transaction.sign(signer)).await
+    }
+
+    /// Sends the transaction with `broadcast_tx_commit`, the status is polled if it times out
+    pub async fn send_transaction(
+        &self,
+        signed_transaction: SignedTransaction,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        let sent_at = time::Instant::now();
+        let result = self
+            .call(
+                || methods::broadcast_tx_commit::RpcBroadcastTxCommitRequest {
+                    signed_transaction: signed_transaction.clone(),
+                },
+            )
             .await;
+        if !matches!(result, Err(NearClientError::Timeout)) {
+            return result;
+        }
 
-        let delta = (time::Instant::now() - sent_at).as_secs();
-        if delta > 60 {
-            Err("time limit exceeded for the transaction to be recognized")?;
+        let tx_hash = signed_transaction.get_hash();
+        let signer_id = signed_transaction.transaction.signer_id.clone();
+        loop {
+            if sent_at.elapsed() > TX_STATUS_TIMEOUT {
+                return Err(NearClientError::Timeout);
+            }
+            time::sleep(TX_STATUS_POLL_INTERVAL).await;
+
+            match self.transaction_status(tx_hash, signer_id.clone()).await {
+                Ok(outcome) => return Ok(outcome),
+                Err(err) if err.is_rejected() => return Err(err),
+                Err(err) => tracing::warn!("The transaction {} is not found yet: {}", tx_hash, err),
+            }
         }
+    }
 
-        match response {
-            Err(err) => match err.handler_error() {
-                Some(err) => {
-                    eprintln!("(An handler error occurred `{:#?}`", err);
-                    time::sleep(time::Duration::from_secs(2)).await;
-                    continue;
-                }
-                _ => Err(err)?,
+    /// The outcome of the sent transaction, `UnknownTransaction` if the node hasn't received it
+    pub async fn transaction_status(
+        &self,
+        tx_hash: CryptoHash,
+        signer_id: AccountId,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        self.call(|| methods::tx::RpcTransactionStatusRequest {
+            transaction_info: TransactionInfo::TransactionId {
+                hash: tx_hash,
+                account_id: signer_id.clone(),
             },
-            Ok(response) => return Ok(response),
+        })
+        .await
+    }
+}
+
+/// The failure of the transaction or of one of its receipts
+pub fn execution_error(outcome: &FinalExecutionOutcomeView) -> Option<NearClientError> {
+    for receipt_outcome in &outcome.receipts_outcome {
+        if let ExecutionStatusView::Failure(err) = &receipt_outcome.outcome.status {
+            return Some(err.clone().into());
         }
     }
+
+    match &outcome.status {
+        FinalExecutionStatus::Failure(err) => Some(err.clone().into()),
+        _ => None,
+    }
+}
+
+/// The result of one action of the transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionResult {
+    Success,
+    Failure(NearClientError),
+    /// The action is reverted after the failure of another action of the transaction
+    Reverted,
+    /// The transaction isn't executed yet
+    Pending,
+    /// A receipt created by one of the actions failed, the failure can't be attributed to the action.
+    /// The result of the action is checked on-chain.
+    Unconfirmed(NearClientError),
+}
+
+/// Maps the outcome of the transaction with `actions_count` actions to the result of every action.
+/// The actions are executed in one receipt, the failure of one action reverts the others. The failed
+/// receipts created by the actions can't be attributed to one of them, they fail only the single action,
+/// the actions of the batch are unconfirmed.
+pub fn action_results(
+    outcome: &FinalExecutionOutcomeView,
+    actions_count: usize,
+) -> Vec<ActionResult> {
+    let all = |result: ActionResult| vec![result; actions_count];
+
+    if matches!(
+        outcome.status,
+        FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started
+    ) {
+        return all(ActionResult::Pending);
+    }
+
+    if let ExecutionStatusView::Failure(err) = &outcome.transaction_outcome.outcome.status {
+        return all(ActionResult::Failure(err.clone().into()));
+    }
+
+    let receipt_status = outcome
+        .transaction_outcome
+        .outcome
+        .receipt_ids
+        .first()
+        .and_then(|receipt_id| {
+            outcome
+                .receipts_outcome
+                .iter()
+                .find(|receipt_outcome| &receipt_outcome.id == receipt_id)
+        })
+        .map(|receipt_outcome| &receipt_outcome.outcome.status);
+
+    match receipt_status {
+        Some(ExecutionStatusView::Failure(err)) => match err {
+            TxExecutionError::ActionError(ActionError {
+                index: Some(index), ..
+            }) if (*index as usize) < actions_count => {
+                let mut results = all(ActionResult::Reverted);
+                results[*index as usize] = ActionResult::Failure(err.clone().into());
+                results
+            }
+            err => all(ActionResult::Failure(err.clone().into())),
+        },
+        Some(ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_)) => {
+            match execution_error(outcome) {
+                Some(err) if actions_count == 1 => vec![ActionResult::Failure(err)],
+                Some(err) => all(ActionResult::Unconfirmed(err)),
+                None => all(ActionResult::Success),
+            }
+        }
+        Some(ExecutionStatusView::Unknown) | None => all(ActionResult::Pending),
+    }
+}
+
+fn parse_account_id(account_id: &str) -> Result<AccountId, NearClientError> {
+    account_id
+        .parse()
+        .map_err(|_| NearClientError::InvalidAccountId(account_id.to_string()))
 }
 
 #[cfg(test)]
 pub mod tests {
-    use crate::methods::{change, view};
-    use crate::test_utils::{get_near_signer, get_near_token, get_server_addr};
-    use near_primitives::views::FinalExecutionStatus;
+    use crate::errors::NearClientError;
+    use crate::methods::{action_results, ActionResult, NearClient};
+    use crate::test_utils::{get_near_client, get_near_signer, get_near_token};
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::errors::{ActionError, ActionErrorKind, FunctionCallErrorSer};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::Transaction;
+    use near_primitives::views::{
+        ExecutionOutcomeView, ExecutionOutcomeWithIdView, ExecutionStatusView,
+        FinalExecutionOutcomeView, FinalExecutionStatus,
+    };
     use near_sdk::borsh::BorshDeserialize;
     use serde_json::json;
     use std::time::SystemTime;
 
+    fn execution_outcome(
+        id: u8,
+        executor_id: &str,
+        status: ExecutionStatusView,
+        receipt_ids: Vec<CryptoHash>,
+    ) -> ExecutionOutcomeWithIdView {
+        ExecutionOutcomeWithIdView {
+            proof: vec![],
+            block_hash: CryptoHash::default(),
+            id: CryptoHash([id; 32]),
+            outcome: ExecutionOutcomeView {
+                logs: vec![],
+                receipt_ids,
+                gas_burnt: 0,
+                tokens_burnt: 0,
+                executor_id: executor_id.parse().unwrap(),
+                status,
+                metadata: Default::default(),
+            },
+        }
+    }
+
+    /// The transaction converted to the receipt 1 which created the receipt 2
+    fn final_outcome(
+        receipt_status: ExecutionStatusView,
+        created_receipt_status: ExecutionStatusView,
+    ) -> FinalExecutionOutcomeView {
+        let signer =
+            InMemorySigner::from_seed("relayer.testnet".parse().unwrap(), KeyType::ED25519, "1");
+        let transaction = Transaction {
+            signer_id: signer.account_id.clone(),
+            public_key: signer.public_key.clone(),
+            nonce: 1,
+            receiver_id: "bridge.testnet".parse().unwrap(),
+            block_hash: CryptoHash::default(),
+            actions: vec![],
+        };
+
+        FinalExecutionOutcomeView {
+            status: FinalExecutionStatus::SuccessValue(Default::default()),
+            transaction: transaction.sign(&signer).into(),
+            transaction_outcome: execution_outcome(
+                0,
+                "relayer.testnet",
+                ExecutionStatusView::SuccessReceiptId(CryptoHash([1; 32])),
+                vec![CryptoHash([1; 32])],
+            ),
+            receipts_outcome: vec![
+                execution_outcome(
+                    1,
+                    "bridge.testnet",
+                    receipt_status,
+                    vec![CryptoHash([2; 32])],
+                ),
+                execution_outcome(2, "bridge.testnet", created_receipt_status, vec![]),
+            ],
+        }
+    }
+
+    /// Every action of the receipt 1 calls the prover with the callback to the bridge,
+    /// the receipt 1 also refunds the relayer
+    fn multi_action_outcome(
+        callback_statuses: Vec<ExecutionStatusView>,
+    ) -> FinalExecutionOutcomeView {
+        let mut outcome = final_outcome(success(), success());
+        let actions: Vec<u8> = (0..callback_statuses.len() as u8).collect();
+
+        let mut created_receipt_ids = vec![];
+        for action in &actions {
+            created_receipt_ids.push(CryptoHash([10 + action; 32]));
+            created_receipt_ids.push(CryptoHash([20 + action; 32]));
+        }
+        created_receipt_ids.push(CryptoHash([30; 32]));
+
+        outcome.receipts_outcome = vec![execution_outcome(
+            1,
+            "bridge.testnet",
+            success(),
+            created_receipt_ids,
+        )];
+        for (action, callback_status) in actions.iter().zip(callback_statuses) {
+            outcome.receipts_outcome.push(execution_outcome(
+                10 + action,
+                "prover.testnet",
+                success(),
+                vec![],
+            ));
+            outcome.receipts_outcome.push(execution_outcome(
+                20 + action,
+                "bridge.testnet",
+                callback_status,
+                vec![],
+            ));
+        }
+        outcome
+            .receipts_outcome
+            .push(execution_outcome(30, "relayer.testnet", success(), vec![]));
+        outcome
+    }
+
+    fn panic_at(index: u64) -> ExecutionStatusView {
+        ExecutionStatusView::Failure(
+            ActionError {
+                index: Some(index),
+                kind: ActionErrorKind::FunctionCallError(FunctionCallErrorSer::ExecutionError(
+                    "Smart contract panicked: Already unlocked".to_string(),
+                )),
+            }
+            .into(),
+        )
+    }
+
+    fn success() -> ExecutionStatusView {
+        ExecutionStatusView::SuccessValue(Default::default())
+    }
+
+    #[test]
+    fn action_results_test() {
+        let contract_panic = ActionResult::Failure(NearClientError::ContractPanic(
+            "Smart contract panicked: Already unlocked".to_string(),
+        ));
+
+        assert_eq!(
+            action_results(&final_outcome(success(), success()), 3),
+            vec![ActionResult::Success; 3]
+        );
+
+        // The failed action reverts the others
+        assert_eq!(
+            action_results(&final_outcome(panic_at(1), success()), 3),
+            vec![
+                ActionResult::Reverted,
+                contract_panic.clone(),
+                ActionResult::Reverted
+            ]
+        );
+
+        // The failed created receipt can't be attributed to one of the actions
+        let unconfirmed = ActionResult::Unconfirmed(NearClientError::ContractPanic(
+            "Smart contract panicked: Already unlocked".to_string(),
+        ));
+        assert_eq!(
+            action_results(&final_outcome(success(), panic_at(0)), 2),
+            vec![unconfirmed.clone(); 2]
+        );
+        assert_eq!(
+            action_results(&final_outcome(success(), panic_at(0)), 1),
+            vec![contract_panic.clone()]
+        );
+
+        // The failed callback isn't attributed by the position of its receipt
+        assert_eq!(
+            action_results(
+                &multi_action_outcome(vec![success(), panic_at(0), success()]),
+                3
+            ),
+            vec![unconfirmed.clone(); 3]
+        );
+        assert_eq!(
+            action_results(&multi_action_outcome(vec![success(); 3]), 3),
+            vec![ActionResult::Success; 3]
+        );
+
+        // The failed final status is the failure too
+        let mut outcome = multi_action_outcome(vec![success(); 3]);
+        outcome.status = FinalExecutionStatus::Failure(
+            ActionError {
+                index: Some(0),
+                kind: ActionErrorKind::FunctionCallError(FunctionCallErrorSer::ExecutionError(
+                    "Smart contract panicked: Already unlocked".to_string(),
+                )),
+            }
+            .into(),
+        );
+        assert_eq!(action_results(&outcome, 3), vec![unconfirmed; 3]);
+        assert_eq!(action_results(&outcome, 1), vec![contract_panic]);
+
+        let mut outcome = final_outcome(success(), success());
+        outcome.status = FinalExecutionStatus::Started;
+        assert_eq!(action_results(&outcome, 2), vec![ActionResult::Pending; 2]);
+    }
+
+    #[test]
+    fn new_without_endpoints_test() {
+        assert!(matches!(
+            NearClient::new(&[], 30),
+            Err(NearClientError::NoEndpoints)
+        ));
+    }
+
+    #[tokio::test]
+    async fn smoke_failover_test() {
+        let near_client = NearClient::new(
+            &[
+                url::Url::parse("http://127.0.0.1:1").unwrap(),
+                url::Url::parse("https://rpc.testnet.near.org").unwrap(),
+            ],
+            30,
+        )
+        .unwrap();
+
+        assert!(near_client.get_gas_price().await.unwrap() > 0);
+        assert_eq!(
+            near_client.current_url().as_str(),
+            "https://rpc.testnet.near.org/"
+        );
+    }
+
     #[tokio::test]
     async fn smoke_blocktimestamp_test() {
         const MIN_IN_NS: u64 = 60_000_000_000;
 
-        let near_timestamp_ns = crate::methods::get_final_block_timestamp(get_server_addr())
-            .await
-            .unwrap();
+        let near_timestamp_ns = get_near_client().get_final_block_timestamp().await.unwrap();
 
         let sys_timestamp_ns = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -165,14 +606,21 @@ pub mod tests {
         assert!(sys_timestamp_ns < near_timestamp_ns + MIN_IN_NS);
     }
 
+    #[tokio::test]
+    async fn smoke_gas_price_test() {
+        let gas_price = get_near_client().get_gas_price().await.unwrap();
+
+        assert!(gas_price > 0);
+    }
+
     #[tokio::test]
     async fn smoke_view_test() {
-        let server_addr = get_server_addr();
         let contract_account_id = "client6.goerli.testnet".to_string();
         let method_name = "last_block_number".to_string();
         let args = json!({});
 
-        let response = view(server_addr, contract_account_id, method_name, args)
+        let response = get_near_client()
+            .view(contract_account_id, method_name, args)
             .await
             .unwrap();
 
@@ -189,24 +637,23 @@ pub mod tests {
 
     #[tokio::test]
     async fn smoke_change_test() {
-        let server_addr = get_server_addr();
         let contract_account_id = get_near_token();
         let method_name = "mint".to_string();
         let signer = get_near_signer();
 
         let args = json!({"account_id": signer.account_id, "amount": "100"});
 
-        let response = change(
-            server_addr,
-            signer,
-            contract_account_id.to_string(),
-            method_name,
-            args,
-            4_000_000_000_000,
-            0,
-        )
-        .await
-        .unwrap();
+        let response = get_near_client()
+            .change(
+                &signer,
+                contract_account_id.to_string(),
+                method_name,
+                args,
+                4_000_000_000_000,
+                0,
+            )
+            .await
+            .unwrap();
 
         if let FinalExecutionStatus::SuccessValue(_) = response.status {
             println!("change response = {:?}", response);
@@ -0,0 +1,344 @@
+//! Relayer signer with the locally managed access key nonces
+//!
+//! The nonce of every access key is read from the chain once and incremented locally, so several
+//! transactions can be signed concurrently. The transactions are distributed over the access keys
+//! in turn. The nonce is resynced when the node rejects a transaction with `InvalidNonce`.
+
+use crate::errors::NearClientError;
+use crate::methods::NearClient;
+use near_crypto::InMemorySigner;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{Action, FunctionCallAction, SignedTransaction, Transaction};
+use near_primitives::types::AccountId;
+use near_primitives::views::FinalExecutionOutcomeView;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// The attempts to send a transaction rejected with `InvalidNonce` or `Expired`
+const MAX_NONCE_RETRIES: usize = 3;
+/// The block hash of the transaction is refreshed after this time, the transaction expires in a day
+const BLOCK_HASH_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NonceState {
+    /// The last used nonce
+    nonce: u64,
+    block_hash: CryptoHash,
+    synced_at: Instant,
+    /// The transaction with the block hash is expired
+    expired: bool,
+}
+
+impl NonceState {
+    fn next(&mut self) -> u64 {
+        self.nonce += 1;
+        self.nonce
+    }
+
+    /// The transactions already signed with the greater nonces stay valid
+    fn resync(&mut self, ak_nonce: u64) {
+        self.nonce = self.nonce.max(ak_nonce);
+    }
+}
+
+struct AccessKey {
+    signer: InMemorySigner,
+    state: Mutex<Option<NonceState>>,
+}
+
+impl AccessKey {
+    /// Reserves the next nonce, the nonce and the block hash are read from the chain if needed
+    async fn next_nonce(
+        &self,
+        near_client: &NearClient,
+    ) -> Result<(u64, CryptoHash), NearClientError> {
+        let mut state = self.state.lock().await;
+
+        let is_stale = state.map_or(true, |state| {
+            state.expired || state.synced_at.elapsed() > BLOCK_HASH_TTL
+        });
+        if is_stale {
+            let (ak_nonce, block_hash) = near_client
+                .get_access_key_nonce(&self.signer.account_id, &self.signer.public_key)
+                .await?;
+            let mut synced = NonceState {
+                nonce: ak_nonce,
+                block_hash,
+                synced_at: Instant::now(),
+                expired: false,
+            };
+            if let Some(state) = *state {
+                synced.resync(state.nonce);
+            }
+            *state = Some(synced);
+        }
+
+        let state = state.as_mut().expect("The nonce is synced");
+        Ok((state.next(), state.block_hash))
+    }
+
+    async fn resync(&self, ak_nonce: u64) {
+        if let Some(state) = self.state.lock().await.as_mut() {
+            state.resync(ak_nonce);
+        }
+    }
+
+    /// The nonce and the block hash are read from the chain before the next transaction
+    async fn invalidate(&self) {
+        if let Some(state) = self.state.lock().await.as_mut() {
+            state.expired = true;
+        }
+    }
+}
+
+/// Signs the transactions of one account with one or several of its access keys
+#[derive(Clone)]
+pub struct NearSigner {
+    near_client: NearClient,
+    access_keys: Arc<Vec<AccessKey>>,
+    next_key: Arc<AtomicUsize>,
+}
+
+impl NearSigner {
+    /// All the `signers` should belong to the same account
+    pub fn new(
+        near_client: NearClient,
+        signers: Vec<InMemorySigner>,
+    ) -> Result<Self, NearClientError> {
+        let account_id = match signers.first() {
+            Some(signer) => signer.account_id.clone(),
+            None => return Err(NearClientError::InvalidSigner("no access keys".to_string())),
+        };
+
+        if let Some(signer) = signers
+            .iter()
+            .find(|signer| signer.account_id != account_id)
+        {
+            return Err(NearClientError::InvalidSigner(format!(
+                "the access key of {} is used with {}",
+                signer.account_id, account_id
+            )));
+        }
+
+        let access_keys = signers
+            .into_iter()
+            .map(|signer| AccessKey {
+                signer,
+                state: Mutex::new(None),
+            })
+            .collect();
+
+        Ok(NearSigner {
+            near_client,
+            access_keys: Arc::new(access_keys),
+            next_key: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        &self.access_keys[0].signer.account_id
+    }
+
+    pub fn access_keys_count(&self) -> usize {
+        self.access_keys.len()
+    }
+
+    pub fn near_client(&self) -> &NearClient {
+        &self.near_client
+    }
+
+    fn next_access_key(&self) -> &AccessKey {
+        let index = self.next_key.fetch_add(1, Ordering::Relaxed) % self.access_keys.len();
+        &self.access_keys[index]
+    }
+
+    /// Signs the function call with the next access key and waits for the outcome
+    pub async fn function_call(
+        &self,
+        receiver_id: String,
+        method_name: String,
+        args: serde_json::Value,
+        gas: u64,
+        deposit: u128,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        self.function_calls(
+            receiver_id,
+            vec![FunctionCallAction {
+                method_name,
+                args: args.to_string().into_bytes(),
+                gas,
+                deposit,
+            }],
+        )
+        .await
+    }
+
+    /// Sends the function calls in one transaction, they are executed in one receipt
+    pub async fn function_calls(
+        &self,
+        receiver_id: String,
+        calls: Vec<FunctionCallAction>,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let signed_transaction = self
+                .sign_function_calls(receiver_id.clone(), calls.clone())
+                .await?;
+
+            match self.send(signed_transaction).await {
+                Err(err) if err.is_rejected() && attempt < MAX_NONCE_RETRIES => {}
+                result => return result,
+            }
+        }
+    }
+
+    /// Signs the function calls with the next access key and reserves its nonce
+    pub async fn sign_function_calls(
+        &self,
+        receiver_id: String,
+        calls: Vec<FunctionCallAction>,
+    ) -> Result<SignedTransaction, NearClientError> {
+        let receiver_id: AccountId = receiver_id
+            .parse()
+            .map_err(|_| NearClientError::InvalidAccountId(receiver_id.clone()))?;
+        let access_key = self.next_access_key();
+        let (nonce, block_hash) = access_key.next_nonce(&self.near_client).await?;
+
+        let transaction = Transaction {
+            signer_id: access_key.signer.account_id.clone(),
+            public_key: access_key.signer.public_key.clone(),
+            nonce,
+            receiver_id,
+            block_hash,
+            actions: calls.into_iter().map(Action::FunctionCall).collect(),
+        };
+
+        Ok(transaction.sign(&access_key.signer))
+    }
+
+    /// Sends the transaction signed by `sign_function_calls`, the nonce of the access key is resynced
+    /// if the transaction is rejected
+    pub async fn send(
+        &self,
+        signed_transaction: SignedTransaction,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        let public_key = signed_transaction.transaction.public_key.clone();
+        let result = self.near_client.send_transaction(signed_transaction).await;
+
+        if let Err(err) = &result {
+            let access_key = self
+                .access_keys
+                .iter()
+                .find(|access_key| access_key.signer.public_key == public_key);
+
+            match (err, access_key) {
+                (NearClientError::InvalidNonce { tx_nonce, ak_nonce }, Some(access_key)) => {
+                    tracing::warn!(
+                        "Invalid nonce {} of the access key {}, the current nonce is {}",
+                        tx_nonce,
+                        public_key,
+                        ak_nonce
+                    );
+                    access_key.resync(*ak_nonce).await;
+                }
+                (NearClientError::Expired, Some(access_key)) => {
+                    tracing::warn!("The transaction is expired, resync the block hash");
+                    access_key.invalidate().await;
+                }
+                _ => {}
+            }
+        }
+
+        result
+    }
+
+    /// Waits for the outcome of the transaction sent before, the transaction is sent again if the node
+    /// doesn't know it. The same signed transaction is never executed twice.
+    pub async fn resume(
+        &self,
+        signed_transaction: SignedTransaction,
+    ) -> Result<FinalExecutionOutcomeView, NearClientError> {
+        let status = self
+            .near_client
+            .transaction_status(
+                signed_transaction.get_hash(),
+                signed_transaction.transaction.signer_id.clone(),
+            )
+            .await;
+
+        match status {
+            Err(NearClientError::UnknownTransaction) => self.send(signed_transaction).await,
+            status => status,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::errors::NearClientError;
+    use crate::signer::{NearSigner, NonceState};
+    use crate::test_utils::get_near_client;
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::hash::CryptoHash;
+    use tokio::time::Instant;
+
+    fn signer(account_id: &str, seed: &str) -> InMemorySigner {
+        InMemorySigner::from_seed(account_id.parse().unwrap(), KeyType::ED25519, seed)
+    }
+
+    #[test]
+    fn nonce_state_test() {
+        let mut state = NonceState {
+            nonce: 10,
+            block_hash: CryptoHash::default(),
+            synced_at: Instant::now(),
+            expired: false,
+        };
+
+        assert_eq!(state.next(), 11);
+        assert_eq!(state.next(), 12);
+
+        // The nonce doesn't go back if the chain is behind the signed transactions
+        state.resync(11);
+        assert_eq!(state.next(), 13);
+
+        state.resync(20);
+        assert_eq!(state.next(), 21);
+    }
+
+    #[test]
+    fn new_signer_test() {
+        assert!(matches!(
+            NearSigner::new(get_near_client(), vec![]),
+            Err(NearClientError::InvalidSigner(_))
+        ));
+        assert!(matches!(
+            NearSigner::new(
+                get_near_client(),
+                vec![signer("relayer.testnet", "1"), signer("other.testnet", "2")]
+            ),
+            Err(NearClientError::InvalidSigner(_))
+        ));
+
+        let near_signer = NearSigner::new(
+            get_near_client(),
+            vec![
+                signer("relayer.testnet", "1"),
+                signer("relayer.testnet", "2"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(near_signer.account_id().as_str(), "relayer.testnet");
+
+        // The access keys are used in turn
+        let public_keys: Vec<_> = (0..3)
+            .map(|_| near_signer.next_access_key().signer.public_key.clone())
+            .collect();
+        assert_ne!(public_keys[0], public_keys[1]);
+        assert_eq!(public_keys[0], public_keys[2]);
+    }
+}
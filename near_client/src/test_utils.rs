@@ -1,3 +1,4 @@
+use crate::methods::NearClient;
 use crate::read_private_key::read_private_key_from_file;
 use near_crypto::InMemorySigner;
 use near_sdk::AccountId;
@@ -26,3 +27,7 @@ pub fn get_near_token() -> AccountId {
 pub fn get_server_addr() -> url::Url {
     url::Url::parse("https://rpc.testnet.near.org").unwrap()
 }
+
+pub fn get_near_client() -> NearClient {
+    NearClient::new(&[get_server_addr()], 30).unwrap()
+}
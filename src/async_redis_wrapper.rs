@@ -3,7 +3,7 @@ use futures_util::StreamExt;
 use redis::AsyncCommands;
 #[cfg(feature = "integration_tests")]
 use redis::RedisResult;
-use web3::types::U256;
+use web3::types::{Address, U256};
 
 #[derive(Clone)]
 pub struct AsyncRedisWrapper {
@@ -16,12 +16,71 @@ pub struct TxData {
     pub block: u64,
     pub proof: fast_bridge_common::Proof,
     pub nonce: u128,
+    /// Absent in the transactions stored before the proofs were verified
+    #[serde(default)]
+    pub transfer: Option<EthTransferData>,
+    /// The failed `lp_unlock` attempts
+    #[serde(default)]
+    pub unlock_attempts: u32,
+    /// The unlock isn't sent before this UNIX timestamp in seconds
+    #[serde(default)]
+    pub retry_after: u64,
+    /// The raised gas of the `lp_unlock` call retried after the gas is exceeded, the call is sent
+    /// alone. `unlock_tokens_worker.gas` is attached if absent.
+    #[serde(default)]
+    pub unlock_gas: Option<u64>,
+}
+
+/// The transfer which can't be unlocked
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FailedTxData {
+    pub tx_data: TxData,
+    pub reason: String,
+    /// The failed unlock transaction, absent if the transfer is rejected before the unlock
+    pub near_tx_hash: Option<String>,
+    /// UNIX timestamp in seconds
+    pub failed_at: u64,
+}
+
+/// The NEAR transaction sent to unlock the transfer
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UnlockTxData {
+    pub near_tx_hash: String,
+    /// The borsh serialized signed transaction in base64
+    pub signed_transaction: String,
+    /// The index of the `lp_unlock` call of the transfer in the transaction
+    pub action_index: usize,
+    pub actions_count: usize,
 }
 
 #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PendingTransactionData {
     pub timestamp: u64,
     pub nonce: u128,
+    #[serde(default)]
+    pub transfer: Option<EthTransferData>,
+}
+
+/// The transfer of the NEAR transfer message, the `TransferTokens` event of the transaction and its
+/// proof are checked against it
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EthTransferData {
+    pub token: Address,
+    pub recipient: Address,
+    pub amount: U256,
+    #[serde(default)]
+    pub valid_till_block_height: Option<U256>,
+}
+
+impl EthTransferData {
+    pub fn from_transfer_message(transfer_message: &fast_bridge_common::TransferMessage) -> Self {
+        EthTransferData {
+            token: Address::from(transfer_message.transfer.token_eth.0),
+            recipient: Address::from(transfer_message.recipient.0),
+            amount: U256::from(transfer_message.transfer.amount.0),
+            valid_till_block_height: transfer_message.valid_till_block_height.map(U256::from),
+        }
+    }
 }
 
 pub const OPTIONS: &str = "options";
@@ -36,6 +95,12 @@ pub const EVENTS: &str = "events";
 
 pub const PENDING_TRANSACTIONS: &str = "pending_transactions";
 
+// Set of pairs <TX_HASH, UNLOCK_TX_DATA>, the NEAR transactions stored before they are sent
+pub const UNLOCK_TRANSACTIONS: &str = "unlock_transactions";
+
+// Set of pairs <TX_HASH, FAILED_TX_DATA>, the transfers removed from the unlock queue
+pub const FAILED_TRANSACTIONS: &str = "failed_transactions";
+
 pub const NEW_EVENTS: &str = "new_events";
 
 impl AsyncRedisWrapper {
@@ -148,6 +213,46 @@ impl AsyncRedisWrapper {
         }
     }
 
+    /// Replaces the data of the stored transaction, the sent unlock transaction is forgotten
+    pub async fn update_tx(&mut self, tx_hash: &str, tx_data: &TxData) -> redis::RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .hset(
+                TRANSACTIONS,
+                tx_hash,
+                serde_json::to_string(tx_data)
+                    .expect("REDIS: Failed to serialize transaction data"),
+            )
+            .ignore()
+            .hdel(UNLOCK_TRANSACTIONS, tx_hash)
+            .ignore()
+            .query_async(&mut self.connection)
+            .await
+    }
+
+    /// Moves the transaction out of the unlock queue
+    pub async fn store_failed_tx(
+        &mut self,
+        tx_hash: &str,
+        failed_tx_data: &FailedTxData,
+    ) -> redis::RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .hset(
+                FAILED_TRANSACTIONS,
+                tx_hash,
+                serde_json::to_string(failed_tx_data)
+                    .expect("REDIS: Failed to serialize failed transaction data"),
+            )
+            .ignore()
+            .hdel(TRANSACTIONS, tx_hash)
+            .ignore()
+            .hdel(UNLOCK_TRANSACTIONS, tx_hash)
+            .ignore()
+            .query_async(&mut self.connection)
+            .await
+    }
+
     pub async fn get_tx_data(&mut self, tx_hash: String) -> redis::RedisResult<TxData> {
         match self.connection.hget(TRANSACTIONS, &tx_hash).await {
             Ok(value) => {
@@ -159,9 +264,85 @@ impl AsyncRedisWrapper {
         }
     }
 
+    /// Checks in one pipelined round trip which of the transactions are already stored
+    pub async fn txs_exist(&mut self, tx_hashes: &[String]) -> redis::RedisResult<Vec<bool>> {
+        if tx_hashes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut pipe = redis::pipe();
+        for tx_hash in tx_hashes {
+            pipe.hexists(TRANSACTIONS, tx_hash);
+        }
+        pipe.query_async(&mut self.connection).await
+    }
+
+    pub async fn remove_pending_txs(&mut self, tx_hashes: &[String]) -> redis::RedisResult<()> {
+        if tx_hashes.is_empty() {
+            return Ok(());
+        }
+
+        self.connection.hdel(PENDING_TRANSACTIONS, tx_hashes).await
+    }
+
     pub async fn get_tx_hashes(&mut self) -> redis::RedisResult<Vec<String>> {
         self.connection.hkeys(TRANSACTIONS).await
     }
+
+    pub async fn store_unlock_txs(
+        &mut self,
+        unlock_txs: &[(String, UnlockTxData)],
+    ) -> redis::RedisResult<()> {
+        if unlock_txs.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for (tx_hash, unlock_tx_data) in unlock_txs {
+            pipe.hset(
+                UNLOCK_TRANSACTIONS,
+                tx_hash,
+                serde_json::to_string(unlock_tx_data)
+                    .expect("REDIS: Failed to serialize unlock transaction data"),
+            )
+            .ignore();
+        }
+        pipe.query_async(&mut self.connection).await
+    }
+
+    /// Reads in one pipelined round trip the NEAR transactions sent to unlock the transfers
+    pub async fn get_unlock_txs(
+        &mut self,
+        tx_hashes: &[String],
+    ) -> redis::RedisResult<Vec<Option<UnlockTxData>>> {
+        if tx_hashes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut pipe = redis::pipe();
+        for tx_hash in tx_hashes {
+            pipe.hget(UNLOCK_TRANSACTIONS, tx_hash);
+        }
+        let values: Vec<Option<String>> = pipe.query_async(&mut self.connection).await?;
+
+        Ok(values
+            .into_iter()
+            .map(|value| {
+                value.map(|value| {
+                    serde_json::from_str(&value)
+                        .expect("REDIS: Failed to deserialize unlock transaction data")
+                })
+            })
+            .collect())
+    }
+
+    pub async fn remove_unlock_txs(&mut self, tx_hashes: &[String]) -> redis::RedisResult<()> {
+        if tx_hashes.is_empty() {
+            return Ok(());
+        }
+
+        self.connection.hdel(UNLOCK_TRANSACTIONS, tx_hashes).await
+    }
 }
 
 #[allow(dead_code)]
@@ -195,12 +376,16 @@ pub fn subscribe<T: 'static + redis::FromRedisValue + Send>(
 
 #[cfg(test)]
 pub mod tests {
-    use crate::async_redis_wrapper::{subscribe, AsyncRedisWrapper, TxData, EVENTS, TRANSACTIONS};
+    use crate::async_redis_wrapper::{
+        subscribe, AsyncRedisWrapper, FailedTxData, TxData, UnlockTxData, EVENTS,
+        FAILED_TRANSACTIONS, TRANSACTIONS, UNLOCK_TRANSACTIONS,
+    };
     use crate::test_utils::{get_settings, remove_all};
     use eth_client::test_utils::{get_eth_token, get_recipient};
     use fast_bridge_common::{EthAddress, TransferDataEthereum, TransferDataNear, TransferMessage};
     use near_client::test_utils::get_near_token;
     use near_sdk::json_types::U128;
+    use redis::AsyncCommands;
     use tokio::time::Duration;
 
     // run `redis-server` in the terminal
@@ -244,6 +429,7 @@ pub mod tests {
             block: 126u64,
             proof: fast_bridge_common::Proof::default(),
             nonce: 15u128,
+            ..Default::default()
         };
 
         redis
@@ -258,8 +444,73 @@ pub mod tests {
         assert_eq!(tx_list.len(), 1);
         assert_eq!(tx_list[0], tx_hash.clone());
 
-        redis.unstore_tx(tx_hash.clone()).await.unwrap();
-        assert!(redis.get_tx_data(tx_hash).await.is_err());
+        let exist = redis
+            .txs_exist(&[tx_hash.clone(), "unknown_tx_hash".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(exist, vec![true, false]);
+
+        let mut updated_tx_data = tx_data.clone();
+        updated_tx_data.unlock_attempts = 1;
+        redis.update_tx(&tx_hash, &updated_tx_data).await.unwrap();
+        let extracted_tx_data = redis.get_tx_data(tx_hash.clone()).await.unwrap();
+        assert_eq!(extracted_tx_data.unlock_attempts, 1);
+
+        redis
+            .store_failed_tx(
+                &tx_hash,
+                &FailedTxData {
+                    tx_data: updated_tx_data,
+                    reason: "Already unlocked".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(redis.get_tx_data(tx_hash.clone()).await.is_err());
+        let failed: Option<String> = redis
+            .connection
+            .hget(FAILED_TRANSACTIONS, &tx_hash)
+            .await
+            .unwrap();
+        assert!(failed.unwrap().contains("Already unlocked"));
+        let _: () = redis
+            .connection
+            .hdel(FAILED_TRANSACTIONS, &tx_hash)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn smoke_unlock_tx_test() {
+        let settings = std::sync::Arc::new(tokio::sync::Mutex::new(get_settings()));
+
+        let mut redis = AsyncRedisWrapper::connect(&settings.lock().await.redis).await;
+
+        remove_all(redis.clone(), UNLOCK_TRANSACTIONS).await;
+
+        let unlock_tx_data = UnlockTxData {
+            near_tx_hash: "test_near_tx_hash".to_string(),
+            signed_transaction: "c2lnbmVk".to_string(),
+            action_index: 1,
+            actions_count: 2,
+        };
+        let tx_hashes = vec!["test_tx_hash".to_string(), "unknown_tx_hash".to_string()];
+
+        redis
+            .store_unlock_txs(&[(tx_hashes[0].clone(), unlock_tx_data.clone())])
+            .await
+            .unwrap();
+        assert_eq!(
+            redis.get_unlock_txs(&tx_hashes).await.unwrap(),
+            vec![Some(unlock_tx_data), None]
+        );
+
+        redis.remove_unlock_txs(&tx_hashes).await.unwrap();
+        assert_eq!(
+            redis.get_unlock_txs(&tx_hashes).await.unwrap(),
+            vec![None, None]
+        );
     }
 
     #[tokio::test]
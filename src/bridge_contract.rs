@@ -0,0 +1,330 @@
+use crate::config::{SafeSettings, Settings};
+use crate::prometheus_metrics::{
+    BRIDGE_IMPLEMENTATION_CHANGES_COUNT, BRIDGE_TRANSFER_SUBMISSION_PAUSED,
+};
+use eth_client::methods::EthClient;
+use web3::types::Address;
+
+macro_rules! info {
+    ($($arg:tt)+) => { tracing::info!(target: crate::logs::BRIDGE_CONTRACT_TARGET, $($arg)+) }
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => { tracing::warn!(target: crate::logs::BRIDGE_CONTRACT_TARGET, $($arg)+) }
+}
+
+pub type SafeBridgeContract = std::sync::Arc<tokio::sync::Mutex<BridgeContract>>;
+
+/// The implementation behind the bridge proxy and the ABI used to call it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BridgeContract {
+    pub implementation: Address,
+    pub abi: std::sync::Arc<String>,
+    /// The transfer submission is paused after the implementation change until the ABI is reloaded
+    pub paused: bool,
+}
+
+impl BridgeContract {
+    pub fn new(implementation: Address, abi: String) -> Self {
+        BridgeContract {
+            implementation,
+            abi: std::sync::Arc::new(abi),
+            paused: false,
+        }
+    }
+
+    /// Pauses the transfer submission if the implementation is changed, returns `true` in this case
+    pub fn update_implementation(&mut self, implementation: Address) -> bool {
+        if implementation == self.implementation {
+            return false;
+        }
+
+        self.implementation = implementation;
+        self.paused = true;
+        true
+    }
+
+    /// Resumes the transfer submission with the ABI loaded for the `implementation`, returns `false`
+    /// if the implementation is changed again meanwhile
+    pub fn resume(&mut self, implementation: Address, abi: String) -> bool {
+        if implementation != self.implementation {
+            return false;
+        }
+
+        self.abi = std::sync::Arc::new(abi);
+        self.paused = false;
+        true
+    }
+
+    /// The `TransferTokens` event of the loaded ABI, the emitted events are decoded with it
+    pub fn transfer_tokens_event(&self) -> Result<web3::ethabi::Event, String> {
+        transfer_tokens_event(&self.abi)
+    }
+}
+
+/// Discovers the implementation behind the proxy and loads its ABI, panics on failure
+pub async fn init(settings: &Settings, eth_client: &EthClient) -> BridgeContract {
+    let implementation = get_implementation(settings, eth_client)
+        .await
+        .expect("Failed to get the bridge implementation address");
+    info!("Bridge implementation: {:?}", implementation);
+
+    if let Some(bridge_impl_address) = settings.eth.bridge_impl_address {
+        if bridge_impl_address != implementation {
+            warn!(
+                "The configured `bridge_impl_address` {:?} is ignored, the proxy implementation is {:?}",
+                bridge_impl_address, implementation
+            );
+        }
+    }
+
+    let abi = load_abi(settings, implementation)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    BRIDGE_TRANSFER_SUBMISSION_PAUSED.set(0);
+    BridgeContract::new(implementation, abi)
+}
+
+async fn get_implementation(
+    settings: &Settings,
+    eth_client: &EthClient,
+) -> web3::contract::Result<Address> {
+    eth_client
+        .get_proxy_implementation(settings.eth.bridge_proxy_address)
+        .await
+}
+
+/// The result of the ABI check against the implementation verified on Etherscan
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum AbiVerification {
+    Verified,
+    /// The ABI isn't checked, the reason is given
+    Unverified(String),
+}
+
+/// Reads the ABI from `eth.abi_path` or the embedded one
+fn read_abi(settings: &Settings) -> Result<String, String> {
+    let abi = match &settings.eth.abi_path {
+        Some(abi_path) => std::fs::read_to_string(abi_path)
+            .map_err(|err| format!("Failed to read the bridge ABI {}: {}", abi_path, err))?,
+        None => eth_client::fast_bridge::FAST_BRIDGE_ABI.to_string(),
+    };
+    eth_client::fast_bridge::load_abi(abi.as_bytes())
+        .map_err(|err| format!("Invalid bridge ABI: {}", err))?;
+    transfer_tokens_event(&abi)?;
+    Ok(abi)
+}
+
+fn transfer_tokens_event(abi: &str) -> Result<web3::ethabi::Event, String> {
+    eth_client::fast_bridge::transfer_tokens_event(abi.as_bytes()).map_err(|err| {
+        format!(
+            "The bridge ABI doesn't contain the `TransferTokens` event: {}",
+            err
+        )
+    })
+}
+
+/// Compares the ABI with the ABI verified on Etherscan for the `implementation` if `verify_abi` is
+/// enabled, returns the error if they differ
+async fn verify_abi(
+    settings: &Settings,
+    implementation: Address,
+    abi: &str,
+) -> Result<AbiVerification, String> {
+    if !settings.etherscan_api.verify_abi {
+        return Ok(AbiVerification::Unverified(
+            "the ABI verification is disabled".to_string(),
+        ));
+    }
+
+    let on_chain_abi = match eth_client::methods::get_contract_abi(
+        settings.etherscan_api.endpoint_url.as_ref(),
+        implementation,
+        &settings.etherscan_api.api_key,
+    )
+    .await
+    {
+        Ok(on_chain_abi) => on_chain_abi,
+        Err(err) => {
+            return Ok(AbiVerification::Unverified(format!(
+                "failed to get the bridge ABI from Etherscan: {}",
+                err
+            )))
+        }
+    };
+
+    let hash = eth_client::fast_bridge::verify_abi(abi.as_bytes(), on_chain_abi.as_bytes())
+        .map_err(|err| {
+            format!(
+                "The bridge ABI doesn't match the implementation {:?}: {}",
+                implementation, err
+            )
+        })?;
+    info!("The bridge ABI is verified, hash: {:?}", hash);
+    Ok(AbiVerification::Verified)
+}
+
+/// Loads the ABI from `eth.abi_path` or the embedded one and compares it with the ABI
+/// verified on Etherscan for the `implementation` if `verify_abi` is enabled
+pub async fn load_abi(settings: &Settings, implementation: Address) -> Result<String, String> {
+    let abi = read_abi(settings)?;
+    if let AbiVerification::Unverified(reason) = verify_abi(settings, implementation, &abi).await? {
+        if settings.etherscan_api.verify_abi {
+            warn!("The bridge ABI isn't verified: {}", reason);
+        }
+    }
+    Ok(abi)
+}
+
+/// The ABI for the upgraded `implementation`. It is accepted if it is verified on Etherscan or, when
+/// it can't be verified, if the operator acknowledges the upgrade with `eth.bridge_impl_address`.
+pub async fn load_upgraded_abi(
+    settings: &Settings,
+    implementation: Address,
+) -> Result<String, String> {
+    let abi = read_abi(settings)?;
+    match verify_abi(settings, implementation, &abi).await? {
+        AbiVerification::Verified => Ok(abi),
+        AbiVerification::Unverified(_)
+            if settings.eth.bridge_impl_address == Some(implementation) =>
+        {
+            info!(
+                "The upgrade to the implementation {:?} is acknowledged by `eth.bridge_impl_address`",
+                implementation
+            );
+            Ok(abi)
+        }
+        AbiVerification::Unverified(reason) => Err(format!(
+            "{}, set `eth.bridge_impl_address` to {:?} to acknowledge the upgrade",
+            reason, implementation
+        )),
+    }
+}
+
+/// Watches the EIP-1967 implementation slot of the bridge proxy. On the upgrade the transfer
+/// submission is paused until the ABI is verified for the new implementation or the operator
+/// acknowledges the upgrade.
+pub async fn bridge_contract_worker(
+    settings: SafeSettings,
+    eth_client: EthClient,
+    bridge_contract: SafeBridgeContract,
+) {
+    loop {
+        let settings = settings.lock().await.clone();
+
+        let mut interval =
+            crate::utils::request_interval(settings.eth.implementation_check_interval_secs).await;
+        interval.tick().await;
+
+        let implementation = match get_implementation(&settings, &eth_client).await {
+            Ok(implementation) => implementation,
+            Err(err) => {
+                warn!("Failed to get the bridge implementation address: {:?}", err);
+                continue;
+            }
+        };
+
+        let paused = {
+            let mut bridge_contract = bridge_contract.lock().await;
+            let old_implementation = bridge_contract.implementation;
+            if bridge_contract.update_implementation(implementation) {
+                warn!(
+                    "Bridge implementation is changed from {:?} to {:?}, the transfer submission is paused",
+                    old_implementation, implementation
+                );
+                BRIDGE_IMPLEMENTATION_CHANGES_COUNT.inc();
+                BRIDGE_TRANSFER_SUBMISSION_PAUSED.set(1);
+            }
+            bridge_contract.paused
+        };
+
+        if paused {
+            match load_upgraded_abi(&settings, implementation).await {
+                Ok(abi) => {
+                    if bridge_contract.lock().await.resume(implementation, abi) {
+                        BRIDGE_TRANSFER_SUBMISSION_PAUSED.set(0);
+                        info!(
+                            "The bridge ABI is reloaded for the implementation {:?}, the transfer submission is resumed",
+                            implementation
+                        );
+                    }
+                }
+                Err(err) => warn!("The transfer submission stays paused: {}", err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::bridge_contract::{load_upgraded_abi, BridgeContract};
+    use crate::test_utils::get_settings;
+    use web3::types::Address;
+
+    #[test]
+    fn update_implementation_test() {
+        let mut bridge_contract = BridgeContract::new(Address::repeat_byte(1), "[]".to_string());
+
+        assert!(!bridge_contract.update_implementation(Address::repeat_byte(1)));
+        assert!(!bridge_contract.paused);
+
+        assert!(bridge_contract.update_implementation(Address::repeat_byte(2)));
+        assert!(bridge_contract.paused);
+        assert_eq!(bridge_contract.implementation, Address::repeat_byte(2));
+
+        // Stays paused until the ABI is reloaded
+        assert!(!bridge_contract.update_implementation(Address::repeat_byte(2)));
+        assert!(bridge_contract.paused);
+
+        // The ABI loaded for the previous implementation isn't used
+        assert!(!bridge_contract.resume(Address::repeat_byte(1), "[{}]".to_string()));
+        assert!(bridge_contract.paused);
+
+        assert!(bridge_contract.resume(Address::repeat_byte(2), "[{}]".to_string()));
+        assert!(!bridge_contract.paused);
+        assert_eq!(bridge_contract.abi.as_str(), "[{}]");
+    }
+
+    #[test]
+    fn transfer_tokens_event_test() {
+        let bridge_contract = BridgeContract::new(Address::repeat_byte(1), "[]".to_string());
+        assert!(bridge_contract.transfer_tokens_event().is_err());
+
+        let bridge_contract = BridgeContract::new(
+            Address::repeat_byte(1),
+            eth_client::fast_bridge::FAST_BRIDGE_ABI.to_string(),
+        );
+        assert_eq!(
+            bridge_contract.transfer_tokens_event().unwrap().name,
+            eth_client::fast_bridge::TRANSFER_TOKENS_EVENT
+        );
+    }
+
+    #[tokio::test]
+    async fn load_upgraded_abi_test() {
+        let mut settings = get_settings();
+        settings.etherscan_api.verify_abi = false;
+        settings.eth.abi_path = None;
+        settings.eth.bridge_impl_address = None;
+
+        // The unverified ABI isn't accepted without the acknowledgement
+        let err = load_upgraded_abi(&settings, Address::repeat_byte(2))
+            .await
+            .unwrap_err();
+        assert!(err.contains("the ABI verification is disabled"));
+
+        settings.eth.bridge_impl_address = Some(Address::repeat_byte(1));
+        assert!(load_upgraded_abi(&settings, Address::repeat_byte(2))
+            .await
+            .is_err());
+
+        settings.eth.bridge_impl_address = Some(Address::repeat_byte(2));
+        assert_eq!(
+            load_upgraded_abi(&settings, Address::repeat_byte(2))
+                .await
+                .unwrap(),
+            eth_client::fast_bridge::FAST_BRIDGE_ABI
+        );
+    }
+}
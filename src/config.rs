@@ -2,7 +2,7 @@ use dotenv::dotenv;
 use std::{env, fs};
 use url::Url;
 
-#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(try_from = "u32")]
 pub struct Decimals(u32);
 
@@ -42,14 +42,74 @@ pub struct NearTokensWhitelist {
     pub mapping: std::collections::HashMap<near_sdk::AccountId, NearTokenInfo>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwapQuote {
+    Usd,
+    Eth,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PriceSource {
+    Coingecko,
+    Chainlink {
+        aggregator: web3::types::Address,
+        max_staleness_secs: Option<u64>,
+    },
+    UniswapV3Twap {
+        pool: web3::types::Address,
+        twap_interval_secs: u32,
+        base_is_token0: bool,
+        token0_decimals: Decimals,
+        token1_decimals: Decimals,
+        quote: TwapQuote,
+    },
+}
+
+impl Default for PriceSource {
+    fn default() -> Self {
+        PriceSource::Coingecko
+    }
+}
+
+/// Percent fee applied to the transfers with amount starting from `min_amount`
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FeeTier {
+    pub min_amount: near_sdk::json_types::U128,
+    pub percent_fee: f64,
+}
+
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct NearTokenInfo {
+    #[serde(default = "default_token_enabled")]
+    pub enabled: bool,
     pub exchange_id: String,
+    #[serde(default)]
+    pub price_source: PriceSource,
     pub fixed_fee: near_sdk::json_types::U128,
     pub percent_fee: f64,
+    #[serde(default)]
+    pub fee_tiers: Vec<FeeTier>,
     pub decimals: Decimals,
     pub eth_address: web3::types::Address,
+    pub min_transfer_amount: Option<near_sdk::json_types::U128>,
     pub max_transfer_amount: Option<near_sdk::json_types::U128>,
+    /// Min profit in USD, overrides the global `profit_thershold`
+    pub profit_threshold: Option<f64>,
+    /// Min profit in percent of the transferred amount value
+    pub min_profit_percent: Option<f64>,
+}
+
+impl NearTokenInfo {
+    /// The percent fee of the highest tier reached by the amount, `percent_fee` if none is reached
+    pub fn percent_fee_for(&self, token_amount: u128) -> f64 {
+        self.fee_tiers
+            .iter()
+            .filter(|tier| tier.min_amount.0 <= token_amount)
+            .max_by_key(|tier| tier.min_amount.0)
+            .map_or(self.percent_fee, |tier| tier.percent_fee)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -59,6 +119,104 @@ pub enum NearNetwork {
     Testnet,
 }
 
+/// Known deployment the settings are filled from, the fields set in the config override the preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkPreset {
+    Mainnet,
+    Sepolia,
+    Holesky,
+}
+
+/// The bridge contracts are deployed by every relayer operator, so the presets don't include their
+/// addresses. The light client account is known only for mainnet.
+struct NetworkPresetValues {
+    chain_id: u64,
+    etherscan_api_endpoint_url: &'static str,
+    near_network: NearNetwork,
+    near_rpc_url: &'static str,
+    light_client_account_id: Option<&'static str>,
+}
+
+impl NetworkPreset {
+    fn values(self) -> NetworkPresetValues {
+        match self {
+            NetworkPreset::Mainnet => NetworkPresetValues {
+                chain_id: 1,
+                etherscan_api_endpoint_url: "https://api.etherscan.io",
+                near_network: NearNetwork::Mainnet,
+                near_rpc_url: "https://rpc.mainnet.near.org",
+                light_client_account_id: Some("client-eth2.bridge.near"),
+            },
+            NetworkPreset::Sepolia => NetworkPresetValues {
+                chain_id: 11155111,
+                etherscan_api_endpoint_url: "https://api-sepolia.etherscan.io",
+                near_network: NearNetwork::Testnet,
+                near_rpc_url: "https://rpc.testnet.near.org",
+                light_client_account_id: None,
+            },
+            NetworkPreset::Holesky => NetworkPresetValues {
+                chain_id: 17000,
+                etherscan_api_endpoint_url: "https://api-holesky.etherscan.io",
+                near_network: NearNetwork::Testnet,
+                near_rpc_url: "https://rpc.testnet.near.org",
+                light_client_account_id: None,
+            },
+        }
+    }
+
+    pub fn chain_id(self) -> u64 {
+        self.values().chain_id
+    }
+
+    /// The preset as a config value, the light client account is omitted if it's not known
+    pub fn to_value(self) -> serde_json::Value {
+        let values = self.values();
+        let mut value = serde_json::json!({
+            "eth": {
+                "chain_id": values.chain_id,
+            },
+            "etherscan_api": {
+                "endpoint_url": values.etherscan_api_endpoint_url,
+            },
+            "near": {
+                "near_network": values.near_network,
+                "rpc_url": values.near_rpc_url,
+            },
+            "last_block_number_worker": {
+                "server_addr": values.near_rpc_url,
+            },
+            "unlock_tokens_worker": {
+                "server_addr": values.near_rpc_url,
+            },
+        });
+
+        if let Some(light_client_account_id) = values.light_client_account_id {
+            value["last_block_number_worker"]["contract_account_id"] =
+                light_client_account_id.into();
+        }
+
+        value
+    }
+}
+
+/// Deep merge of the objects, the `overrides` values replace the `base` ones
+fn merge(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
 impl NearTokensWhitelist {
     pub fn get_token_info(
         &self,
@@ -72,6 +230,9 @@ impl NearTokensWhitelist {
 pub struct EtherscanAPISettings {
     pub endpoint_url: url::Url,
     pub api_key: String,
+    /// Compare the bridge ABI with the verified contract ABI at startup
+    #[serde(default)]
+    pub verify_abi: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -87,28 +248,131 @@ pub struct UnlockTokensWorkerSettings {
     pub contract_account_id: String,
     pub request_interval_secs: u64,
     pub blocks_for_tx_finalization: u64,
+    #[serde(default = "default_lp_unlock_gas")]
+    pub gas: u64,
+    pub expected_gas_burnt: Option<u64>,
+    /// The number of `lp_unlock` transactions sent in parallel
+    #[serde(default = "default_max_concurrent_unlocks")]
+    pub max_concurrent_unlocks: usize,
+    /// The number of `lp_unlock` calls packed in one transaction, `gas` is attached to every call
+    #[serde(default = "default_max_unlocks_per_tx")]
+    pub max_unlocks_per_tx: usize,
+    /// The gas budget of the transaction with several `lp_unlock` calls
+    #[serde(default = "default_max_gas_per_tx")]
+    pub max_gas_per_tx: u64,
+    /// The transfer is moved out of the queue after this number of failed `lp_unlock` calls
+    #[serde(default = "default_max_unlock_attempts")]
+    pub max_unlock_attempts: u32,
+    /// The failed unlock is retried after this delay multiplied by the number of attempts
+    #[serde(default = "default_unlock_retry_delay_secs")]
+    pub unlock_retry_delay_secs: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CapitalCostSettings {
+    pub annual_rate: f64,
+    #[serde(default = "default_eth_block_time_secs")]
+    pub eth_block_time_secs: u64,
+    #[serde(default = "default_unlock_delay_secs")]
+    pub default_unlock_delay_secs: u64,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct EthSettings {
+    /// The relayer refuses to start if the RPC `eth_chainId` is different
+    pub chain_id: Option<u64>,
     pub bridge_proxy_address: web3::types::Address,
-    pub bridge_impl_address: web3::types::Address,
+    /// The implementation is read from the EIP-1967 slot of the proxy, this address acknowledges the
+    /// upgrade to it when the ABI can't be verified on Etherscan
+    pub bridge_impl_address: Option<web3::types::Address>,
+    /// The bridge ABI file, the ABI embedded in `eth_client` is used by default
+    pub abi_path: Option<String>,
     pub private_key: Option<String>,
     pub rpc_url: Url,
+    /// The endpoints used when `rpc_url` fails or is rate limited
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<Url>,
+    /// The number of endpoints which should return the same transaction receipt
+    pub receipt_quorum: Option<usize>,
+    /// The `newHeads` subscription endpoint, the latest block is polled over HTTP without it
+    pub ws_url: Option<Url>,
+    #[serde(default = "default_head_poll_interval_secs")]
+    pub head_poll_interval_secs: u64,
     #[serde(default)]
     pub pending_transaction_poll_delay_sec: u32,
     pub rainbow_bridge_index_js_path: String,
+    #[serde(default = "default_implementation_check_interval_secs")]
+    pub implementation_check_interval_secs: u64,
+}
+
+impl EthSettings {
+    /// `rpc_url` followed by the fallback endpoints, the duplicates are skipped
+    pub fn rpc_urls(&self) -> Vec<Url> {
+        let mut rpc_urls = vec![self.rpc_url.clone()];
+        for url in &self.fallback_rpc_urls {
+            if !rpc_urls.contains(url) {
+                rpc_urls.push(url.clone());
+            }
+        }
+        rpc_urls
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct NearSettings {
     pub near_credentials_path: Option<String>,
+    /// The credentials of the additional function call access keys of the relayer account,
+    /// the transactions are signed with all the keys in turn
+    #[serde(default)]
+    pub function_call_key_paths: Vec<String>,
     pub rpc_url: Url,
+    /// The endpoints used when `rpc_url` or the `server_addr` of a worker fails
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<Url>,
     pub contract_address: near_lake_framework::near_indexer_primitives::types::AccountId,
     pub near_lake_init_block: u64,
     pub near_network: NearNetwork,
 }
 
+impl NearSettings {
+    /// `rpc_url` followed by the fallback endpoints, the duplicates are skipped
+    pub fn rpc_urls(&self) -> Vec<Url> {
+        self.rpc_urls_with(&self.rpc_url)
+    }
+
+    /// `primary` followed by the fallback endpoints, the duplicates are skipped
+    pub fn rpc_urls_with(&self, primary: &Url) -> Vec<Url> {
+        let mut rpc_urls = vec![primary.clone()];
+        for url in &self.fallback_rpc_urls {
+            if !rpc_urls.contains(url) {
+                rpc_urls.push(url.clone());
+            }
+        }
+        rpc_urls
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FeeOracleLevelSettings {
+    pub reward_percentile: f64,
+    pub base_fee_multiplier: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FeeOracleSettings {
+    #[serde(default = "default_fee_history_blocks")]
+    pub fee_history_blocks: u64,
+    pub low: FeeOracleLevelSettings,
+    pub medium: FeeOracleLevelSettings,
+    pub high: FeeOracleLevelSettings,
+    /// The transfers with less blocks left before `valid_till_block_height` use the medium level
+    pub medium_urgency_blocks_left: u64,
+    /// The transfers with less blocks left before `valid_till_block_height` use the high level
+    pub high_urgency_blocks_left: u64,
+    pub min_priority_fee_per_gas: Option<web3::types::U256>,
+    pub max_fee_per_gas: Option<web3::types::U256>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct RedisSettings {
     pub url: Url,
@@ -118,11 +382,20 @@ pub type SafeSettings = std::sync::Arc<tokio::sync::Mutex<Settings>>;
 
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Settings {
+    pub network: Option<NetworkPreset>,
     pub eth: EthSettings,
     pub near: NearSettings,
     pub redis: RedisSettings,
     pub profit_thershold: Option<f64>,
+    #[serde(default)]
+    pub eth_price_source: PriceSource,
+    #[serde(default)]
+    pub near_price_source: PriceSource,
+    pub capital_cost: Option<CapitalCostSettings>,
     pub max_priority_fee_per_gas: Option<web3::types::U256>,
+    pub fee_oracle: Option<FeeOracleSettings>,
+    #[serde(default)]
+    pub worst_case_profit_estimation: bool,
     pub min_time_before_unlock_in_sec: Option<u64>,
     pub min_blocks_before_unlock: Option<u64>,
     pub vault_addr: Url,
@@ -134,6 +407,11 @@ pub struct Settings {
     pub near_tokens_whitelist: NearTokensWhitelist,
     #[serde(default = "default_rpc_timeout_secs")]
     pub rpc_timeout_secs: u64,
+    /// Retries of the Ethereum read requests failed with a transport error
+    #[serde(default = "default_rpc_max_retries")]
+    pub rpc_max_retries: u32,
+    #[serde(default = "default_rpc_retry_backoff_ms")]
+    pub rpc_retry_backoff_ms: u64,
     pub prometheus_metrics_port: Option<u16>,
 }
 
@@ -141,6 +419,62 @@ pub fn default_rpc_timeout_secs() -> u64 {
     30
 }
 
+pub fn default_rpc_max_retries() -> u32 {
+    2
+}
+
+pub fn default_rpc_retry_backoff_ms() -> u64 {
+    500
+}
+
+pub fn default_implementation_check_interval_secs() -> u64 {
+    60
+}
+
+pub fn default_head_poll_interval_secs() -> u64 {
+    3
+}
+
+pub fn default_fee_history_blocks() -> u64 {
+    20
+}
+
+pub fn default_token_enabled() -> bool {
+    true
+}
+
+pub fn default_lp_unlock_gas() -> u64 {
+    300_000_000_000_000
+}
+
+pub fn default_max_concurrent_unlocks() -> usize {
+    4
+}
+
+pub fn default_max_unlocks_per_tx() -> usize {
+    1
+}
+
+pub fn default_max_gas_per_tx() -> u64 {
+    300_000_000_000_000
+}
+
+pub fn default_max_unlock_attempts() -> u32 {
+    5
+}
+
+pub fn default_unlock_retry_delay_secs() -> u64 {
+    60
+}
+
+pub fn default_eth_block_time_secs() -> u64 {
+    12
+}
+
+pub fn default_unlock_delay_secs() -> u64 {
+    4 * 60 * 60
+}
+
 impl Settings {
     pub fn init(file_path: String) -> Result<Settings, String> {
         let path = std::path::Path::new(&file_path);
@@ -148,46 +482,158 @@ impl Settings {
             return Err("Given config path doesn't exist".to_string());
         }
 
-        let file = fs::File::open(path).map_err(|e| e.to_string())?;
-        let reader = std::io::BufReader::new(file);
-
         dotenv().ok();
 
-        // Read the JSON contents of the file as an instance of `User`.
-        let mut config: Settings = serde_json::from_reader(reader).map_err(|e| e.to_string())?;
-        config.config_path = file_path;
-        if let Some(eth_private_key) = config.eth.private_key {
-            config.eth.private_key = Some(eth_private_key.replace(
-                "${FAST_BRIDGE_ETH_PRIVATE_KEY}",
-                &env::var("FAST_BRIDGE_ETH_PRIVATE_KEY").unwrap_or("".to_string()),
-            ));
+        let mut value: serde_json::Value = config::Config::builder()
+            .add_source(config::File::new(
+                &file_path,
+                config_file_format(&file_path),
+            ))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| e.to_string())?;
+        interpolate_value(&mut value, "")?;
+
+        if let Some(network) = value.get("network").cloned() {
+            let network: NetworkPreset =
+                serde_json::from_value(network).map_err(|e| format!("network: {}", e))?;
+            let mut preset = network.to_value();
+            merge(&mut preset, value);
+            value = preset;
         }
-        config.eth.rpc_url = url::Url::parse(&config.eth.rpc_url.as_str().replace(
-            "FAST_BRIDGE_INFURA_PROJECT_ID",
-            &env::var("FAST_BRIDGE_INFURA_PROJECT_ID").unwrap_or("".to_string()),
-        ))
-        .unwrap();
-        config.etherscan_api.api_key = config.etherscan_api.api_key.replace(
-            "${FAST_BRIDGE_ETHERSCAN_API_KEY}",
-            &env::var("FAST_BRIDGE_ETHERSCAN_API_KEY").unwrap_or("".to_string()),
-        );
+
+        let mut config: Settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        config.config_path = file_path;
 
         Ok(config)
     }
+
+    pub fn rpc_retry_policy(&self) -> eth_client::methods::RetryPolicy {
+        eth_client::methods::RetryPolicy {
+            max_retries: self.rpc_max_retries,
+            backoff: std::time::Duration::from_millis(self.rpc_retry_backoff_ms),
+        }
+    }
+}
+
+/// The format by the first known extension from the end, so `config.json.example` is JSON
+fn config_file_format(file_path: &str) -> config::FileFormat {
+    let file_name = std::path::Path::new(file_path)
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    file_name
+        .rsplit('.')
+        .find_map(|extension| match extension {
+            "json" => Some(config::FileFormat::Json),
+            "toml" => Some(config::FileFormat::Toml),
+            "yaml" | "yml" => Some(config::FileFormat::Yaml),
+            _ => None,
+        })
+        .unwrap_or(config::FileFormat::Json)
+}
+
+fn interpolate_value(value: &mut serde_json::Value, path: &str) -> Result<(), String> {
+    match value {
+        serde_json::Value::String(string) => *string = interpolate(string, path)?,
+        serde_json::Value::Array(array) => {
+            for (index, item) in array.iter_mut().enumerate() {
+                interpolate_value(item, &format!("{}[{}]", path, index))?;
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (key, item) in object.iter_mut() {
+                let item_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                interpolate_value(item, &item_path)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Substitutes `${VAR}`, `${VAR:-default}` with the environment variables and `${file:/path}` with
+/// the file content without the trailing newline. `$${` is kept as `${`.
+pub fn interpolate(value: &str, path: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(position) = rest.find("${") {
+        if rest[..position].ends_with('$') {
+            result.push_str(&rest[..position - 1]);
+            result.push_str("${");
+            rest = &rest[position + 2..];
+            continue;
+        }
+
+        result.push_str(&rest[..position]);
+        let expression_len = rest[position + 2..]
+            .find('}')
+            .ok_or(format!("Unclosed `${{` in `{}`", path))?;
+        let expression = &rest[position + 2..position + 2 + expression_len];
+        result.push_str(&resolve(expression, path)?);
+        rest = &rest[position + 2 + expression_len + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+fn resolve(expression: &str, path: &str) -> Result<String, String> {
+    if let Some(file_path) = expression.strip_prefix("file:") {
+        return fs::read_to_string(file_path)
+            .map(|content| content.trim_end_matches(&['\n', '\r'][..]).to_string())
+            .map_err(|e| {
+                format!(
+                    "Failed to read the file `{}` referenced in `{}`: {}",
+                    file_path, path, e
+                )
+            });
+    }
+
+    match expression.split_once(":-") {
+        Some((variable, default)) => Ok(env::var(variable)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| default.to_string())),
+        None => env::var(expression).map_err(|_| {
+            format!(
+                "The environment variable `{}` referenced in `{}` is not set",
+                expression, path
+            )
+        }),
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use crate::config::Settings;
+    use crate::config::{
+        config_file_format, interpolate, merge, FeeTier, NearNetwork, NetworkPreset, Settings,
+    };
+    use crate::test_utils::{init_config_env, ENV_LOCK};
 
-    #[tokio::test]
-    async fn smoke_init_test() {
+    #[test]
+    fn init_test() {
+        init_config_env();
         let config_path = "config.json.example";
         let settings = Settings::init(config_path.to_string()).unwrap();
 
-        assert_ne!(
-            settings.eth.rpc_url,
-            url::Url::parse("https://goerli.infura.io/v3/FAST_BRIDGE_INFURA_PROJECT_ID").unwrap()
+        let project_id = std::env::var("FAST_BRIDGE_INFURA_PROJECT_ID").unwrap();
+        assert_eq!(
+            settings.eth.rpc_url.as_str(),
+            format!("https://goerli.infura.io/v3/{}", project_id)
+        );
+        assert_eq!(settings.network, None);
+        assert_eq!(settings.eth.chain_id, Some(5));
+        assert_eq!(
+            settings.etherscan_api.api_key,
+            std::env::var("FAST_BRIDGE_ETHERSCAN_API_KEY").unwrap()
         );
         assert_eq!(settings.config_path, config_path);
         assert_eq!(
@@ -205,4 +651,155 @@ pub mod tests {
             340282366920938463463374607431768211455u128
         );
     }
+
+    #[test]
+    fn percent_fee_for_test() {
+        let mut token_info = crate::test_utils::get_settings()
+            .near_tokens_whitelist
+            .get_token_info(near_client::test_utils::NEAR_TOKEN_ADDRESS.parse().unwrap())
+            .unwrap();
+        token_info.percent_fee = 0.01;
+        assert_eq!(token_info.percent_fee_for(1_000), 0.01);
+
+        token_info.fee_tiers = vec![
+            FeeTier {
+                min_amount: 1_000_000.into(),
+                percent_fee: 0.001,
+            },
+            FeeTier {
+                min_amount: 1_000.into(),
+                percent_fee: 0.005,
+            },
+        ];
+        assert_eq!(token_info.percent_fee_for(999), 0.01);
+        assert_eq!(token_info.percent_fee_for(1_000), 0.005);
+        assert_eq!(token_info.percent_fee_for(999_999), 0.005);
+        assert_eq!(token_info.percent_fee_for(u128::MAX), 0.001);
+    }
+
+    #[test]
+    fn interpolate_test() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        std::env::set_var("FAST_BRIDGE_TEST_INTERPOLATE_HOST", "example.com");
+        std::env::set_var("FAST_BRIDGE_TEST_INTERPOLATE_EMPTY", "");
+        std::env::remove_var("FAST_BRIDGE_TEST_INTERPOLATE_UNSET");
+
+        assert_eq!(
+            interpolate("https://${FAST_BRIDGE_TEST_INTERPOLATE_HOST}/v3", "url").unwrap(),
+            "https://example.com/v3"
+        );
+        assert_eq!(
+            interpolate("${FAST_BRIDGE_TEST_INTERPOLATE_UNSET:-8545}", "port").unwrap(),
+            "8545"
+        );
+        assert_eq!(
+            interpolate("${FAST_BRIDGE_TEST_INTERPOLATE_EMPTY:-default}", "value").unwrap(),
+            "default"
+        );
+        assert_eq!(
+            interpolate("${FAST_BRIDGE_TEST_INTERPOLATE_EMPTY}", "value").unwrap(),
+            ""
+        );
+        assert_eq!(
+            interpolate("$${NOT_A_VARIABLE}", "value").unwrap(),
+            "${NOT_A_VARIABLE}"
+        );
+        assert_eq!(
+            interpolate("no variables", "value").unwrap(),
+            "no variables"
+        );
+
+        let err =
+            interpolate("${FAST_BRIDGE_TEST_INTERPOLATE_UNSET}", "eth.private_key").unwrap_err();
+        assert!(err.contains("FAST_BRIDGE_TEST_INTERPOLATE_UNSET"));
+        assert!(err.contains("eth.private_key"));
+        assert!(interpolate("${FAST_BRIDGE_TEST_INTERPOLATE_HOST", "url").is_err());
+    }
+
+    #[test]
+    fn interpolate_file_test() {
+        let secret_path = std::env::temp_dir().join("fast_bridge_interpolate_file_test");
+        std::fs::write(&secret_path, "secret\n").unwrap();
+
+        assert_eq!(
+            interpolate(&format!("${{file:{}}}", secret_path.display()), "key").unwrap(),
+            "secret"
+        );
+        assert!(interpolate("${file:/nonexistent/fast_bridge_secret}", "key").is_err());
+
+        std::fs::remove_file(secret_path).unwrap();
+    }
+
+    #[test]
+    fn config_file_format_test() {
+        assert_eq!(
+            config_file_format("config.json.example"),
+            config::FileFormat::Json
+        );
+        assert_eq!(
+            config_file_format("/etc/relayer/config.toml"),
+            config::FileFormat::Toml
+        );
+        assert_eq!(config_file_format("config.yml"), config::FileFormat::Yaml);
+        assert_eq!(config_file_format("config.YAML"), config::FileFormat::Yaml);
+        assert_eq!(config_file_format("config"), config::FileFormat::Json);
+    }
+
+    #[test]
+    fn merge_test() {
+        let mut base = serde_json::json!({"a": {"b": 1, "c": 2}, "d": [1, 2], "e": "e"});
+        merge(
+            &mut base,
+            serde_json::json!({"a": {"c": 3, "f": 4}, "d": [3], "g": null}),
+        );
+
+        assert_eq!(
+            base,
+            serde_json::json!({"a": {"b": 1, "c": 3, "f": 4}, "d": [3], "e": "e", "g": null})
+        );
+    }
+
+    #[test]
+    fn network_preset_test() {
+        init_config_env();
+        let mut config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("config.json.example").unwrap()).unwrap();
+        config["network"] = "mainnet".into();
+        config["etherscan_api"]
+            .as_object_mut()
+            .unwrap()
+            .remove("endpoint_url");
+        config["near"]
+            .as_object_mut()
+            .unwrap()
+            .remove("near_network");
+        config["last_block_number_worker"]
+            .as_object_mut()
+            .unwrap()
+            .remove("contract_account_id");
+
+        let config_path = std::env::temp_dir().join("fast_bridge_network_preset_test.json");
+        std::fs::write(&config_path, config.to_string()).unwrap();
+        let settings = Settings::init(config_path.to_str().unwrap().to_string()).unwrap();
+        std::fs::remove_file(config_path).unwrap();
+
+        assert_eq!(settings.network, Some(NetworkPreset::Mainnet));
+        assert_eq!(settings.eth.chain_id, Some(1));
+        assert_eq!(
+            settings.etherscan_api.endpoint_url.as_str(),
+            "https://api.etherscan.io/"
+        );
+        assert_eq!(settings.near.near_network, NearNetwork::Mainnet);
+        assert_eq!(
+            settings.last_block_number_worker.contract_account_id,
+            "client-eth2.bridge.near"
+        );
+        // The fields from the config override the preset
+        assert_eq!(
+            settings.near.rpc_url.as_str(),
+            "https://rpc.testnet.near.org/"
+        );
+        assert_eq!(NetworkPreset::Sepolia.chain_id(), 11155111);
+        assert_eq!(NetworkPreset::Holesky.chain_id(), 17000);
+    }
 }
@@ -0,0 +1,667 @@
+use crate::config::{NearTokenInfo, PriceSource, Settings};
+use eth_client::methods::EthClient;
+use near_client::methods::NearClient;
+use near_jsonrpc_primitives::types::query::QueryResponseKind;
+
+// Max prepaid gas of a NEAR function call
+const MAX_NEAR_GAS: u64 = 300_000_000_000_000;
+const HIGH_PERCENT_FEE: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    /// JSON path of the checked value
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.path, self.message)
+    }
+}
+
+type Rule = fn(&Settings) -> Vec<ConfigIssue>;
+
+const RULES: &[Rule] = &[
+    check_network,
+    check_tokens,
+    check_vault_addr,
+    check_unlock_tokens_worker,
+    check_profit_settings,
+    check_fee_oracle,
+    check_rpc_timeout,
+    check_eth_rpc_urls,
+    check_near_rpc_urls,
+    check_near_function_call_keys,
+];
+
+/// Runs all the offline rules
+pub fn check(settings: &Settings) -> Vec<ConfigIssue> {
+    RULES.iter().flat_map(|rule| rule(settings)).collect()
+}
+
+pub fn has_errors(issues: &[ConfigIssue]) -> bool {
+    issues.iter().any(|issue| issue.severity == Severity::Error)
+}
+
+fn token_path(token: &near_sdk::AccountId) -> String {
+    format!("$.near_tokens_whitelist.mapping[\"{}\"]", token)
+}
+
+fn sorted_tokens(settings: &Settings) -> Vec<(&near_sdk::AccountId, &NearTokenInfo)> {
+    let mut tokens: Vec<_> = settings.near_tokens_whitelist.mapping.iter().collect();
+    tokens.sort_by(|(a, _), (b, _)| a.cmp(b));
+    tokens
+}
+
+fn check_percent_fee(path: String, percent_fee: f64, issues: &mut Vec<ConfigIssue>) {
+    if !percent_fee.is_finite() || !(0. ..1.).contains(&percent_fee) {
+        issues.push(ConfigIssue::error(
+            path,
+            format!(
+                "the percent fee is a fraction of the amount and must be in [0, 1), found {}",
+                percent_fee
+            ),
+        ));
+    } else if percent_fee > HIGH_PERCENT_FEE {
+        issues.push(ConfigIssue::warning(
+            path,
+            format!(
+                "the percent fee {} is more than 10% of the amount",
+                percent_fee
+            ),
+        ));
+    }
+}
+
+fn check_tokens(settings: &Settings) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+
+    for (token, token_info) in sorted_tokens(settings) {
+        let path = token_path(token);
+
+        if token_info.eth_address.is_zero() {
+            issues.push(ConfigIssue::error(
+                format!("{}.eth_address", path),
+                "the zero address",
+            ));
+        }
+
+        // The tokens bridged by the Rainbow Bridge are named `<eth address>.factory.bridge.near`
+        if let Some((eth_address, _)) = token.as_str().split_once('.') {
+            if eth_address.len() == 40 && eth_address.chars().all(|c| c.is_ascii_hexdigit()) {
+                let expected = format!("{:x}", token_info.eth_address);
+                if expected.trim_start_matches("0x") != eth_address.to_lowercase() {
+                    issues.push(ConfigIssue::error(
+                        format!("{}.eth_address", path),
+                        format!(
+                            "{:?} doesn't match the bridged token 0x{}",
+                            token_info.eth_address, eth_address
+                        ),
+                    ));
+                }
+            }
+        }
+
+        check_percent_fee(
+            format!("{}.percent_fee", path),
+            token_info.percent_fee,
+            &mut issues,
+        );
+
+        for (index, tier) in token_info.fee_tiers.iter().enumerate() {
+            let tier_path = format!("{}.fee_tiers[{}]", path, index);
+            check_percent_fee(
+                format!("{}.percent_fee", tier_path),
+                tier.percent_fee,
+                &mut issues,
+            );
+
+            if token_info.fee_tiers[..index]
+                .iter()
+                .any(|other| other.min_amount == tier.min_amount)
+            {
+                issues.push(ConfigIssue::error(
+                    format!("{}.min_amount", tier_path),
+                    format!("duplicated tier for the amount {}", tier.min_amount.0),
+                ));
+            }
+        }
+
+        if token_info.fixed_fee.0 == u128::MAX {
+            issues.push(ConfigIssue::warning(
+                format!("{}.fixed_fee", path),
+                "u128::MAX makes every transfer unprofitable, use `\"enabled\": false` to disable the token",
+            ));
+        }
+
+        if let (Some(min_amount), Some(max_amount)) = (
+            token_info.min_transfer_amount,
+            token_info.max_transfer_amount,
+        ) {
+            if min_amount.0 > max_amount.0 {
+                issues.push(ConfigIssue::error(
+                    format!("{}.min_transfer_amount", path),
+                    format!(
+                        "the min transfer amount {} is greater than the max transfer amount {}",
+                        min_amount.0, max_amount.0
+                    ),
+                ));
+            }
+        }
+
+        if token_info.exchange_id.is_empty() && token_info.price_source == PriceSource::Coingecko {
+            issues.push(ConfigIssue::error(
+                format!("{}.exchange_id", path),
+                "the CoinGecko coin id is required for the CoinGecko price source",
+            ));
+        }
+
+        if let Some(min_profit_percent) = token_info.min_profit_percent {
+            if !(0. ..=100.).contains(&min_profit_percent) {
+                issues.push(ConfigIssue::error(
+                    format!("{}.min_profit_percent", path),
+                    format!("must be in [0, 100], found {}", min_profit_percent),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+fn check_network(settings: &Settings) -> Vec<ConfigIssue> {
+    match (settings.network, settings.eth.chain_id) {
+        (Some(network), Some(chain_id)) if network.chain_id() != chain_id => {
+            vec![ConfigIssue::warning(
+                "$.eth.chain_id",
+                format!(
+                    "{} overrides the {:?} preset chain id {}",
+                    chain_id,
+                    network,
+                    network.chain_id()
+                ),
+            )]
+        }
+        (None, None) => vec![ConfigIssue::warning(
+            "$.eth.chain_id",
+            "the Ethereum network is not checked at startup without `network` or `eth.chain_id`",
+        )],
+        _ => vec![],
+    }
+}
+
+fn check_vault_addr(settings: &Settings) -> Vec<ConfigIssue> {
+    if settings.vault_addr.path().ends_with('/') {
+        vec![]
+    } else {
+        vec![ConfigIssue::error(
+            "$.vault_addr",
+            "must end with `/`, the key names are appended to it",
+        )]
+    }
+}
+
+fn check_unlock_tokens_worker(settings: &Settings) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    let worker = &settings.unlock_tokens_worker;
+
+    if worker.contract_account_id != settings.near.contract_address.as_str() {
+        issues.push(ConfigIssue::error(
+            "$.unlock_tokens_worker.contract_account_id",
+            format!(
+                "{} differs from `near.contract_address` {}",
+                worker.contract_account_id, settings.near.contract_address
+            ),
+        ));
+    }
+
+    if worker.gas > MAX_NEAR_GAS {
+        issues.push(ConfigIssue::error(
+            "$.unlock_tokens_worker.gas",
+            format!("exceeds the max prepaid gas {}", MAX_NEAR_GAS),
+        ));
+    }
+
+    if let Some(expected_gas_burnt) = worker.expected_gas_burnt {
+        if expected_gas_burnt > worker.gas {
+            issues.push(ConfigIssue::warning(
+                "$.unlock_tokens_worker.expected_gas_burnt",
+                "is greater than the attached gas",
+            ));
+        }
+    }
+
+    if worker.request_interval_secs == 0 {
+        issues.push(ConfigIssue::warning(
+            "$.unlock_tokens_worker.request_interval_secs",
+            "the zero interval makes the worker poll NEAR without a pause",
+        ));
+    }
+
+    if worker.max_gas_per_tx > MAX_NEAR_GAS {
+        issues.push(ConfigIssue::error(
+            "$.unlock_tokens_worker.max_gas_per_tx",
+            format!("exceeds the max prepaid gas {}", MAX_NEAR_GAS),
+        ));
+    }
+
+    if worker.max_unlocks_per_tx > 1 && worker.max_gas_per_tx < 2 * worker.gas {
+        issues.push(ConfigIssue::warning(
+            "$.unlock_tokens_worker.max_unlocks_per_tx",
+            "the gas budget `max_gas_per_tx` fits one `lp_unlock` call, reduce `gas` to batch the unlocks",
+        ));
+    }
+
+    if worker.max_concurrent_unlocks == 0 {
+        issues.push(ConfigIssue::error(
+            "$.unlock_tokens_worker.max_concurrent_unlocks",
+            "must be positive, the worker doesn't unlock anything",
+        ));
+    }
+
+    if worker.max_unlock_attempts == 0 {
+        issues.push(ConfigIssue::error(
+            "$.unlock_tokens_worker.max_unlock_attempts",
+            "must be positive, the transfers are never unlocked",
+        ));
+    }
+
+    issues
+}
+
+fn check_profit_settings(settings: &Settings) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+
+    if let Some(profit_threshold) = settings.profit_thershold {
+        if profit_threshold < 0. {
+            issues.push(ConfigIssue::warning(
+                "$.profit_thershold",
+                "the negative threshold allows unprofitable transfers",
+            ));
+        }
+    }
+
+    if let Some(capital_cost) = &settings.capital_cost {
+        if !capital_cost.annual_rate.is_finite() || capital_cost.annual_rate < 0. {
+            issues.push(ConfigIssue::error(
+                "$.capital_cost.annual_rate",
+                format!("must be non-negative, found {}", capital_cost.annual_rate),
+            ));
+        } else if capital_cost.annual_rate > 1. {
+            issues.push(ConfigIssue::warning(
+                "$.capital_cost.annual_rate",
+                format!(
+                    "the rate is a fraction per year, {} is more than 100%",
+                    capital_cost.annual_rate
+                ),
+            ));
+        }
+    }
+
+    issues
+}
+
+fn check_fee_oracle(settings: &Settings) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    let fee_oracle = match &settings.fee_oracle {
+        Some(fee_oracle) => fee_oracle,
+        None => return issues,
+    };
+
+    let levels = [
+        ("low", &fee_oracle.low),
+        ("medium", &fee_oracle.medium),
+        ("high", &fee_oracle.high),
+    ];
+    for (name, level) in levels {
+        if !(0. ..=100.).contains(&level.reward_percentile) {
+            issues.push(ConfigIssue::error(
+                format!("$.fee_oracle.{}.reward_percentile", name),
+                format!("must be in [0, 100], found {}", level.reward_percentile),
+            ));
+        }
+        if !level.base_fee_multiplier.is_finite() || level.base_fee_multiplier < 1. {
+            issues.push(ConfigIssue::warning(
+                format!("$.fee_oracle.{}.base_fee_multiplier", name),
+                "the transactions can't be included if the base fee grows",
+            ));
+        }
+    }
+
+    if fee_oracle.low.reward_percentile > fee_oracle.medium.reward_percentile
+        || fee_oracle.medium.reward_percentile > fee_oracle.high.reward_percentile
+    {
+        issues.push(ConfigIssue::error(
+            "$.fee_oracle",
+            "the reward percentiles must increase from `low` to `high`",
+        ));
+    }
+
+    if fee_oracle.high_urgency_blocks_left > fee_oracle.medium_urgency_blocks_left {
+        issues.push(ConfigIssue::error(
+            "$.fee_oracle.high_urgency_blocks_left",
+            "is greater than `medium_urgency_blocks_left`",
+        ));
+    }
+
+    if fee_oracle.fee_history_blocks == 0 {
+        issues.push(ConfigIssue::error(
+            "$.fee_oracle.fee_history_blocks",
+            "at least one block is required",
+        ));
+    }
+
+    issues
+}
+
+fn check_rpc_timeout(settings: &Settings) -> Vec<ConfigIssue> {
+    if settings.rpc_timeout_secs == 0 {
+        vec![ConfigIssue::error(
+            "$.rpc_timeout_secs",
+            "the zero timeout fails every request",
+        )]
+    } else {
+        vec![]
+    }
+}
+
+fn check_eth_rpc_urls(settings: &Settings) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    let rpc_urls = settings.eth.rpc_urls();
+
+    if rpc_urls.len() != settings.eth.fallback_rpc_urls.len() + 1 {
+        issues.push(ConfigIssue::warning(
+            "$.eth.fallback_rpc_urls",
+            "contains duplicated endpoints",
+        ));
+    }
+
+    if let Some(receipt_quorum) = settings.eth.receipt_quorum {
+        if receipt_quorum == 0 || receipt_quorum > rpc_urls.len() {
+            issues.push(ConfigIssue::error(
+                "$.eth.receipt_quorum",
+                format!("{} is out of range 1..={}", receipt_quorum, rpc_urls.len()),
+            ));
+        }
+    }
+
+    if let Some(ws_url) = &settings.eth.ws_url {
+        if ws_url.scheme() != "ws" && ws_url.scheme() != "wss" {
+            issues.push(ConfigIssue::error(
+                "$.eth.ws_url",
+                format!("the scheme should be ws or wss, not {}", ws_url.scheme()),
+            ));
+        }
+    }
+
+    if settings.eth.head_poll_interval_secs == 0 {
+        issues.push(ConfigIssue::error(
+            "$.eth.head_poll_interval_secs",
+            "the latest block can't be polled with the zero interval",
+        ));
+    }
+
+    issues
+}
+
+fn check_near_rpc_urls(settings: &Settings) -> Vec<ConfigIssue> {
+    if settings.near.rpc_urls().len() != settings.near.fallback_rpc_urls.len() + 1 {
+        vec![ConfigIssue::warning(
+            "$.near.fallback_rpc_urls",
+            "contains duplicated endpoints",
+        )]
+    } else {
+        vec![]
+    }
+}
+
+fn check_near_function_call_keys(settings: &Settings) -> Vec<ConfigIssue> {
+    let paths = &settings.near.function_call_key_paths;
+    let duplicated = paths.iter().enumerate().any(|(index, path)| {
+        paths[..index].contains(path) || settings.near.near_credentials_path.as_ref() == Some(path)
+    });
+
+    if duplicated {
+        vec![ConfigIssue::warning(
+            "$.near.function_call_key_paths",
+            "contains duplicated keys, the transactions signed with the same key can be rejected with an invalid nonce",
+        )]
+    } else {
+        vec![]
+    }
+}
+
+/// Compares the whitelist with the token contracts on Ethereum and NEAR
+pub async fn check_online(settings: &Settings) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+
+    let eth_client = match EthClient::new(
+        &settings.eth.rpc_urls(),
+        settings.rpc_timeout_secs,
+        settings.rpc_retry_policy(),
+    ) {
+        Ok(eth_client) => eth_client,
+        Err(err) => {
+            issues.push(ConfigIssue::error(
+                "$.eth.rpc_url",
+                format!("failed to create the Ethereum client: {:?}", err),
+            ));
+            return issues;
+        }
+    };
+
+    let near_client = match NearClient::new(&settings.near.rpc_urls(), settings.rpc_timeout_secs) {
+        Ok(near_client) => near_client,
+        Err(err) => {
+            issues.push(ConfigIssue::error(
+                "$.near.rpc_url",
+                format!("failed to create the NEAR client: {}", err),
+            ));
+            return issues;
+        }
+    };
+
+    for (token, token_info) in sorted_tokens(settings) {
+        let path = format!("{}.decimals", token_path(token));
+        let decimals: u32 = token_info.decimals.clone().into();
+
+        match eth_client.erc20_decimals(token_info.eth_address).await {
+            Ok(eth_decimals) if u32::from(eth_decimals) != decimals => {
+                issues.push(ConfigIssue::error(
+                    path.clone(),
+                    format!(
+                        "{} differs from the ERC20 `decimals()` {}",
+                        decimals, eth_decimals
+                    ),
+                ))
+            }
+            Ok(_) => {}
+            Err(err) => issues.push(ConfigIssue::error(
+                format!("{}.eth_address", token_path(token)),
+                format!("failed to call ERC20 `decimals()`: {:?}", err),
+            )),
+        }
+
+        match ft_metadata_decimals(&near_client, token).await {
+            Ok(near_decimals) if near_decimals != decimals => issues.push(ConfigIssue::error(
+                path,
+                format!(
+                    "{} differs from the NEAR `ft_metadata` decimals {}",
+                    decimals, near_decimals
+                ),
+            )),
+            Ok(_) => {}
+            Err(err) => issues.push(ConfigIssue::error(
+                token_path(token),
+                format!("failed to call NEAR `ft_metadata`: {}", err),
+            )),
+        }
+    }
+
+    issues
+}
+
+async fn ft_metadata_decimals(
+    near_client: &NearClient,
+    token: &near_sdk::AccountId,
+) -> Result<u32, String> {
+    let response = near_client
+        .view(
+            token.to_string(),
+            "ft_metadata".to_string(),
+            serde_json::json!({}),
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+
+    #[derive(serde::Deserialize)]
+    struct FungibleTokenMetadata {
+        decimals: u32,
+    }
+
+    if let QueryResponseKind::CallResult(result) = response.kind {
+        serde_json::from_slice::<FungibleTokenMetadata>(&result.result)
+            .map(|metadata| metadata.decimals)
+            .map_err(|err| err.to_string())
+    } else {
+        Err("unexpected response".to_string())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::config::FeeTier;
+    use crate::config_check::{check, has_errors, ConfigIssue, Severity};
+    use crate::test_utils::get_settings;
+
+    fn issue_paths(issues: &[ConfigIssue], severity: Severity) -> Vec<String> {
+        issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .map(|issue| issue.path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn check_tokens_test() {
+        let mut settings = get_settings();
+        settings.near_tokens_whitelist.mapping.clear();
+        settings.unlock_tokens_worker.contract_account_id =
+            settings.near.contract_address.to_string();
+        assert!(check(&settings).is_empty());
+
+        let mut token_info = get_settings()
+            .near_tokens_whitelist
+            .get_token_info(near_client::test_utils::NEAR_TOKEN_ADDRESS.parse().unwrap())
+            .unwrap();
+        token_info.eth_address = "dac17f958d2ee523a2206206994597c13d831ec7".parse().unwrap();
+        token_info.percent_fee = 1.5;
+        token_info.fixed_fee = u128::MAX.into();
+        token_info.fee_tiers = vec![
+            FeeTier {
+                min_amount: 100.into(),
+                percent_fee: 0.2,
+            },
+            FeeTier {
+                min_amount: 100.into(),
+                percent_fee: 0.01,
+            },
+        ];
+        settings.near_tokens_whitelist.mapping.insert(
+            "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48.factory.bridge.near"
+                .parse()
+                .unwrap(),
+            token_info,
+        );
+
+        let issues = check(&settings);
+        let path = "$.near_tokens_whitelist.mapping[\"a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48.factory.bridge.near\"]";
+        assert_eq!(
+            issue_paths(&issues, Severity::Error),
+            vec![
+                format!("{}.eth_address", path),
+                format!("{}.percent_fee", path),
+                format!("{}.fee_tiers[1].min_amount", path),
+            ]
+        );
+        assert_eq!(
+            issue_paths(&issues, Severity::Warning),
+            vec![
+                format!("{}.fee_tiers[0].percent_fee", path),
+                format!("{}.fixed_fee", path),
+            ]
+        );
+        assert!(has_errors(&issues));
+    }
+
+    #[test]
+    fn check_settings_test() {
+        let mut settings = get_settings();
+        settings.near_tokens_whitelist.mapping.clear();
+        settings.vault_addr = "http://127.0.0.1:8200/v1/kv".parse().unwrap();
+        settings.unlock_tokens_worker.contract_account_id = "other.testnet".to_string();
+        settings.rpc_timeout_secs = 0;
+        settings.eth.fallback_rpc_urls = vec![settings.eth.rpc_url.clone()];
+        settings.eth.receipt_quorum = Some(2);
+        settings.eth.ws_url = Some(settings.eth.rpc_url.clone());
+        settings.near.fallback_rpc_urls = vec![settings.near.rpc_url.clone()];
+        settings.near.function_call_key_paths = vec!["key.json".to_string(); 2];
+        settings.unlock_tokens_worker.max_concurrent_unlocks = 0;
+        settings.unlock_tokens_worker.max_unlocks_per_tx = 10;
+        settings.unlock_tokens_worker.max_unlock_attempts = 0;
+
+        let issues = check(&settings);
+        assert_eq!(
+            issue_paths(&issues, Severity::Error),
+            vec![
+                "$.vault_addr",
+                "$.unlock_tokens_worker.contract_account_id",
+                "$.unlock_tokens_worker.max_concurrent_unlocks",
+                "$.unlock_tokens_worker.max_unlock_attempts",
+                "$.rpc_timeout_secs",
+                "$.eth.receipt_quorum",
+                "$.eth.ws_url"
+            ]
+        );
+        assert_eq!(
+            issue_paths(&issues, Severity::Warning),
+            vec![
+                "$.unlock_tokens_worker.max_unlocks_per_tx",
+                "$.eth.fallback_rpc_urls",
+                "$.near.fallback_rpc_urls",
+                "$.near.function_call_key_paths"
+            ]
+        );
+    }
+}
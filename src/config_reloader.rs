@@ -0,0 +1,353 @@
+use crate::config::{SafeSettings, Settings};
+use crate::config_check::{self, Severity};
+use serde_json::Value;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+
+macro_rules! info {
+    ($($arg:tt)+) => { tracing::info!(target: crate::logs::CONFIG_RELOADER_TARGET, $($arg)+) }
+}
+
+macro_rules! warn {
+    ($($arg:tt)+) => { tracing::warn!(target: crate::logs::CONFIG_RELOADER_TARGET, $($arg)+) }
+}
+
+const CONFIG_CHECK_INTERVAL_SEC: u64 = 5;
+
+// The values used to create the connections and the contract handles at startup
+const UNSAFE_CHANGES: &[&str] = &[
+    "network",
+    "eth.chain_id",
+    "eth.rpc_url",
+    "eth.fallback_rpc_urls",
+    "eth.receipt_quorum",
+    "eth.ws_url",
+    "eth.head_poll_interval_secs",
+    "eth.bridge_proxy_address",
+    "eth.rainbow_bridge_index_js_path",
+    "eth.private_key",
+    "near.rpc_url",
+    "near.fallback_rpc_urls",
+    "near.contract_address",
+    "near.near_credentials_path",
+    "near.function_call_key_paths",
+    "near.near_network",
+    "near.near_lake_init_block",
+    "last_block_number_worker.server_addr",
+    "unlock_tokens_worker.server_addr",
+    "unlock_tokens_worker.gas",
+    "redis.url",
+    "vault_addr",
+    "rpc_timeout_secs",
+    "rpc_max_retries",
+    "rpc_retry_backoff_ms",
+    "prometheus_metrics_port",
+];
+
+// The values which are not printed in the diff, the RPC URLs contain the API keys of the providers
+const SECRET_FIELDS: &[&str] = &[
+    "private_key",
+    "api_key",
+    "rpc_url",
+    "fallback_rpc_urls",
+    "ws_url",
+    "url",
+];
+
+/// Reloads the settings on SIGHUP or when the config file is modified
+pub async fn run(settings: SafeSettings) {
+    let config_path = settings.lock().await.config_path.clone();
+    let mut last_modified = modified_time(&config_path);
+
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(sighup) => Some(sighup),
+        Err(err) => {
+            warn!("Failed to subscribe to SIGHUP: {}", err);
+            None
+        }
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(CONFIG_CHECK_INTERVAL_SEC));
+
+    loop {
+        tokio::select! {
+            Some(_) = async { sighup.as_mut()?.recv().await } => {
+                info!("SIGHUP received, reload config {}", config_path);
+            }
+            _ = interval.tick() => {
+                let modified = modified_time(&config_path);
+                if modified == last_modified {
+                    continue;
+                }
+                info!("Config {} is modified, reload it", config_path);
+            }
+        }
+
+        last_modified = modified_time(&config_path);
+
+        if let Err(err) = reload(&settings, &config_path).await {
+            warn!("Config is not reloaded: {}", err);
+        }
+    }
+}
+
+fn modified_time(config_path: &str) -> Option<SystemTime> {
+    std::fs::metadata(config_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub async fn reload(settings: &SafeSettings, config_path: &str) -> Result<(), String> {
+    let new_settings = Settings::init(config_path.to_string())?;
+    validate(&new_settings)?;
+
+    let mut locked_settings = settings.lock().await;
+    let changes = diff(&*locked_settings, &new_settings)?;
+    if changes.is_empty() {
+        info!("Config is not changed");
+        return Ok(());
+    }
+
+    check_unsafe_changes(&changes)?;
+
+    for change in &changes {
+        info!("Config changed: {}", change);
+    }
+    *locked_settings = new_settings;
+
+    Ok(())
+}
+
+/// Runs the offline rules of `check-config`, the settings with errors are not applied
+pub fn validate(settings: &Settings) -> Result<(), String> {
+    let issues = config_check::check(settings);
+    for issue in &issues {
+        if issue.severity == Severity::Warning {
+            warn!("{}", issue);
+        }
+    }
+
+    if config_check::has_errors(&issues) {
+        let errors: Vec<String> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| issue.to_string())
+            .collect();
+        return Err(format!("the config is invalid: {}", errors.join("; ")));
+    }
+    Ok(())
+}
+
+fn is_secret(field: &str) -> bool {
+    SECRET_FIELDS.contains(&field)
+}
+
+/// Replaces the secret values nested in the added or removed objects
+fn mask_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| match is_secret(key) {
+                    true => (key.clone(), Value::String("***".to_string())),
+                    false => (key.clone(), mask_secrets(value)),
+                })
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsChange {
+    pub path: String,
+    pub old_value: Option<Value>,
+    pub new_value: Option<Value>,
+}
+
+impl std::fmt::Display for SettingsChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = self.path.rsplit('.').next().unwrap_or_default();
+        let format_value = |value: &Option<Value>| match value {
+            _ if is_secret(field) => "***".to_string(),
+            Some(value) => mask_secrets(value).to_string(),
+            None => "<none>".to_string(),
+        };
+
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            format_value(&self.old_value),
+            format_value(&self.new_value)
+        )
+    }
+}
+
+pub fn diff(old: &Settings, new: &Settings) -> Result<Vec<SettingsChange>, String> {
+    let old = serde_json::to_value(old).map_err(|err| err.to_string())?;
+    let new = serde_json::to_value(new).map_err(|err| err.to_string())?;
+
+    let mut changes = vec![];
+    diff_values("", Some(&old), Some(&new), &mut changes);
+    Ok(changes)
+}
+
+fn diff_values(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<SettingsChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let key_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(&key_path, old.get(key), new.get(key), changes);
+            }
+        }
+        (old, new) if old != new => changes.push(SettingsChange {
+            path: path.to_string(),
+            old_value: old.cloned(),
+            new_value: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+pub fn check_unsafe_changes(changes: &[SettingsChange]) -> Result<(), String> {
+    let unsafe_changes: Vec<String> = changes
+        .iter()
+        .filter(|change| {
+            UNSAFE_CHANGES
+                .iter()
+                .any(|path| change.path == *path || change.path.starts_with(&format!("{}.", path)))
+        })
+        .map(|change| change.path.clone())
+        .collect();
+
+    if unsafe_changes.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the fields can't be changed without restart: {}",
+            unsafe_changes.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::config_reloader::{check_unsafe_changes, diff, validate, SettingsChange};
+    use crate::test_utils::get_settings;
+    use serde_json::json;
+
+    #[test]
+    fn diff_test() {
+        let settings = get_settings();
+        assert!(diff(&settings, &settings).unwrap().is_empty());
+
+        let mut new_settings = settings.clone();
+        new_settings.profit_thershold = Some(1.5);
+        new_settings.unlock_tokens_worker.request_interval_secs += 1;
+        new_settings.eth.private_key = Some("secret".to_string());
+
+        let changes = diff(&settings, &new_settings).unwrap();
+        let paths: Vec<&str> = changes.iter().map(|change| change.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "eth.private_key",
+                "profit_thershold",
+                "unlock_tokens_worker.request_interval_secs"
+            ]
+        );
+        assert_eq!(changes[1].new_value, Some(json!(1.5)));
+        assert!(!changes[0].to_string().contains("secret"));
+    }
+
+    #[test]
+    fn secret_fields_test() {
+        let change = |path: &str, old_value, new_value| {
+            SettingsChange {
+                path: path.to_string(),
+                old_value,
+                new_value,
+            }
+            .to_string()
+        };
+
+        let rpc_url = json!("https://sepolia.infura.io/v3/secret");
+        for path in ["eth.rpc_url", "eth.ws_url", "near.rpc_url", "redis.url"] {
+            assert_eq!(
+                change(path, Some(rpc_url.clone()), None),
+                format!("{}: *** -> ***", path)
+            );
+        }
+        let fallback_rpc_urls = change(
+            "eth.fallback_rpc_urls",
+            Some(json!([])),
+            Some(json!([rpc_url])),
+        );
+        assert!(!fallback_rpc_urls.contains("secret"));
+
+        // The secrets of the added object are masked too
+        let added = change(
+            "fee_oracle",
+            None,
+            Some(json!({"rpc_url": rpc_url, "blocks": 20})),
+        );
+        assert!(!added.contains("secret"));
+        assert!(added.contains("\"blocks\":20"));
+
+        assert_eq!(
+            change("profit_thershold", Some(json!(1)), Some(json!(2))),
+            "profit_thershold: 1 -> 2"
+        );
+    }
+
+    #[test]
+    fn validate_test() {
+        let mut settings = get_settings();
+        settings.near_tokens_whitelist.mapping.clear();
+        settings.unlock_tokens_worker.contract_account_id =
+            settings.near.contract_address.to_string();
+        assert!(validate(&settings).is_ok());
+
+        let mut invalid_settings = settings;
+        invalid_settings.unlock_tokens_worker.max_unlock_attempts = 0;
+        let err = validate(&invalid_settings).unwrap_err();
+        assert!(err.contains("max_unlock_attempts"));
+    }
+
+    #[test]
+    fn check_unsafe_changes_test() {
+        let change = |path: &str| SettingsChange {
+            path: path.to_string(),
+            old_value: Some(json!(1)),
+            new_value: Some(json!(2)),
+        };
+
+        assert!(check_unsafe_changes(&[
+            change("profit_thershold"),
+            change("near_tokens_whitelist.mapping.token.near.percent_fee"),
+        ])
+        .is_ok());
+
+        let err = check_unsafe_changes(&[
+            change("profit_thershold"),
+            change("eth.bridge_proxy_address"),
+            change("redis.url"),
+            change("unlock_tokens_worker.gas"),
+        ])
+        .unwrap_err();
+        assert!(err.contains("eth.bridge_proxy_address, redis.url, unlock_tokens_worker.gas"));
+    }
+}
@@ -9,12 +9,33 @@ pub enum CustomError {
     #[error("Failed to fetch gas price in WEI: {0:?}")]
     FailedFetchGasPrice(web3::contract::Error),
 
+    #[error("The base fee {0} is above the `max_fee_per_gas` limit {1}")]
+    BaseFeeAboveMaxFee(web3::types::U256, web3::types::U256),
+
     #[error("Failed to fetch Ethereum price in USD: invalid coin id")]
     FailedFetchEthPriceInvalidCoinId,
 
     #[error("Failed to fetch Ethereum price in USD: {0:?}")]
     FailedFetchEthereumPrice(reqwest::Error),
 
+    #[error("Invalid price: {0}")]
+    InvalidPrice(f64),
+
+    #[error("Invalid capital cost rate: {0}")]
+    InvalidCapitalCostRate(f64),
+
+    #[error("Failed to fetch NEAR gas price: {0}")]
+    FailedFetchNearGasPrice(near_client::errors::NearClientError),
+
+    #[error("Failed to estimate transfer execution cost: {0}")]
+    FailedEstimateExecutionCost(String),
+
+    #[error("Failed to fetch on-chain price: {0:?}")]
+    FailedFetchOnChainPrice(web3::contract::Error),
+
+    #[error("Invalid price source: {0}")]
+    InvalidPriceSource(String),
+
     #[error("Failed to fetch last block number: {0:?}")]
     FailedFetchLastBlockNumber(web3::contract::Error),
 
@@ -31,7 +52,7 @@ pub enum CustomError {
     FailedExecuteTransferTokens(web3::contract::Error),
 
     #[error("Failed to execute lp_unlock contract method: {0}")]
-    FailedExecuteUnlockTokens(String),
+    FailedExecuteUnlockTokens(near_client::errors::NearClientError),
 
     #[error("Failed to get transaction count: {0:?}")]
     FailedGetTxCount(web3::Error),
@@ -57,8 +78,47 @@ pub enum CustomError {
     #[error("Failed to unstore new event: {0:?}")]
     FailedRemoveNewEvent(redis::RedisError),
 
+    #[error("Failed to store unlock transaction: {0:?}")]
+    FailedStoreUnlockTx(redis::RedisError),
+
+    #[error("Failed to get unlock transactions: {0:?}")]
+    FailedGetUnlockTxs(redis::RedisError),
+
+    #[error("Failed to unstore unlock transactions: {0:?}")]
+    FailedUnstoreUnlockTxs(redis::RedisError),
+
+    #[error("Failed to update transaction: {0:?}")]
+    FailedUpdateTransaction(redis::RedisError),
+
+    #[error("Failed to store failed transaction: {0:?}")]
+    FailedStoreFailedTx(redis::RedisError),
+
+    #[error("Failed to decode unlock transaction: {0}")]
+    FailedDecodeUnlockTx(String),
+
     #[error("Failed to execute last_block_number contract method: {0}")]
-    FailedExecuteLastBlockNumber(String),
+    FailedExecuteLastBlockNumber(near_client::errors::NearClientError),
+
+    #[error("Failed to execute block_hash_safe contract method: {0}")]
+    FailedExecuteBlockHashSafe(near_client::errors::NearClientError),
+
+    #[error("Failed to execute get_pending_transfer contract method: {0}")]
+    FailedExecuteGetPendingTransfer(near_client::errors::NearClientError),
+
+    #[error("Invalid proof: {0}")]
+    InvalidProof(crate::ethereum::receipt_proof::ProofVerificationError),
+
+    #[error("CRITICAL: the transfer event doesn't match the NEAR transfer message: {0}")]
+    TransferEventMismatch(crate::ethereum::receipt_proof::ProofVerificationError),
+
+    #[error("Receipt of transaction {0} is not found")]
+    ReceiptNotFound(String),
+
+    #[error("Failed to fetch transaction receipt: {0:?}")]
+    FailedFetchReceipt(web3::Error),
+
+    #[error("Failed to decode proof header: {0}")]
+    FailedDecodeProofHeader(String),
 
     #[error("Failed to fetch transaction status: {0:?}")]
     FailedFetchTxStatus(web3::Error),
@@ -78,11 +138,11 @@ pub enum CustomError {
     #[error("Invalid eth token address")]
     InvalidEthTokenAddress,
 
-    #[error("The fee amount: {0} is lower than the min allowed fee: {1}")]
-    NotEnoughFeeToken(u128, u128),
-
-    #[error("The profit: {0}, is lower than the min allowed profit: {1}")]
-    TxNotProfitable(f64, f64),
+    #[error(
+        "Transfer rejected: {}",
+        .0.iter().map(|reason| reason.to_string()).collect::<Vec<_>>().join("; ")
+    )]
+    TransferRejected(Vec<RejectionReason>),
 
     #[error(
         "Error in parsing tokens amount during profit estimation. \
@@ -96,10 +156,29 @@ pub enum CustomError {
 
     #[error("Invalid valid till block height")]
     InvalidValidTillBlockHeight,
+}
+
+/// The token policy a transfer doesn't satisfy
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum RejectionReason {
+    #[error("The token is disabled")]
+    TokenDisabled,
+
+    #[error("The transferred tokens amount: {0} is lower than the min allowed amount: {1}")]
+    BelowMinTransferAmount(u128, u128),
 
     #[error(
         "Exceeding the maximum allowable token amount. \
-    Transferred tokens amount: {0:?}, max transfer allowed: {1:?}"
+    Transferred tokens amount: {0}, max transfer allowed: {1}"
     )]
-    ExceedingMaxAllowableTokenAmount(near_sdk::json_types::U128, near_sdk::json_types::U128),
+    ExceedingMaxAllowableTokenAmount(u128, u128),
+
+    #[error("The fee amount: {0} is lower than the min allowed fee: {1}")]
+    NotEnoughFeeToken(u128, u128),
+
+    #[error("The profit: {0}, is lower than the min allowed profit: {1}")]
+    TxNotProfitable(f64, f64),
+
+    #[error("The profit: {0}% of the amount, is lower than the min allowed profit: {1}%")]
+    ProfitPercentTooLow(f64, f64),
 }
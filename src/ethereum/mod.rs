@@ -41,34 +41,47 @@
 //! ```
 
 pub mod proof;
+pub mod receipt_proof;
 pub mod transactions;
 
-use eth_client::methods::new_eth_rpc_client;
-use web3::{api::Namespace, transports::Http};
+use eth_client::methods::EthClient;
+use eth_client::provider::{QuorumTransport, RpcProvider};
+use web3::api::Namespace;
 
 pub struct RainbowBridgeEthereumClient<'a> {
-    api_url: http_types::Url,
+    provider: RpcProvider,
     rainbow_bridge_index: &'a str,
-    client: web3::api::Eth<Http>,
+    client: web3::api::Eth<RpcProvider>,
+    /// Used to fetch the transaction receipts if `eth.receipt_quorum` is set
+    quorum_client: Option<web3::api::Eth<QuorumTransport>>,
 }
 
 impl<'a> RainbowBridgeEthereumClient<'a> {
     pub fn new(
-        eth_endpoint: http_types::Url,
+        eth_client: &EthClient,
         rainbow_bridge_index: &'a str,
-        rpc_timeout_secs: u64,
+        receipt_quorum: Option<usize>,
     ) -> Result<Self, std::string::String> {
-        let transport = web3::transports::Http::with_client(
-            new_eth_rpc_client(Some(std::time::Duration::from_secs(rpc_timeout_secs)))
-                .map_err(|e| e.to_string())?,
-            eth_endpoint.clone(),
-        );
-        let client = web3::api::Eth::new(transport);
+        let provider = eth_client.provider().clone();
+        if let Some(quorum) = receipt_quorum {
+            if quorum == 0 || quorum > provider.endpoints_count() {
+                return Err(format!(
+                    "Receipt quorum {} is out of range 1..={}",
+                    quorum,
+                    provider.endpoints_count()
+                ));
+            }
+        }
+
+        let client = web3::api::Eth::new(provider.clone());
+        let quorum_client =
+            receipt_quorum.map(|quorum| web3::api::Eth::new(provider.with_quorum(quorum)));
 
         Ok(Self {
-            api_url: eth_endpoint,
+            provider,
             rainbow_bridge_index,
             client,
+            quorum_client,
         })
     }
 
@@ -76,7 +89,34 @@ impl<'a> RainbowBridgeEthereumClient<'a> {
         &self,
         tx_hash: web3::types::H256,
     ) -> web3::error::Result<transactions::TransactionStatus> {
-        transactions::transaction_status(&self.client, tx_hash).await
+        match &self.quorum_client {
+            Some(quorum_client) => transactions::transaction_status(quorum_client, tx_hash).await,
+            None => transactions::transaction_status(&self.client, tx_hash).await,
+        }
+    }
+
+    /// Fetches the statuses of all the transactions and the last block number in one batch
+    pub async fn transaction_statuses(
+        &self,
+        tx_hashes: &[web3::types::H256],
+    ) -> web3::error::Result<transactions::TransactionStatuses> {
+        match &self.quorum_client {
+            Some(quorum_client) => {
+                transactions::transaction_statuses(quorum_client.transport().clone(), tx_hashes)
+                    .await
+            }
+            None => transactions::transaction_statuses(self.provider.clone(), tx_hashes).await,
+        }
+    }
+
+    pub async fn transaction_receipt(
+        &self,
+        tx_hash: web3::types::H256,
+    ) -> web3::error::Result<Option<web3::types::TransactionReceipt>> {
+        match &self.quorum_client {
+            Some(quorum_client) => quorum_client.transaction_receipt(tx_hash).await,
+            None => self.client.transaction_receipt(tx_hash).await,
+        }
     }
 
     pub async fn get_proof<'b, 'c>(
@@ -84,7 +124,7 @@ impl<'a> RainbowBridgeEthereumClient<'a> {
         tx_hash: &'b web3::types::H256,
     ) -> Result<fast_bridge_common::Proof, proof::Error<'c>> {
         proof::get_proof(
-            self.api_url.as_str(),
+            &self.provider.best_url(),
             &self.client,
             self.rainbow_bridge_index,
             tx_hash,
@@ -95,38 +135,41 @@ impl<'a> RainbowBridgeEthereumClient<'a> {
 
 #[cfg(test)]
 pub mod tests {
-    use crate::config::default_rpc_timeout_secs;
     use crate::ethereum::transactions::TransactionStatus;
     use crate::ethereum::RainbowBridgeEthereumClient;
     use crate::test_utils::get_rb_index_path_str;
-    use eth_client::test_utils::get_eth_rpc_url;
-    use url::Url;
+    use eth_client::methods::EthClient;
+    use eth_client::test_utils::get_eth_client;
     use web3::types::U64;
 
-    async fn get_params() -> (Url, String) {
-        (get_eth_rpc_url(), get_rb_index_path_str())
+    async fn get_params() -> (EthClient, String) {
+        (get_eth_client(), get_rb_index_path_str())
     }
 
     #[tokio::test]
     async fn smoke_new_test() {
-        let (eth1_endpoint, rb_index_path_str) = get_params().await;
-        let _eth = RainbowBridgeEthereumClient::new(
-            eth1_endpoint,
+        let (eth_client, rb_index_path_str) = get_params().await;
+        let _eth = RainbowBridgeEthereumClient::new(&eth_client, &rb_index_path_str, None).unwrap();
+    }
+
+    #[tokio::test]
+    async fn new_with_invalid_quorum_test() {
+        let (eth_client, rb_index_path_str) = get_params().await;
+        assert!(
+            RainbowBridgeEthereumClient::new(&eth_client, &rb_index_path_str, Some(0)).is_err()
+        );
+        assert!(RainbowBridgeEthereumClient::new(
+            &eth_client,
             &rb_index_path_str,
-            default_rpc_timeout_secs(),
+            Some(eth_client.provider().endpoints_count() + 1)
         )
-        .unwrap();
+        .is_err());
     }
 
     #[tokio::test]
     async fn smoke_get_proof_test() {
-        let (eth1_endpoint, rb_index_path_str) = get_params().await;
-        let eth = RainbowBridgeEthereumClient::new(
-            eth1_endpoint,
-            &rb_index_path_str,
-            default_rpc_timeout_secs(),
-        )
-        .unwrap();
+        let (eth_client, rb_index_path_str) = get_params().await;
+        let eth = RainbowBridgeEthereumClient::new(&eth_client, &rb_index_path_str, None).unwrap();
 
         let tx_hash = web3::types::H256::from_slice(
             &hex::decode("cb50c668e750650fc53d0027112d0580b42f3b658780598cb6899344e2b94183")
@@ -139,13 +182,8 @@ pub mod tests {
 
     #[tokio::test]
     async fn smoke_transaction_status_test() {
-        let (eth1_endpoint, rb_index_path_str) = get_params().await;
-        let eth = RainbowBridgeEthereumClient::new(
-            eth1_endpoint,
-            &rb_index_path_str,
-            default_rpc_timeout_secs(),
-        )
-        .unwrap();
+        let (eth_client, rb_index_path_str) = get_params().await;
+        let eth = RainbowBridgeEthereumClient::new(&eth_client, &rb_index_path_str, None).unwrap();
 
         let tx_hash = web3::types::H256::from_slice(
             &hex::decode("564e7a804e74e45710021c692a0fdc2ef5284bc4fbfd3b552b359adb89e21f14")
@@ -112,6 +112,13 @@ pub async fn get_transaction_log_index<'a, 'b, T: web3::Transport>(
     log.log_index.ok_or(Error::Empty)
 }
 
+/// The number and the hash of the block from the RLP-encoded header of the proof
+pub fn header_block(header_data: &[u8]) -> Result<(u64, H256), rlp::DecoderError> {
+    // parentHash, sha3Uncles, miner, stateRoot, transactionsRoot, receiptsRoot, logsBloom, difficulty, number
+    let number = rlp::Rlp::new(header_data).val_at::<u64>(8)?;
+    Ok((number, H256::from(web3::signing::keccak256(header_data))))
+}
+
 impl ToString for Error<'_> {
     #[allow(unconditional_recursion)]
     fn to_string(&self) -> String {
@@ -122,11 +129,33 @@ impl ToString for Error<'_> {
 #[cfg(test)]
 pub mod tests {
     use crate::ethereum::proof;
-    use crate::ethereum::proof::get_transaction_log_index;
+    use crate::ethereum::proof::{get_transaction_log_index, header_block};
     use crate::test_utils::get_rb_index_path_str;
     use eth_client::test_utils::get_eth_rpc_url;
     use web3::api::Namespace;
 
+    #[test]
+    fn header_block_test() {
+        let mut header = rlp::RlpStream::new_list(15);
+        for _ in 0..8 {
+            header.append(&vec![1u8; 32]);
+        }
+        header.append(&8249163u64);
+        for _ in 0..6 {
+            header.append(&0u64);
+        }
+        let header_data = header.out().to_vec();
+
+        let (number, hash) = header_block(&header_data).unwrap();
+        assert_eq!(number, 8249163);
+        assert_eq!(
+            hash,
+            web3::types::H256::from(web3::signing::keccak256(&header_data))
+        );
+
+        assert!(header_block(&[0x80]).is_err());
+    }
+
     #[tokio::test]
     async fn smoke_get_transaction_log_index_test() {
         let tx_hash = web3::types::H256::from_slice(
@@ -0,0 +1,591 @@
+//! Local verification of the receipt proofs built by the rainbow bridge
+//!
+//! The proof is checked the way the prover on NEAR does it: the receipt is found in the receipts trie
+//! of the header by the Merkle-Patricia proof, the log entry is found in the receipt and should be the
+//! `TransferTokens` event of the bridge with the expected transfer.
+
+use crate::async_redis_wrapper::EthTransferData;
+use eth_client::fast_bridge::TransferTokensEvent;
+use fast_bridge_common::Proof;
+use rlp::Rlp;
+use web3::ethabi::{Event, RawLog};
+use web3::signing::keccak256;
+use web3::types::{Address, Log, H256, U256};
+
+/// The index of `receiptsRoot` in the RLP list of the header
+const RECEIPTS_ROOT_INDEX: usize = 5;
+/// The index of the logs in the RLP list of the receipt
+const RECEIPT_LOGS_INDEX: usize = 3;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProofVerificationError {
+    #[error("Invalid RLP of the {0}: {1}")]
+    InvalidRlp(&'static str, rlp::DecoderError),
+    #[error("Invalid receipts trie proof: {0}")]
+    InvalidTrieProof(&'static str),
+    #[error("The receipt data doesn't match the receipts trie")]
+    ReceiptMismatch,
+    #[error("The transaction is failed")]
+    FailedReceipt,
+    #[error("The receipt doesn't contain the log entry {0}")]
+    LogNotFound(u64),
+    #[error("The log is emitted by {0:?}, not by the bridge")]
+    UnexpectedEmitter(Address),
+    #[error("The log is not a TransferTokens event: {0}")]
+    NotTransferTokens(String),
+    #[error("The receipt doesn't contain the TransferTokens event of the bridge")]
+    EventNotFound,
+    #[error("The event {field} is {actual}, expected {expected}")]
+    EventMismatch {
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl ProofVerificationError {
+    /// The proof is valid but proves another transfer, a new proof doesn't help
+    pub fn is_event_mismatch(&self) -> bool {
+        matches!(
+            self,
+            ProofVerificationError::EventMismatch { .. }
+                | ProofVerificationError::UnexpectedEmitter(_)
+                | ProofVerificationError::EventNotFound
+        )
+    }
+}
+
+/// Verifies that the proof proves the `TransferTokens` event of `bridge_address` with the `nonce`
+/// and the `transfer` if it is known, the event is decoded with `event_abi` of the loaded bridge ABI
+pub fn verify_transfer_proof(
+    event_abi: &Event,
+    proof: &Proof,
+    bridge_address: Address,
+    nonce: U256,
+    transfer: Option<&EthTransferData>,
+) -> Result<TransferTokensEvent, ProofVerificationError> {
+    let header = Rlp::new(&proof.header_data);
+    let receipts_root: Vec<u8> = header
+        .val_at(RECEIPTS_ROOT_INDEX)
+        .map_err(|err| ProofVerificationError::InvalidRlp("header", err))?;
+
+    let key = rlp::encode(&proof.receipt_index);
+    let receipt_data = verify_trie_proof(&receipts_root, &key, &proof.proof)?;
+    if receipt_data != proof.receipt_data {
+        return Err(ProofVerificationError::ReceiptMismatch);
+    }
+
+    let log_entry_data = receipt_log(&receipt_data, proof.log_index)?;
+    if log_entry_data != proof.log_entry_data.as_slice() {
+        return Err(ProofVerificationError::LogNotFound(proof.log_index));
+    }
+
+    let event = decode_transfer_tokens_log(event_abi, log_entry_data, bridge_address)?;
+    check_transfer_event(&event, nonce, transfer)?;
+    Ok(event)
+}
+
+/// Finds the `TransferTokens` event of `bridge_address` in the receipt logs and compares it with the
+/// transfer
+pub fn verify_receipt_event(
+    event_abi: &Event,
+    logs: &[Log],
+    bridge_address: Address,
+    nonce: U256,
+    transfer: &EthTransferData,
+) -> Result<TransferTokensEvent, ProofVerificationError> {
+    let topic = TransferTokensEvent::topic(event_abi);
+    let log = logs
+        .iter()
+        .find(|log| log.address == bridge_address && log.topics.first() == Some(&topic))
+        .ok_or(ProofVerificationError::EventNotFound)?;
+
+    let event = TransferTokensEvent::from_log(event_abi, log)
+        .map_err(|err| ProofVerificationError::NotTransferTokens(err.to_string()))?;
+    check_transfer_event(&event, nonce, Some(transfer))?;
+    Ok(event)
+}
+
+fn check_transfer_event(
+    event: &TransferTokensEvent,
+    nonce: U256,
+    transfer: Option<&EthTransferData>,
+) -> Result<(), ProofVerificationError> {
+    check_event_field("nonce", nonce, event.nonce)?;
+    if let Some(transfer) = transfer {
+        check_event_field("token", transfer.token, event.token)?;
+        check_event_field("recipient", transfer.recipient, event.recipient)?;
+        check_event_field("amount", transfer.amount, event.amount)?;
+        if let Some(valid_till_block_height) = transfer.valid_till_block_height {
+            check_event_field(
+                "valid_till_block_height",
+                valid_till_block_height,
+                event.valid_till_block_height,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the value of `key` proved by the Merkle-Patricia `proof` nodes from `root`
+pub fn verify_trie_proof(
+    root: &[u8],
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Vec<u8>, ProofVerificationError> {
+    let invalid_node = |err| ProofVerificationError::InvalidRlp("trie node", err);
+    let key: Vec<u8> = key.iter().flat_map(|byte| [byte / 16, byte % 16]).collect();
+
+    let mut expected_reference = root.to_vec();
+    let mut key_index = 0;
+    for (proof_index, node_data) in proof.iter().enumerate() {
+        let is_last = proof_index + 1 == proof.len();
+
+        // The root and the nodes of 32 bytes or more are referenced by the hash
+        let reference = if proof_index == 0 || node_data.len() >= 32 {
+            keccak256(node_data).to_vec()
+        } else {
+            node_data.clone()
+        };
+        if reference != expected_reference {
+            return Err(ProofVerificationError::InvalidTrieProof(
+                "the node doesn't match the reference of its parent",
+            ));
+        }
+
+        let node = Rlp::new(node_data);
+        match node.item_count().map_err(invalid_node)? {
+            // Branch node
+            17 => {
+                if key_index == key.len() {
+                    if !is_last {
+                        return Err(ProofVerificationError::InvalidTrieProof(
+                            "the proof has extra nodes",
+                        ));
+                    }
+                    return node.val_at(16).map_err(invalid_node);
+                }
+                expected_reference =
+                    node_reference(&node.at(key[key_index] as usize).map_err(invalid_node)?)?;
+                if expected_reference.is_empty() {
+                    return Err(ProofVerificationError::InvalidTrieProof(
+                        "the key is absent in the trie",
+                    ));
+                }
+                key_index += 1;
+            }
+            // Leaf or extension node
+            2 => {
+                let encoded_path: Vec<u8> = node.val_at(0).map_err(invalid_node)?;
+                let (path, is_leaf) = decode_path(&encoded_path)?;
+                if !key[key_index..].starts_with(&path) {
+                    return Err(ProofVerificationError::InvalidTrieProof(
+                        "the key doesn't match the node path",
+                    ));
+                }
+                key_index += path.len();
+
+                if is_leaf {
+                    if !is_last || key_index != key.len() {
+                        return Err(ProofVerificationError::InvalidTrieProof(
+                            "the leaf doesn't end the proof",
+                        ));
+                    }
+                    return node.val_at(1).map_err(invalid_node);
+                }
+                expected_reference = node_reference(&node.at(1).map_err(invalid_node)?)?;
+            }
+            _ => {
+                return Err(ProofVerificationError::InvalidTrieProof(
+                    "the node is neither a branch nor a leaf",
+                ))
+            }
+        }
+    }
+
+    Err(ProofVerificationError::InvalidTrieProof(
+        "the proof is incomplete",
+    ))
+}
+
+/// The hash of the child node or the child node itself if it is shorter than 32 bytes
+fn node_reference(item: &Rlp) -> Result<Vec<u8>, ProofVerificationError> {
+    if item.is_list() {
+        Ok(item.as_raw().to_vec())
+    } else {
+        item.data()
+            .map(|data| data.to_vec())
+            .map_err(|err| ProofVerificationError::InvalidRlp("trie node", err))
+    }
+}
+
+/// Decodes the hex-prefix encoded path, returns the nibbles and the leaf flag
+fn decode_path(encoded_path: &[u8]) -> Result<(Vec<u8>, bool), ProofVerificationError> {
+    let (first, rest) = encoded_path
+        .split_first()
+        .ok_or(ProofVerificationError::InvalidTrieProof("empty node path"))?;
+    let flag = first / 16;
+    if flag > 3 {
+        return Err(ProofVerificationError::InvalidTrieProof(
+            "invalid node path prefix",
+        ));
+    }
+
+    let mut path = vec![];
+    if flag % 2 == 1 {
+        path.push(first % 16);
+    }
+    path.extend(rest.iter().flat_map(|byte| [byte / 16, byte % 16]));
+    Ok((path, flag >= 2))
+}
+
+/// Returns the raw log entry of the successful receipt
+fn receipt_log(receipt_data: &[u8], log_index: u64) -> Result<&[u8], ProofVerificationError> {
+    let invalid_receipt = |err| ProofVerificationError::InvalidRlp("receipt", err);
+
+    // EIP-2718 typed receipt starts with the transaction type
+    let receipt_data = match receipt_data.first() {
+        Some(tx_type) if *tx_type < 0x7f => &receipt_data[1..],
+        _ => receipt_data,
+    };
+    let receipt = Rlp::new(receipt_data);
+
+    let status: Vec<u8> = receipt.val_at(0).map_err(invalid_receipt)?;
+    if status != [1] {
+        return Err(ProofVerificationError::FailedReceipt);
+    }
+
+    let logs = receipt.at(RECEIPT_LOGS_INDEX).map_err(invalid_receipt)?;
+    let log_count = logs.item_count().map_err(invalid_receipt)?;
+    if log_index as usize >= log_count {
+        return Err(ProofVerificationError::LogNotFound(log_index));
+    }
+    Ok(logs
+        .at(log_index as usize)
+        .map_err(invalid_receipt)?
+        .as_raw())
+}
+
+fn decode_transfer_tokens_log(
+    event_abi: &Event,
+    log_entry_data: &[u8],
+    bridge_address: Address,
+) -> Result<TransferTokensEvent, ProofVerificationError> {
+    let invalid_log = |err| ProofVerificationError::InvalidRlp("log entry", err);
+    let log = Rlp::new(log_entry_data);
+
+    let address: Vec<u8> = log.val_at(0).map_err(invalid_log)?;
+    if address.len() != Address::len_bytes() {
+        return Err(invalid_log(rlp::DecoderError::RlpInvalidLength));
+    }
+    let address = Address::from_slice(&address);
+    if address != bridge_address {
+        return Err(ProofVerificationError::UnexpectedEmitter(address));
+    }
+
+    let topics: Vec<Vec<u8>> = log.list_at(1).map_err(invalid_log)?;
+    if topics.iter().any(|topic| topic.len() != H256::len_bytes()) {
+        return Err(invalid_log(rlp::DecoderError::RlpInvalidLength));
+    }
+    let topics = topics.iter().map(|topic| H256::from_slice(topic)).collect();
+    let data: Vec<u8> = log.val_at(2).map_err(invalid_log)?;
+    TransferTokensEvent::from_raw_log(event_abi, RawLog { topics, data })
+        .map_err(|err| ProofVerificationError::NotTransferTokens(err.to_string()))
+}
+
+fn check_event_field<T: PartialEq + std::fmt::Debug>(
+    field: &'static str,
+    expected: T,
+    actual: T,
+) -> Result<(), ProofVerificationError> {
+    if expected != actual {
+        return Err(ProofVerificationError::EventMismatch {
+            field,
+            expected: format!("{:?}", expected),
+            actual: format!("{:?}", actual),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::async_redis_wrapper::EthTransferData;
+    use crate::ethereum::receipt_proof::{
+        verify_receipt_event, verify_transfer_proof, verify_trie_proof, ProofVerificationError,
+    };
+    use eth_client::fast_bridge::{transfer_tokens_event, TransferTokensEvent, FAST_BRIDGE_ABI};
+    use fast_bridge_common::Proof;
+    use web3::ethabi::{encode, Event, Token};
+    use web3::signing::keccak256;
+    use web3::types::{Address, Bytes, Log, H256, U256};
+
+    const BRIDGE: Address = Address::repeat_byte(0xb1);
+
+    fn transfer_tokens_abi() -> Event {
+        transfer_tokens_event(FAST_BRIDGE_ABI.as_bytes()).unwrap()
+    }
+
+    fn transfer() -> EthTransferData {
+        EthTransferData {
+            token: Address::repeat_byte(0x70),
+            recipient: Address::repeat_byte(0x7e),
+            amount: U256::from(1_000_000),
+            valid_till_block_height: Some(U256::from(8_300_000)),
+        }
+    }
+
+    fn log(address: Address, nonce: U256, transfer: &EthTransferData) -> Log {
+        Log {
+            address,
+            topics: vec![
+                TransferTokensEvent::topic(&transfer_tokens_abi()),
+                H256::from_low_u64_be(nonce.as_u64()),
+                H256::from_low_u64_be(8_300_000),
+            ],
+            data: Bytes(encode(&[
+                Token::Address(Address::repeat_byte(0x12)),
+                Token::Address(transfer.token),
+                Token::Address(transfer.recipient),
+                Token::Uint(transfer.amount),
+                Token::String("relayer.testnet".to_string()),
+            ])),
+            ..Default::default()
+        }
+    }
+
+    fn log_entry(address: Address, nonce: U256, transfer: &EthTransferData) -> Vec<u8> {
+        let log = log(address, nonce, transfer);
+
+        let mut log_entry = rlp::RlpStream::new_list(3);
+        log_entry
+            .append(&log.address.as_bytes().to_vec())
+            .begin_list(log.topics.len());
+        for topic in log.topics {
+            log_entry.append(&topic.as_bytes().to_vec());
+        }
+        log_entry.append(&log.data.0);
+        log_entry.out().to_vec()
+    }
+
+    fn receipt(logs: &[Vec<u8>]) -> Vec<u8> {
+        let mut receipt = rlp::RlpStream::new_list(4);
+        receipt
+            .append(&1u8)
+            .append(&21_000u64)
+            .append(&vec![0u8; 256])
+            .begin_list(logs.len());
+        for log in logs {
+            receipt.append_raw(log, 1);
+        }
+        // EIP-1559 receipt
+        [vec![2], receipt.out().to_vec()].concat()
+    }
+
+    /// The receipts trie with one receipt is one leaf node
+    fn leaf(key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut path = vec![0x20];
+        path.extend_from_slice(key);
+        let mut leaf = rlp::RlpStream::new_list(2);
+        leaf.append(&path).append(&value.to_vec());
+        leaf.out().to_vec()
+    }
+
+    fn header(receipts_root: &[u8]) -> Vec<u8> {
+        let mut header = rlp::RlpStream::new_list(15);
+        for _ in 0..5 {
+            header.append(&vec![1u8; 32]);
+        }
+        header.append(&receipts_root.to_vec());
+        header.append(&vec![0u8; 256]);
+        for _ in 0..8 {
+            header.append(&0u64);
+        }
+        header.out().to_vec()
+    }
+
+    fn proof(log_entry_data: Vec<u8>) -> Proof {
+        let receipt_data = receipt(&[vec![0xc0], log_entry_data.clone()]);
+        let node = leaf(&rlp::encode(&0u64), &receipt_data);
+        Proof {
+            log_index: 1,
+            log_entry_data,
+            receipt_index: 0,
+            receipt_data,
+            header_data: header(&keccak256(&node)),
+            proof: vec![node],
+        }
+    }
+
+    #[test]
+    fn verify_trie_proof_test() {
+        let value = vec![0x42; 40];
+        // The key 0x80 is the nibbles 8, 0: the branch goes to 8 and the leaf has the path 0
+        let mut leaf = rlp::RlpStream::new_list(2);
+        leaf.append(&vec![0x30u8]).append(&value);
+        let leaf = leaf.out().to_vec();
+
+        let mut branch = rlp::RlpStream::new_list(17);
+        for nibble in 0..17 {
+            match nibble {
+                8 => branch.append(&keccak256(&leaf).to_vec()),
+                _ => branch.append_empty_data(),
+            };
+        }
+        let branch = branch.out().to_vec();
+        let root = keccak256(&branch);
+        let proof = vec![branch.clone(), leaf.clone()];
+
+        assert_eq!(verify_trie_proof(&root, &[0x80], &proof), Ok(value));
+        assert!(verify_trie_proof(&root, &[0x81], &proof).is_err());
+        assert!(verify_trie_proof(&root, &[0x70], &proof).is_err());
+        assert!(verify_trie_proof(&[0u8; 32], &[0x80], &proof).is_err());
+        assert!(verify_trie_proof(&root, &[0x80], &[branch.clone()]).is_err());
+
+        let mut tampered_leaf = leaf;
+        *tampered_leaf.last_mut().unwrap() = 0x43;
+        assert!(verify_trie_proof(&root, &[0x80], &[branch, tampered_leaf]).is_err());
+    }
+
+    #[test]
+    fn verify_transfer_proof_test() {
+        let nonce = U256::from(17);
+        let proof = proof(log_entry(BRIDGE, nonce, &transfer()));
+
+        let event = verify_transfer_proof(
+            &transfer_tokens_abi(),
+            &proof,
+            BRIDGE,
+            nonce,
+            Some(&transfer()),
+        )
+        .unwrap();
+        assert_eq!(event.nonce, nonce);
+        assert_eq!(event.amount, transfer().amount);
+        assert!(verify_transfer_proof(&transfer_tokens_abi(), &proof, BRIDGE, nonce, None).is_ok());
+
+        let err =
+            verify_transfer_proof(&transfer_tokens_abi(), &proof, BRIDGE, U256::from(18), None)
+                .unwrap_err();
+        assert!(err.is_event_mismatch());
+
+        let other_transfer = EthTransferData {
+            amount: U256::from(1),
+            ..transfer()
+        };
+        let err = verify_transfer_proof(
+            &transfer_tokens_abi(),
+            &proof,
+            BRIDGE,
+            nonce,
+            Some(&other_transfer),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ProofVerificationError::EventMismatch {
+                field: "amount",
+                ..
+            }
+        ));
+
+        let other_bridge = Address::repeat_byte(0xb2);
+        let err = verify_transfer_proof(&transfer_tokens_abi(), &proof, other_bridge, nonce, None)
+            .unwrap_err();
+        assert_eq!(err, ProofVerificationError::UnexpectedEmitter(BRIDGE));
+
+        let mut wrong_log = proof.clone();
+        wrong_log.log_index = 0;
+        assert!(
+            verify_transfer_proof(&transfer_tokens_abi(), &wrong_log, BRIDGE, nonce, None).is_err()
+        );
+
+        let mut wrong_receipt = proof.clone();
+        wrong_receipt.receipt_data.push(0);
+        assert_eq!(
+            verify_transfer_proof(&transfer_tokens_abi(), &wrong_receipt, BRIDGE, nonce, None)
+                .unwrap_err(),
+            ProofVerificationError::ReceiptMismatch
+        );
+
+        let mut wrong_header = proof;
+        wrong_header.header_data = header(&[0u8; 32]);
+        assert!(matches!(
+            verify_transfer_proof(&transfer_tokens_abi(), &wrong_header, BRIDGE, nonce, None)
+                .unwrap_err(),
+            ProofVerificationError::InvalidTrieProof(_)
+        ));
+    }
+
+    #[test]
+    fn verify_receipt_event_test() {
+        let nonce = U256::from(17);
+        let other_log = Log {
+            address: BRIDGE,
+            topics: vec![H256::repeat_byte(1)],
+            ..Default::default()
+        };
+        let logs = vec![other_log, log(BRIDGE, nonce, &transfer())];
+
+        let event = verify_receipt_event(&transfer_tokens_abi(), &logs, BRIDGE, nonce, &transfer())
+            .unwrap();
+        assert_eq!(event.recipient, transfer().recipient);
+
+        let other_recipient = EthTransferData {
+            recipient: Address::repeat_byte(0x7f),
+            ..transfer()
+        };
+        let err = verify_receipt_event(
+            &transfer_tokens_abi(),
+            &logs,
+            BRIDGE,
+            nonce,
+            &other_recipient,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ProofVerificationError::EventMismatch {
+                field: "recipient",
+                ..
+            }
+        ));
+
+        let other_deadline = EthTransferData {
+            valid_till_block_height: Some(U256::from(8_300_001)),
+            ..transfer()
+        };
+        assert!(verify_receipt_event(
+            &transfer_tokens_abi(),
+            &logs,
+            BRIDGE,
+            nonce,
+            &other_deadline
+        )
+        .unwrap_err()
+        .is_event_mismatch());
+
+        assert_eq!(
+            verify_receipt_event(
+                &transfer_tokens_abi(),
+                &logs[..1],
+                BRIDGE,
+                nonce,
+                &transfer()
+            )
+            .unwrap_err(),
+            ProofVerificationError::EventNotFound
+        );
+        assert_eq!(
+            verify_receipt_event(
+                &transfer_tokens_abi(),
+                &logs,
+                Address::repeat_byte(0xb2),
+                nonce,
+                &transfer()
+            )
+            .unwrap_err(),
+            ProofVerificationError::EventNotFound
+        );
+    }
+}
@@ -1,4 +1,10 @@
-use web3::{api, error::Error, types::TransactionId};
+use web3::{
+    api::{self, Namespace},
+    error::Error,
+    transports::Batch,
+    types::{Transaction, TransactionId, TransactionReceipt, H256, U64},
+    BatchTransport,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransactionStatus {
@@ -7,6 +13,13 @@ pub enum TransactionStatus {
     Success(web3::types::U64), // block_number
 }
 
+/// The statuses of the transactions and the last block number fetched in one JSON-RPC batch
+#[derive(Debug)]
+pub struct TransactionStatuses {
+    pub block_number: Option<U64>,
+    pub statuses: Vec<web3::error::Result<TransactionStatus>>,
+}
+
 pub async fn transaction_status<T: web3::Transport>(
     client: &api::Eth<T>,
     tx_hash: web3::types::H256,
@@ -19,9 +32,59 @@ pub async fn transaction_status<T: web3::Transport>(
 
     let res = client.transaction_receipt(tx_hash).await?;
     let res = res.ok_or(Error::Unreachable)?;
+    receipt_status(&res)
+}
+
+/// Requests `eth_getTransactionByHash` and `eth_getTransactionReceipt` of every transaction
+/// and `eth_blockNumber` in a single batch
+pub async fn transaction_statuses<T: BatchTransport>(
+    transport: T,
+    tx_hashes: &[H256],
+) -> web3::error::Result<TransactionStatuses> {
+    let batch = Batch::new(transport);
+    let client = api::Eth::new(batch.clone());
+
+    let block_number = client.block_number();
+    let requests: Vec<_> = tx_hashes
+        .iter()
+        .map(|tx_hash| {
+            (
+                client.transaction(TransactionId::from(*tx_hash)),
+                client.transaction_receipt(*tx_hash),
+            )
+        })
+        .collect();
+    batch.submit_batch().await?;
+
+    let mut statuses = Vec::with_capacity(requests.len());
+    for (transaction, receipt) in requests {
+        statuses.push(match (transaction.await, receipt.await) {
+            (Ok(transaction), Ok(receipt)) => status_from(transaction, receipt),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        });
+    }
+
+    Ok(TransactionStatuses {
+        block_number: block_number.await.ok(),
+        statuses,
+    })
+}
+
+fn status_from(
+    transaction: Option<Transaction>,
+    receipt: Option<TransactionReceipt>,
+) -> web3::error::Result<TransactionStatus> {
+    let transaction = transaction.ok_or(Error::Unreachable)?;
+    match receipt {
+        Some(receipt) if transaction.block_number.is_some() => receipt_status(&receipt),
+        // The transaction could be included between the requests of the batch
+        _ => Ok(TransactionStatus::Pending),
+    }
+}
 
-    if let Some(s) = res.status {
-        let block_number = res.block_number.unwrap();
+fn receipt_status(receipt: &TransactionReceipt) -> web3::error::Result<TransactionStatus> {
+    if let Some(s) = receipt.status {
+        let block_number = receipt.block_number.ok_or(Error::Unreachable)?;
         return Ok(if s == web3::types::U64::from(0) {
             TransactionStatus::Failure(block_number)
         } else {
@@ -34,10 +97,73 @@ pub async fn transaction_status<T: web3::Transport>(
 
 #[cfg(test)]
 pub mod tests {
-    use crate::ethereum::transactions::{transaction_status, TransactionStatus};
-    use eth_client::test_utils::get_eth_rpc_url;
+    use crate::ethereum::transactions::{
+        transaction_status, transaction_statuses, TransactionStatus,
+    };
+    use eth_client::test_utils::{get_eth_rpc_url, RecordedTransport};
+    use serde_json::json;
     use web3::api::Namespace;
-    use web3::types::U64;
+    use web3::types::{Transaction, TransactionReceipt, H256, U64};
+
+    #[tokio::test]
+    async fn transaction_statuses_test() {
+        let transport = RecordedTransport::default();
+        let mined = |block_number: u64| Transaction {
+            block_number: Some(block_number.into()),
+            ..Default::default()
+        };
+        let receipt = |block_number: u64, status: u64| TransactionReceipt {
+            block_number: Some(block_number.into()),
+            status: Some(status.into()),
+            ..Default::default()
+        };
+
+        transport.push_response("eth_blockNumber", json!("0x20"));
+        for transaction in [
+            json!(mined(10)),
+            json!(mined(11)),
+            json!(Transaction::default()),
+            json!(mined(12)),
+            json!(null),
+        ] {
+            transport.push_response("eth_getTransactionByHash", transaction);
+        }
+        for receipt in [
+            json!(receipt(10, 1)),
+            json!(receipt(11, 0)),
+            json!(null),
+            json!(null),
+            json!(null),
+        ] {
+            transport.push_response("eth_getTransactionReceipt", receipt);
+        }
+
+        let tx_hashes: Vec<H256> = (1..=5).map(H256::from_low_u64_be).collect();
+        let statuses = transaction_statuses(transport.clone(), &tx_hashes)
+            .await
+            .unwrap();
+
+        assert_eq!(transport.batches_count(), 1);
+        assert_eq!(statuses.block_number, Some(U64::from(0x20)));
+        assert_eq!(
+            statuses.statuses[0].as_ref().unwrap(),
+            &TransactionStatus::Success(10.into())
+        );
+        assert_eq!(
+            statuses.statuses[1].as_ref().unwrap(),
+            &TransactionStatus::Failure(11.into())
+        );
+        assert_eq!(
+            statuses.statuses[2].as_ref().unwrap(),
+            &TransactionStatus::Pending
+        );
+        // The receipt isn't available yet
+        assert_eq!(
+            statuses.statuses[3].as_ref().unwrap(),
+            &TransactionStatus::Pending
+        );
+        assert!(statuses.statuses[4].is_err());
+    }
 
     #[tokio::test]
     async fn smoke_transaction_status_test() {
@@ -0,0 +1,146 @@
+use crate::config::{FeeOracleLevelSettings, FeeOracleSettings, Settings};
+use crate::errors::CustomError;
+use crate::logs::EVENT_PROCESSOR_TARGET;
+use eth_client::fee_oracle::{FeeLimits, FeeOracleLevel};
+use eth_client::methods::{EthClient, FeeData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Medium,
+    High,
+}
+
+impl Urgency {
+    // The index of the level reward percentile in the `eth_feeHistory` request
+    fn reward_index(self) -> usize {
+        match self {
+            Urgency::Low => 0,
+            Urgency::Medium => 1,
+            Urgency::High => 2,
+        }
+    }
+}
+
+pub fn get_urgency(
+    fee_oracle: &FeeOracleSettings,
+    current_block: u64,
+    valid_till_block_height: Option<u64>,
+) -> Urgency {
+    let valid_till_block_height = match valid_till_block_height {
+        Some(valid_till_block_height) => valid_till_block_height,
+        None => return Urgency::Medium,
+    };
+
+    let blocks_left = valid_till_block_height.saturating_sub(current_block);
+    if blocks_left < fee_oracle.high_urgency_blocks_left {
+        Urgency::High
+    } else if blocks_left < fee_oracle.medium_urgency_blocks_left {
+        Urgency::Medium
+    } else {
+        Urgency::Low
+    }
+}
+
+/// Fee data for the transfer, the static `max_priority_fee_per_gas` is used without the fee oracle
+pub async fn get_fee_data(
+    settings: &Settings,
+    eth_client: &EthClient,
+    valid_till_block_height: Option<u64>,
+) -> Result<FeeData, CustomError> {
+    let fee_oracle = match &settings.fee_oracle {
+        Some(fee_oracle) => fee_oracle,
+        None => {
+            return eth_client
+                .get_fee_data(settings.max_priority_fee_per_gas)
+                .await
+                .map_err(CustomError::FailedFetchGasPrice)
+        }
+    };
+
+    let levels = [&fee_oracle.low, &fee_oracle.medium, &fee_oracle.high];
+    let fee_history = eth_client
+        .get_fee_history(
+            fee_oracle.fee_history_blocks,
+            Some(levels.iter().map(|level| level.reward_percentile).collect()),
+        )
+        .await
+        .map_err(CustomError::FailedFetchGasPrice)?;
+
+    let current_block = eth_client::fee_oracle::newest_block(&fee_history)
+        .map_err(CustomError::FailedFetchGasPrice)?;
+    if let (Some(max_fee_per_gas), Some(base_fee_per_gas)) = (
+        fee_oracle.max_fee_per_gas,
+        fee_history.base_fee_per_gas.last().copied(),
+    ) {
+        if base_fee_per_gas > max_fee_per_gas {
+            return Err(CustomError::BaseFeeAboveMaxFee(
+                base_fee_per_gas,
+                max_fee_per_gas,
+            ));
+        }
+    }
+
+    let urgency = get_urgency(fee_oracle, current_block, valid_till_block_height);
+    let level: &FeeOracleLevelSettings = levels[urgency.reward_index()];
+
+    let fee_data = eth_client::fee_oracle::estimate_fee_data(
+        &fee_history,
+        urgency.reward_index(),
+        &FeeOracleLevel {
+            reward_percentile: level.reward_percentile,
+            base_fee_multiplier: level.base_fee_multiplier,
+        },
+        &FeeLimits {
+            min_priority_fee_per_gas: fee_oracle.min_priority_fee_per_gas,
+            max_priority_fee_per_gas: settings.max_priority_fee_per_gas,
+            max_fee_per_gas: fee_oracle.max_fee_per_gas,
+        },
+    )
+    .map_err(CustomError::FailedFetchGasPrice)?;
+
+    tracing::info!(
+        target: EVENT_PROCESSOR_TARGET,
+        "Fee oracle: urgency {:?} at block {} (valid till block {:?}), fee data: {:?}",
+        urgency,
+        current_block,
+        valid_till_block_height,
+        fee_data
+    );
+
+    Ok(fee_data)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::config::{FeeOracleLevelSettings, FeeOracleSettings};
+    use crate::fee_oracle::{get_urgency, Urgency};
+
+    fn level(reward_percentile: f64) -> FeeOracleLevelSettings {
+        FeeOracleLevelSettings {
+            reward_percentile,
+            base_fee_multiplier: 2.,
+        }
+    }
+
+    #[test]
+    fn get_urgency_test() {
+        let fee_oracle = FeeOracleSettings {
+            fee_history_blocks: 20,
+            low: level(10.),
+            medium: level(50.),
+            high: level(90.),
+            medium_urgency_blocks_left: 300,
+            high_urgency_blocks_left: 50,
+            min_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+        };
+
+        assert_eq!(get_urgency(&fee_oracle, 1000, Some(1300)), Urgency::Low);
+        assert_eq!(get_urgency(&fee_oracle, 1000, Some(1299)), Urgency::Medium);
+        assert_eq!(get_urgency(&fee_oracle, 1000, Some(1050)), Urgency::Medium);
+        assert_eq!(get_urgency(&fee_oracle, 1000, Some(1049)), Urgency::High);
+        assert_eq!(get_urgency(&fee_oracle, 1000, Some(900)), Urgency::High);
+        assert_eq!(get_urgency(&fee_oracle, 1000, None), Urgency::Medium);
+    }
+}
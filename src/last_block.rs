@@ -1,24 +1,45 @@
 use crate::prometheus_metrics::LAST_ETH_BLOCK_ON_NEAR;
 use crate::{config::SafeSettings, errors::CustomError};
+use near_client::errors::NearClientError;
+use near_client::methods::NearClient;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use near_sdk::borsh::BorshDeserialize;
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use tokio::sync::watch;
 
 pub type SafeStorage = std::sync::Arc<tokio::sync::Mutex<Storage>>;
 
 #[derive(Clone, Debug)]
 pub struct Storage {
     pub eth_last_block_number_on_near: u64,
+    /// Publishes the new light client blocks to the subscribed workers
+    eth_last_block_number_sender: std::sync::Arc<watch::Sender<u64>>,
 }
 
 impl Storage {
     pub fn new() -> Self {
+        let (sender, _) = watch::channel(0);
         Storage {
             eth_last_block_number_on_near: 0,
+            eth_last_block_number_sender: std::sync::Arc::new(sender),
         }
     }
+
+    pub fn set_eth_last_block_number_on_near(&mut self, block_number: u64) {
+        self.eth_last_block_number_on_near = block_number;
+        self.eth_last_block_number_sender.send_replace(block_number);
+    }
+
+    /// The receiver is notified when the light client on NEAR reaches a new block
+    pub fn subscribe_eth_last_block_number_on_near(&self) -> watch::Receiver<u64> {
+        self.eth_last_block_number_sender.subscribe()
+    }
 }
 
-pub async fn last_block_number_worker(settings: SafeSettings, storage: SafeStorage) {
+pub async fn last_block_number_worker(
+    settings: SafeSettings,
+    near_client: NearClient,
+    storage: SafeStorage,
+) {
     tokio::spawn(async move {
         loop {
             let last_block_number_worker_settings =
@@ -31,14 +52,17 @@ pub async fn last_block_number_worker(settings: SafeSettings, storage: SafeStora
             interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
             interval.tick().await;
             let number = last_block_number(
-                last_block_number_worker_settings.server_addr,
+                &near_client,
                 last_block_number_worker_settings.contract_account_id,
             )
             .await;
             match number {
                 Ok(result) => match result {
                     Some(block_number) => {
-                        storage.lock().await.eth_last_block_number_on_near = block_number;
+                        storage
+                            .lock()
+                            .await
+                            .set_eth_last_block_number_on_near(block_number);
                         LAST_ETH_BLOCK_ON_NEAR.set(block_number);
                     }
                     None => (),
@@ -50,14 +74,15 @@ pub async fn last_block_number_worker(settings: SafeSettings, storage: SafeStora
 }
 
 pub async fn last_block_number(
-    server_addr: url::Url,
+    near_client: &NearClient,
     contract_account_id: String,
 ) -> Result<Option<u64>, CustomError> {
     let method_name = "last_block_number".to_string();
     let args = serde_json::json!({});
-    let response = near_client::methods::view(server_addr, contract_account_id, method_name, args)
+    let response = near_client
+        .view(contract_account_id, method_name, args)
         .await
-        .map_err(|err| CustomError::FailedExecuteLastBlockNumber(err.to_string()))?;
+        .map_err(CustomError::FailedExecuteLastBlockNumber)?;
 
     if let QueryResponseKind::CallResult(result) = response.kind {
         Ok(Some(u64::try_from_slice(&result.result).unwrap()))
@@ -66,24 +91,57 @@ pub async fn last_block_number(
     }
 }
 
+/// The hash of the block stored by the light client, `None` if the block is unknown or pruned
+pub async fn block_hash_safe(
+    near_client: &NearClient,
+    contract_account_id: String,
+    block_number: u64,
+) -> Result<Option<web3::types::H256>, CustomError> {
+    let method_name = "block_hash_safe".to_string();
+    let args = block_number.try_to_vec().unwrap();
+    let response = near_client
+        .view_raw(contract_account_id, method_name, args)
+        .await
+        .map_err(CustomError::FailedExecuteBlockHashSafe)?;
+
+    if let QueryResponseKind::CallResult(result) = response.kind {
+        let hash = Option::<[u8; 32]>::try_from_slice(&result.result).map_err(|err| {
+            CustomError::FailedExecuteBlockHashSafe(NearClientError::UnexpectedResponse(
+                err.to_string(),
+            ))
+        })?;
+        Ok(hash.map(web3::types::H256::from))
+    } else {
+        Ok(None)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
-    use crate::last_block::{last_block_number, last_block_number_worker, Storage};
+    use crate::last_block::{
+        block_hash_safe, last_block_number, last_block_number_worker, Storage,
+    };
     use crate::logs::init_logger;
     use crate::test_utils::get_settings;
+    use near_client::test_utils::get_near_client;
     use std::time::Duration;
 
     #[tokio::test]
     async fn smoke_last_block_number_test() {
         init_logger();
-        let server_addr = url::Url::parse("https://rpc.testnet.near.org").unwrap();
         let contract_account_id = "client-eth2.goerli.testnet".to_string();
 
-        let last_block_number = last_block_number(server_addr, contract_account_id)
+        let last_block_number = last_block_number(&get_near_client(), contract_account_id.clone())
             .await
             .unwrap()
             .unwrap();
         println!("last_block_number = {}", last_block_number);
+
+        let block_hash =
+            block_hash_safe(&get_near_client(), contract_account_id, last_block_number)
+                .await
+                .unwrap();
+        assert!(block_hash.is_some());
     }
 
     #[tokio::test]
@@ -94,7 +152,8 @@ pub mod tests {
 
         let storage = std::sync::Arc::new(tokio::sync::Mutex::new(Storage::new()));
 
-        let _last_block_worker = last_block_number_worker(settings.clone(), storage.clone()).await;
+        let _last_block_worker =
+            last_block_number_worker(settings.clone(), get_near_client(), storage.clone()).await;
         tokio::time::sleep(Duration::from_secs(16)).await;
 
         let new_last_block_number = storage.clone().lock().await.eth_last_block_number_on_near;
@@ -1,15 +1,22 @@
 pub mod async_redis_wrapper;
+pub mod bridge_contract;
 pub mod config;
+pub mod config_check;
+pub mod config_reloader;
 pub mod errors;
 pub mod ethereum;
+pub mod fee_oracle;
 pub mod last_block;
 pub mod logs;
 pub mod near_event_processor;
 pub mod near_events_tracker;
 pub mod pending_transactions_worker;
+pub mod price_sources;
 pub mod profit_estimation;
 pub mod prometheus_metrics;
 pub mod transfer;
+pub mod unlock_failure;
+pub mod unlock_schedule;
 pub mod unlock_tokens;
 pub mod utils;
 pub mod vault_private_key;
@@ -1,6 +1,8 @@
 pub const NEAR_EVENTS_TRACER_TARGET: &str = "near_events_tracker";
 pub const EVENT_PROCESSOR_TARGET: &str = "event_processor";
 pub const PENDING_TRANSACTION_TARGET: &str = "pending_transactions";
+pub const CONFIG_RELOADER_TARGET: &str = "config_reloader";
+pub const BRIDGE_CONTRACT_TARGET: &str = "bridge_contract";
 
 pub fn init_logger() {
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
@@ -1,15 +1,22 @@
 mod async_redis_wrapper;
+mod bridge_contract;
 mod config;
+mod config_check;
+mod config_reloader;
 mod errors;
 mod ethereum;
+mod fee_oracle;
 mod last_block;
 mod logs;
 mod near_event_processor;
 mod near_events_tracker;
 mod pending_transactions_worker;
+mod price_sources;
 mod profit_estimation;
 mod prometheus_metrics;
 mod transfer;
+mod unlock_failure;
+mod unlock_schedule;
 mod unlock_tokens;
 mod utils;
 mod vault_private_key;
@@ -20,6 +27,10 @@ mod test_utils;
 use crate::config::Settings;
 use crate::logs::init_logger;
 use clap::Parser;
+use eth_client::chain_head::ChainHeadService;
+use eth_client::methods::EthClient;
+use near_client::methods::NearClient;
+use near_client::signer::NearSigner;
 use near_crypto::InMemorySigner;
 use std::str::FromStr;
 use std::thread;
@@ -44,6 +55,38 @@ struct Args {
     /// to override the value from redis
     #[clap(long)]
     near_lake_init_block: Option<u64>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// validate the config and exit, the exit code is non-zero if there are errors
+    CheckConfig {
+        /// also compare the token decimals with the ERC20 and NEAR token contracts
+        #[clap(long)]
+        online: bool,
+    },
+}
+
+async fn check_config(settings: &Settings, online: bool) -> ! {
+    let mut issues = config_check::check(settings);
+    if online {
+        issues.extend(config_check::check_online(settings).await);
+    }
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    if config_check::has_errors(&issues) {
+        println!("Config {} is invalid", settings.config_path);
+        std::process::exit(1);
+    }
+
+    println!("Config {} is valid", settings.config_path);
+    std::process::exit(0);
 }
 
 async fn get_eth_private_key(
@@ -73,10 +116,24 @@ async fn get_near_private_key(args: &Args, settings: &Settings) -> InMemorySigne
     }
 }
 
-async fn check_system_time(near_rpc_url: url::Url) {
+/// The additional function call access keys of the relayer account
+fn get_near_function_call_keys(settings: &Settings) -> Vec<InMemorySigner> {
+    settings
+        .near
+        .function_call_key_paths
+        .iter()
+        .map(|path| {
+            near_client::read_private_key::read_private_key_from_file(path.as_str())
+                .unwrap_or_else(|err| panic!("Unable to get a NEAR access key: {}", err))
+        })
+        .collect()
+}
+
+async fn check_system_time(near_client: &NearClient) {
     const MAX_TIMESTAMP_DIFF_SEC: u64 = 60;
 
-    let near_timestamp_ns = near_client::methods::get_final_block_timestamp(near_rpc_url)
+    let near_timestamp_ns = near_client
+        .get_final_block_timestamp()
         .await
         .expect("Error on getting NEAR block timestamp");
     let near_timestamp_sec = std::time::Duration::from_nanos(near_timestamp_ns).as_secs();
@@ -96,6 +153,25 @@ async fn check_system_time(near_rpc_url: url::Url) {
     }
 }
 
+async fn check_eth_chain_id(settings: &Settings, eth_client: &EthClient) {
+    let expected_chain_id = match settings.eth.chain_id {
+        Some(chain_id) => chain_id,
+        None => return,
+    };
+
+    let chain_id = eth_client
+        .get_chain_id()
+        .await
+        .expect("Error on getting Ethereum chain id");
+
+    if chain_id != expected_chain_id {
+        panic!(
+            "Incorrect Ethereum network. RPC chain id = {}, expected chain id = {}",
+            chain_id, expected_chain_id
+        );
+    }
+}
+
 #[allow(unused_must_use)]
 #[tokio::main]
 async fn main() {
@@ -103,6 +179,16 @@ async fn main() {
 
     init_logger();
 
+    if let Some(Command::CheckConfig { online }) = args.command {
+        match Settings::init(args.config.clone()) {
+            Ok(settings) => check_config(&settings, online).await,
+            Err(msg) => {
+                println!("error: $: {}", msg);
+                std::process::exit(1);
+            }
+        }
+    }
+
     let settings = match Settings::init(args.config.clone()) {
         Ok(settings) => std::sync::Arc::new(tokio::sync::Mutex::new(settings)),
         Err(msg) => panic!("{}", msg),
@@ -113,7 +199,29 @@ async fn main() {
         thread::spawn(move || prometheus_metrics::run_prometheus_service(port));
     }
 
-    check_system_time(locked_settings.near.rpc_url.clone()).await;
+    let eth_client = EthClient::new(
+        &locked_settings.eth.rpc_urls(),
+        locked_settings.rpc_timeout_secs,
+        locked_settings.rpc_retry_policy(),
+    )
+    .expect("Failed to create Ethereum client");
+
+    let new_near_client = |rpc_urls: Vec<url::Url>| {
+        NearClient::new(&rpc_urls, locked_settings.rpc_timeout_secs)
+            .expect("Failed to create NEAR client")
+    };
+    let near_client = new_near_client(locked_settings.near.rpc_urls());
+
+    check_system_time(&near_client).await;
+    check_eth_chain_id(&locked_settings, &eth_client).await;
+
+    let (chain_head_service, chain_head) = ChainHeadService::new(
+        eth_client.clone(),
+        locked_settings.eth.ws_url.clone(),
+        std::time::Duration::from_secs(locked_settings.eth.head_poll_interval_secs),
+    )
+    .await
+    .expect("Error on getting the latest Ethereum block");
 
     let mut async_redis =
         async_redis_wrapper::AsyncRedisWrapper::connect(&locked_settings.redis).await;
@@ -122,15 +230,9 @@ async fn main() {
     let eth_keypair = get_eth_private_key(&args, &locked_settings.clone()).await;
     let eth_contract_address = std::sync::Arc::new(locked_settings.eth.bridge_proxy_address);
 
-    let eth_contract_abi = std::sync::Arc::new(
-        eth_client::methods::get_contract_abi(
-            locked_settings.etherscan_api.endpoint_url.as_ref(),
-            locked_settings.eth.bridge_impl_address,
-            &locked_settings.etherscan_api.api_key,
-        )
-        .await
-        .expect("Failed to get contract abi"),
-    );
+    let bridge_contract = std::sync::Arc::new(tokio::sync::Mutex::new(
+        bridge_contract::init(&locked_settings, &eth_client).await,
+    ));
 
     let near_account = get_near_private_key(&args, &locked_settings.clone()).await;
 
@@ -157,32 +259,70 @@ async fn main() {
 
     let near_events_processor_worker = near_event_processor::process_near_events_worker(
         settings.clone(),
+        eth_client.clone(),
+        near_client,
         eth_keypair.clone(),
         async_redis.clone(),
-        eth_contract_abi.clone(),
+        bridge_contract.clone(),
         eth_contract_address.clone(),
         near_account.account_id.to_string(),
+        storage.clone(),
+        chain_head.clone(),
     );
 
     let pending_transactions_worker = pending_transactions_worker::run(
-        locked_settings.eth.rpc_url.clone(),
+        eth_client.clone(),
         locked_settings.eth.rainbow_bridge_index_js_path.clone(),
         async_redis.clone(),
-        locked_settings.rpc_timeout_secs.clone(),
+        locked_settings.eth.receipt_quorum,
+        settings.clone(),
+        bridge_contract.clone(),
+        chain_head,
+    );
+
+    let last_block_number_worker = last_block::last_block_number_worker(
+        settings.clone(),
+        new_near_client(
+            locked_settings
+                .near
+                .rpc_urls_with(&locked_settings.last_block_number_worker.server_addr),
+        ),
+        storage.clone(),
     );
 
-    let last_block_number_worker =
-        last_block::last_block_number_worker(settings.clone(), storage.clone());
+    let mut near_signers = vec![near_account.clone()];
+    near_signers.extend(get_near_function_call_keys(&locked_settings));
+    let near_signer = NearSigner::new(
+        new_near_client(
+            locked_settings
+                .near
+                .rpc_urls_with(&locked_settings.unlock_tokens_worker.server_addr),
+        ),
+        near_signers,
+    )
+    .expect("Failed to create NEAR signer");
 
     let unlock_tokens_worker = unlock_tokens::unlock_tokens_worker(
-        near_account.clone(),
-        300_000_000_000_000,
+        near_signer,
+        eth_client.clone(),
+        locked_settings.eth.rainbow_bridge_index_js_path.clone(),
+        locked_settings.eth.receipt_quorum,
+        locked_settings.unlock_tokens_worker.gas,
         settings.clone(),
         storage.clone(),
+        bridge_contract.clone(),
         async_redis,
     );
 
+    let bridge_contract_worker =
+        bridge_contract::bridge_contract_worker(settings.clone(), eth_client, bridge_contract);
+
+    let config_reloader = config_reloader::run(settings.clone());
+
     let tasks = vec![
+        tokio::spawn(config_reloader),
+        tokio::spawn(chain_head_service.run()),
+        tokio::spawn(bridge_contract_worker),
         tokio::spawn(last_block_number_worker),
         tokio::spawn(near_events_processor_worker),
         tokio::spawn(pending_transactions_worker),
@@ -198,6 +338,7 @@ async fn main() {
 #[cfg(test)]
 pub mod tests {
     use crate::{check_system_time, last_block};
+    use near_client::methods::NearClient;
     use std::str::FromStr;
 
     const APP_USER_AGENT: &str = "fast-bridge-service/0.1.0";
@@ -206,9 +347,13 @@ pub mod tests {
         "https://goerli.infura.io/v3/ba5fd6c86e5c4e8c9b36f3f5b4013f7a";
     const ETHERSCAN_RPC_ENDPOINT_URL: &str = "https://api-goerli.etherscan.io";
 
+    fn near_client() -> NearClient {
+        NearClient::new(&[url::Url::from_str(NEAR_RPC_ENDPOINT_URL).unwrap()], 30).unwrap()
+    }
+
     #[tokio::test]
     async fn check_sys_time_test() {
-        check_system_time(url::Url::from_str(NEAR_RPC_ENDPOINT_URL).unwrap()).await;
+        check_system_time(&near_client()).await;
     }
 
     #[tokio::test]
@@ -246,11 +391,9 @@ pub mod tests {
 
     #[tokio::test]
     pub async fn last_block_number() {
-        let result = last_block::last_block_number(
-            NEAR_RPC_ENDPOINT_URL.try_into().unwrap(),
-            "client-eth2.goerli.testnet".to_string(),
-        )
-        .await;
+        let result =
+            last_block::last_block_number(&near_client(), "client-eth2.goerli.testnet".to_string())
+                .await;
 
         assert!(result.unwrap().unwrap() >= 8129711);
     }
@@ -1,11 +1,18 @@
-use crate::async_redis_wrapper::{self, AsyncRedisWrapper, NEW_EVENTS};
+use crate::async_redis_wrapper::{self, AsyncRedisWrapper, EthTransferData, NEW_EVENTS};
+use crate::bridge_contract::SafeBridgeContract;
 use crate::config::{SafeSettings, Settings};
+use crate::errors::RejectionReason;
+use crate::last_block::SafeStorage;
 use crate::prometheus_metrics::{
-    BALANCE_ERRORS, CONNECTION_ERRORS, NEAR_EVENTS_PROCESSOR_CURRENT_ETH_BLOCK_HEIGHT,
-    PENDING_TRANSACTIONS_COUNT, SKIP_TRANSACTIONS_COUNT,
+    BALANCE_ERRORS, CONNECTION_ERRORS, DEFERRED_TRANSACTIONS_COUNT,
+    NEAR_EVENTS_PROCESSOR_CURRENT_ETH_BLOCK_HEIGHT, PENDING_TRANSACTIONS_COUNT,
+    SKIP_TRANSACTIONS_COUNT, STALE_CHAIN_HEAD,
 };
 use crate::{errors::CustomError, utils::get_tx_count};
+use eth_client::chain_head::{wait_for_new_head, ChainHeadReceiver};
+use eth_client::methods::{BaseFeeForecast, EthClient};
 use fast_bridge_common::Event::FastBridgeInitTransferEvent;
+use near_client::methods::NearClient;
 use near_sdk::AccountId;
 use redis::AsyncCommands;
 use std::collections::HashMap;
@@ -25,6 +32,34 @@ macro_rules! error {
 }
 
 const SLEEP_TIME_AFTER_EVENTS_PROCESS_SEC: u64 = 10;
+const BASE_FEE_FORECAST_BLOCKS: u64 = 20;
+/// The shared head older than 5 Ethereum blocks isn't trusted, the latest block is read over RPC
+const MAX_CHAIN_HEAD_AGE: Duration = Duration::from_secs(60);
+/// The deferred transfers aren't re-priced while the next block base fee exceeds the median base fee
+/// over `BASE_FEE_FORECAST_BLOCKS` by this percent, they wait until the spike is over
+const BASE_FEE_SPIKE_PERCENT: u64 = 50;
+
+/// The transfer which isn't profitable yet, it stays in the new events and is re-priced each loop
+/// until it becomes profitable or there is no time left to execute it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeferredTransfer {
+    pub deferred_at: u64,
+    pub attempts: u64,
+}
+
+impl DeferredTransfer {
+    /// Seconds since the transfer is deferred
+    pub fn deferred_secs(&self) -> u64 {
+        unix_timestamp().saturating_sub(self.deferred_at)
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
 
 #[allow(clippy::too_many_arguments)]
 pub async fn process_transfer_event(
@@ -32,15 +67,20 @@ pub async fn process_transfer_event(
     sender_id: AccountId,
     transfer_message: fast_bridge_common::TransferMessage,
     settings: &Settings,
+    eth_client: &EthClient,
+    near_client: &NearClient,
     redis: &mut AsyncRedisWrapper,
     eth_erc20_fast_bridge_proxy_contract_address: web3::types::Address,
     relay_eth_key: std::sync::Arc<secp256k1::SecretKey>,
     eth_erc20_fast_bridge_contract_abi: std::sync::Arc<String>,
     near_relay_account_id: String,
     pending_events: &mut HashMap<u128, H256>,
+    deferred_events: &mut HashMap<u128, DeferredTransfer>,
+    eth_last_block_number_on_near: u64,
+    current_eth_block_height: u64,
 ) -> Result<(), CustomError> {
-    let rpc_url = settings.eth.rpc_url.clone();
-    let transaction_count = get_tx_count(redis, rpc_url.clone(), relay_eth_key.address()).await?;
+    let transaction_count = get_tx_count(redis, eth_client, relay_eth_key.address()).await?;
+    let transfer = EthTransferData::from_transfer_message(&transfer_message);
 
     info!("Execute transfer on eth with nonce {:?}", nonce);
 
@@ -49,6 +89,7 @@ pub async fn process_transfer_event(
         return update_pending_transactions(
             tx_hash,
             nonce,
+            transfer,
             redis,
             transaction_count,
             pending_events,
@@ -64,27 +105,78 @@ pub async fn process_transfer_event(
             transfer_message,
         },
         eth_erc20_fast_bridge_contract_abi.as_bytes(),
-        rpc_url,
+        eth_client,
+        near_client,
         eth_erc20_fast_bridge_proxy_contract_address,
         settings.profit_thershold,
         &settings,
         near_relay_account_id,
         transaction_count,
+        eth_last_block_number_on_near,
+        current_eth_block_height,
     )
     .await;
 
     match tx_hash {
         Ok(tx_hash) => {
             info!("New eth transaction: {:#?}", tx_hash);
+            if let Some(deferred_transfer) = deferred_events.remove(&nonce.0) {
+                info!(
+                    "Deferred transfer with nonce {} executed after {} attempts in {} seconds",
+                    nonce.0,
+                    deferred_transfer.attempts,
+                    deferred_transfer.deferred_secs()
+                );
+            }
             pending_events.insert(nonce.0, tx_hash);
-            update_pending_transactions(tx_hash, nonce, redis, transaction_count, pending_events)
-                .await
+            update_pending_transactions(
+                tx_hash,
+                nonce,
+                transfer,
+                redis,
+                transaction_count,
+                pending_events,
+            )
+            .await
         }
         Err(error) => {
+            if is_unprofitable_now(&error) {
+                let deferred_transfer =
+                    deferred_events
+                        .entry(nonce.0)
+                        .or_insert_with(|| DeferredTransfer {
+                            deferred_at: unix_timestamp(),
+                            attempts: 0,
+                        });
+                deferred_transfer.attempts += 1;
+                info!(
+                    "Transfer with nonce {} deferred (attempt {}): {}",
+                    nonce.0, deferred_transfer.attempts, error
+                );
+                return Ok(());
+            }
+
+            if let Some(deferred_transfer) = deferred_events.remove(&nonce.0) {
+                warn!(
+                    "Deferred transfer with nonce {} is not executed after {} attempts in {} seconds: {}",
+                    nonce.0,
+                    deferred_transfer.attempts,
+                    deferred_transfer.deferred_secs(),
+                    error
+                );
+            }
+
             if is_connection_error(&error) {
                 CONNECTION_ERRORS.inc();
             } else if is_balance_error(&error) {
                 BALANCE_ERRORS.inc();
+            } else if let CustomError::TransferRejected(ref rejection_reasons) = error {
+                for reason in rejection_reasons {
+                    warn!("Transfer with nonce {} rejected: {}", nonce.0, reason);
+                }
+                let res: redis::RedisResult<()> = redis.remove_new_event(nonce.0).await;
+                res.map_err(|e| CustomError::FailedRemoveNewEvent(e))?;
+                SKIP_TRANSACTIONS_COUNT.inc();
             } else {
                 warn!(
                     "Failed to process tx with nonce {}, err: {:?}. Skip transaction.",
@@ -102,6 +194,7 @@ pub async fn process_transfer_event(
 pub async fn update_pending_transactions(
     tx_hash: H256,
     nonce: near_sdk::json_types::U128,
+    transfer: EthTransferData,
     redis: &mut AsyncRedisWrapper,
     mut transaction_count: U256,
     pending_events: &mut HashMap<u128, H256>,
@@ -112,6 +205,7 @@ pub async fn update_pending_transactions(
             .unwrap()
             .as_secs(),
         nonce: u128::from(nonce),
+        transfer: Some(transfer),
     };
 
     let res: redis::RedisResult<()> = redis
@@ -141,13 +235,18 @@ pub async fn update_pending_transactions(
 
 pub async fn process_near_events_worker(
     settings: SafeSettings,
+    eth_client: EthClient,
+    near_client: NearClient,
     eth_keypair: std::sync::Arc<secp256k1::SecretKey>,
     mut redis: AsyncRedisWrapper,
-    eth_contract_abi: std::sync::Arc<String>,
+    bridge_contract: SafeBridgeContract,
     eth_contract_address: std::sync::Arc<web3::types::Address>,
     near_relay_account_id: String,
+    storage: SafeStorage,
+    mut chain_head: ChainHeadReceiver,
 ) {
     let mut pending_events: HashMap<u128, H256> = HashMap::new();
+    let mut deferred_events: HashMap<u128, DeferredTransfer> = HashMap::new();
 
     loop {
         let mut redis_connection = redis.connection.clone();
@@ -161,7 +260,23 @@ pub async fn process_near_events_worker(
                 }
             };
 
+        let bridge_contract = bridge_contract.lock().await.clone();
+        if bridge_contract.paused {
+            warn!(
+                "Transfer submission is paused until the bridge ABI is reloaded for the implementation {:?}",
+                bridge_contract.implementation
+            );
+            sleep(Duration::from_secs(SLEEP_TIME_AFTER_EVENTS_PROCESS_SEC));
+            continue;
+        }
+
         let settings = settings.lock().await.clone();
+        let eth_last_block_number_on_near = storage.lock().await.eth_last_block_number_on_near;
+
+        let base_fee_spike = !deferred_events.is_empty()
+            && check_base_fee_spike(&eth_client, deferred_events.len()).await;
+
+        let mut processed_nonces = std::collections::HashSet::new();
 
         while let Some((_key, event_str)) = iter.next_item().await {
             if let Ok(event) = serde_json::from_str::<fast_bridge_common::Event>(&event_str) {
@@ -173,17 +288,57 @@ pub async fn process_near_events_worker(
                     transfer_message,
                 } = event
                 {
+                    processed_nonces.insert(nonce.0);
+                    let current_eth_block_height =
+                        match current_eth_block_height(&chain_head, &eth_client).await {
+                            Ok(current_eth_block_height) => current_eth_block_height,
+                            Err(error) => {
+                                error!(
+                                    "Transfer with nonce {} is not processed: {}",
+                                    nonce.0, error
+                                );
+                                continue;
+                            }
+                        };
+                    if base_fee_spike && deferred_events.contains_key(&nonce.0) {
+                        // The expired transfer is dropped regardless of the spike
+                        if let Err(error) = crate::transfer::check_time_before_unlock(
+                            &transfer_message,
+                            settings.min_time_before_unlock_in_sec,
+                            settings.min_blocks_before_unlock,
+                            current_eth_block_height,
+                        ) {
+                            drop_deferred_transfer(
+                                nonce.0,
+                                &error,
+                                &mut deferred_events,
+                                &mut redis,
+                            )
+                            .await;
+                            continue;
+                        }
+                        info!(
+                            "Transfer with nonce {} stays deferred until the base fee spike is over",
+                            nonce.0
+                        );
+                        continue;
+                    }
                     let res = process_transfer_event(
                         nonce,
                         sender_id.clone(),
                         transfer_message.clone(),
                         &settings,
+                        &eth_client,
+                        &near_client,
                         &mut redis,
                         *eth_contract_address,
                         eth_keypair.clone(),
-                        eth_contract_abi.clone(),
+                        bridge_contract.abi.clone(),
                         near_relay_account_id.clone(),
                         &mut pending_events,
+                        &mut deferred_events,
+                        eth_last_block_number_on_near,
+                        current_eth_block_height,
                     )
                     .await;
 
@@ -197,13 +352,119 @@ pub async fn process_near_events_worker(
             }
         }
 
-        if let Ok(current_eth_block_height) =
-            eth_client::methods::get_last_block_number(settings.eth.rpc_url.as_str()).await
-        {
-            NEAR_EVENTS_PROCESSOR_CURRENT_ETH_BLOCK_HEIGHT.set(current_eth_block_height);
+        // The events could be removed from the new events by other relayer instances
+        deferred_events.retain(|nonce, _| processed_nonces.contains(nonce));
+        DEFERRED_TRANSACTIONS_COUNT.set(deferred_events.len() as u64);
+
+        NEAR_EVENTS_PROCESSOR_CURRENT_ETH_BLOCK_HEIGHT.set(chain_head.borrow().number);
+
+        // the deferred transfers are re-priced on every new block
+        wait_for_new_head(
+            &mut chain_head,
+            Duration::from_secs(SLEEP_TIME_AFTER_EVENTS_PROCESS_SEC),
+        )
+        .await;
+    }
+}
+
+/// Removes the deferred transfer which can't be executed anymore from the new events
+async fn drop_deferred_transfer(
+    nonce: u128,
+    error: &CustomError,
+    deferred_events: &mut HashMap<u128, DeferredTransfer>,
+    redis: &mut AsyncRedisWrapper,
+) {
+    if let Some(deferred_transfer) = deferred_events.remove(&nonce) {
+        warn!(
+            "Deferred transfer with nonce {} is not executed after {} attempts in {} seconds: {}",
+            nonce,
+            deferred_transfer.attempts,
+            deferred_transfer.deferred_secs(),
+            error
+        );
+    }
+
+    let res: redis::RedisResult<()> = redis.remove_new_event(nonce).await;
+    match res {
+        Ok(()) => SKIP_TRANSACTIONS_COUNT.inc(),
+        Err(err) => error!("{}", CustomError::FailedRemoveNewEvent(err)),
+    }
+}
+
+/// The latest block from the shared head, it's read over RPC if the head is stale
+async fn current_eth_block_height(
+    chain_head: &ChainHeadReceiver,
+    eth_client: &EthClient,
+) -> Result<u64, CustomError> {
+    let head = *chain_head.borrow();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    if !head.is_stale(now, MAX_CHAIN_HEAD_AGE) {
+        STALE_CHAIN_HEAD.set(0);
+        return Ok(head.number);
+    }
+
+    STALE_CHAIN_HEAD.set(1);
+    warn!(
+        "The chain head {} is {} seconds old, read the latest block over RPC",
+        head.number,
+        now.saturating_sub(head.timestamp)
+    );
+    eth_client
+        .get_last_block_number()
+        .await
+        .map_err(CustomError::FailedFetchLastBlockNumber)
+}
+
+/// Returns `true` if the deferred transfers shouldn't be re-priced in this block
+async fn check_base_fee_spike(eth_client: &EthClient, deferred_events_count: usize) -> bool {
+    match eth_client
+        .get_base_fee_forecast(BASE_FEE_FORECAST_BLOCKS)
+        .await
+    {
+        Ok(forecast) => {
+            let spike = is_base_fee_spike(&forecast);
+            info!(
+                "{} {} deferred transfers. Base fee per gas: next block {}, lowest {}, median {} over last {} blocks",
+                if spike { "Keep" } else { "Re-price" },
+                deferred_events_count,
+                forecast.next_block,
+                forecast.lowest,
+                forecast.median,
+                BASE_FEE_FORECAST_BLOCKS
+            );
+            spike
         }
+        Err(err) => {
+            warn!("Failed to get base fee forecast: {:?}", err);
+            false
+        }
+    }
+}
+
+fn is_base_fee_spike(forecast: &BaseFeeForecast) -> bool {
+    forecast.next_block.saturating_mul(U256::from(100))
+        > forecast
+            .median
+            .saturating_mul(U256::from(100 + BASE_FEE_SPIKE_PERCENT))
+}
 
-        sleep(Duration::from_secs(SLEEP_TIME_AFTER_EVENTS_PROCESS_SEC));
+fn is_unprofitable_now(error: &CustomError) -> bool {
+    match error {
+        CustomError::TransferRejected(rejection_reasons) => {
+            rejection_reasons.iter().all(|reason| {
+                matches!(
+                    reason,
+                    RejectionReason::TxNotProfitable(_, _)
+                        | RejectionReason::ProfitPercentTooLow(_, _)
+                )
+            })
+        }
+        CustomError::BaseFeeAboveMaxFee(_, _) => true,
+        _ => false,
     }
 }
 
@@ -213,6 +474,9 @@ fn is_connection_error(error: &CustomError) -> bool {
         | CustomError::FailedFetchGasPrice(Api(Transport(_)))
         | CustomError::FailedEstimateGas(Api(Transport(_)))
         | CustomError::FailedGetTxCount(Transport(_))
+        | CustomError::FailedFetchOnChainPrice(Api(Transport(_)))
+        | CustomError::FailedFetchLastBlockNumber(Api(Transport(_)))
+        | CustomError::FailedFetchNearGasPrice(_)
         | CustomError::FailedGetTokenPrice(_)
         | CustomError::FailedFetchEthereumPrice(_) => true,
         CustomError::FailedExecuteTransferTokens(Api(Rpc(ref rpc_error))) => rpc_error
@@ -246,22 +510,27 @@ fn is_balance_error(error: &CustomError) -> bool {
 #[cfg(test)]
 pub mod tests {
     use crate::async_redis_wrapper::{AsyncRedisWrapper, PENDING_TRANSACTIONS};
+    use crate::errors::{CustomError, RejectionReason};
     use crate::logs::init_logger;
-    use crate::near_event_processor::process_transfer_event;
+    use crate::near_event_processor::{
+        is_base_fee_spike, is_unprofitable_now, process_transfer_event,
+    };
     use crate::test_utils;
     use crate::test_utils::get_settings;
+    use eth_client::methods::BaseFeeForecast;
     use eth_client::test_utils::{
-        get_eth_erc20_fast_bridge_contract_abi, get_eth_erc20_fast_bridge_proxy_contract_address,
-        get_eth_token, get_recipient, get_relay_eth_key,
+        get_eth_client, get_eth_erc20_fast_bridge_contract_abi,
+        get_eth_erc20_fast_bridge_proxy_contract_address, get_eth_token, get_recipient,
+        get_relay_eth_key,
     };
     use fast_bridge_common::{EthAddress, TransferDataEthereum, TransferDataNear, TransferMessage};
-    use near_client::test_utils::{get_near_signer, get_near_token};
+    use near_client::test_utils::{get_near_client, get_near_signer, get_near_token};
     use near_sdk::json_types::U128;
     use rand::Rng;
     use redis::AsyncCommands;
     use std::collections::HashMap;
     use std::time::Duration;
-    use web3::types::H256;
+    use web3::types::{H256, U256};
 
     #[tokio::test]
     async fn smoke_process_transfer_event_test() {
@@ -286,7 +555,7 @@ pub mod tests {
 
         let relay_eth_key = std::sync::Arc::new(get_relay_eth_key());
         let eth_erc20_fast_bridge_contract_abi =
-            std::sync::Arc::new(get_eth_erc20_fast_bridge_contract_abi().await);
+            std::sync::Arc::new(get_eth_erc20_fast_bridge_contract_abi());
 
         let near_account = get_near_signer().account_id.to_string();
 
@@ -307,12 +576,17 @@ pub mod tests {
                 aurora_sender: None,
             },
             &settings.lock().await.clone(),
+            &get_eth_client(),
+            &get_near_client(),
             &mut redis,
             get_eth_erc20_fast_bridge_proxy_contract_address(),
             relay_eth_key.clone(),
             eth_erc20_fast_bridge_contract_abi.clone(),
             near_account,
             &mut pending_events,
+            &mut HashMap::new(),
+            0,
+            get_eth_client().get_last_block_number().await.unwrap(),
         )
         .await
         .unwrap();
@@ -327,4 +601,45 @@ pub mod tests {
             new_pending_transactions.len()
         );
     }
+
+    #[test]
+    fn is_unprofitable_now_test() {
+        assert!(is_unprofitable_now(&CustomError::TransferRejected(vec![
+            RejectionReason::TxNotProfitable(-1., 0.),
+            RejectionReason::ProfitPercentTooLow(-0.1, 0.),
+        ])));
+        assert!(!is_unprofitable_now(&CustomError::TransferRejected(vec![
+            RejectionReason::TxNotProfitable(-1., 0.),
+            RejectionReason::TokenDisabled,
+        ])));
+        assert!(!is_unprofitable_now(
+            &CustomError::NotEnoughTimeBeforeUnlock
+        ));
+        assert!(is_unprofitable_now(&CustomError::BaseFeeAboveMaxFee(
+            U256::from(2),
+            U256::from(1)
+        )));
+    }
+
+    #[test]
+    fn is_base_fee_spike_test() {
+        let forecast = |next_block: u64| BaseFeeForecast {
+            next_block: U256::from(next_block),
+            lowest: U256::from(80),
+            median: U256::from(100),
+        };
+
+        assert!(!is_base_fee_spike(&forecast(90)));
+        assert!(!is_base_fee_spike(&forecast(150)));
+        assert!(is_base_fee_spike(&forecast(151)));
+        assert!(is_base_fee_spike(&BaseFeeForecast {
+            median: U256::zero(),
+            ..forecast(1)
+        }));
+        assert!(!is_base_fee_spike(&BaseFeeForecast {
+            next_block: U256::MAX,
+            lowest: U256::MAX,
+            median: U256::MAX,
+        }));
+    }
 }
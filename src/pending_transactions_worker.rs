@@ -1,18 +1,23 @@
 use crate::async_redis_wrapper::{
-    AsyncRedisWrapper, PendingTransactionData, TxData, PENDING_TRANSACTIONS,
+    AsyncRedisWrapper, FailedTxData, PendingTransactionData, TxData, PENDING_TRANSACTIONS,
 };
+use crate::bridge_contract::SafeBridgeContract;
+use crate::config::SafeSettings;
+use crate::ethereum::receipt_proof::{verify_receipt_event, verify_transfer_proof};
 use crate::prometheus_metrics::{
     FAIL_TRANSACTIONS_COUNT, PENDING_TRANSACTIONS_CURRENT_ETH_BLOCK_HEIGHT,
-    SUCCESS_TRANSACTIONS_COUNT,
+    SUCCESS_TRANSACTIONS_COUNT, TRANSFER_EVENT_MISMATCH_COUNT,
 };
 use crate::{
     errors::CustomError,
     ethereum::{transactions::TransactionStatus, RainbowBridgeEthereumClient},
 };
-use redis::{AsyncCommands, RedisResult};
-use std::{collections::HashMap, str::FromStr};
+use eth_client::chain_head::{wait_for_new_head, ChainHeadReceiver};
+use eth_client::methods::EthClient;
+use redis::AsyncCommands;
+use std::{collections::HashMap, str::FromStr, time::Duration};
 use uint::rustc_hex::ToHex;
-use web3::types::H256;
+use web3::types::{Address, H256, U256};
 
 macro_rules! info {
     ($($arg:tt)+) => { tracing::info!(target: crate::logs::PENDING_TRANSACTION_TARGET, $($arg)+) }
@@ -22,21 +27,41 @@ macro_rules! error {
     ($($arg:tt)+) => { tracing::error!(target: crate::logs::PENDING_TRANSACTION_TARGET, $($arg)+) }
 }
 
+const MAX_WAIT_FOR_NEW_BLOCK_SEC: u64 = 30;
+
 #[allow(clippy::needless_lifetimes)]
 pub async fn run<'a>(
-    eth_rpc_url: url::Url,
+    eth_client: EthClient,
     rainbow_bridge_index_js_path: String,
     mut redis: AsyncRedisWrapper,
-    rpc_timeout_secs: u64,
+    receipt_quorum: Option<usize>,
+    settings: SafeSettings,
+    bridge_contract: SafeBridgeContract,
+    mut chain_head: ChainHeadReceiver,
 ) {
     let rb_index = rainbow_bridge_index_js_path.as_str();
-    let eth_client =
-        RainbowBridgeEthereumClient::new(eth_rpc_url.clone(), rb_index, rpc_timeout_secs).unwrap();
+    let rb_eth_client =
+        RainbowBridgeEthereumClient::new(&eth_client, rb_index, receipt_quorum).unwrap();
 
     // transaction hash and last processed time
     let mut pending_transactions = HashMap::<H256, PendingTransactionData>::new();
 
     loop {
+        let bridge_proxy_address = settings.lock().await.eth.bridge_proxy_address;
+        // The events are decoded with the ABI reloaded after the bridge upgrade
+        let transfer_tokens_event = match bridge_contract.lock().await.transfer_tokens_event() {
+            Ok(transfer_tokens_event) => transfer_tokens_event,
+            Err(err) => {
+                error!("{}", err);
+                wait_for_new_head(
+                    &mut chain_head,
+                    Duration::from_secs(MAX_WAIT_FOR_NEW_BLOCK_SEC),
+                )
+                .await;
+                continue;
+            }
+        };
+
         // fill the pending_transactions
         let mut iter: redis::AsyncIter<(String, String)> =
             redis.connection.hscan(PENDING_TRANSACTIONS).await.unwrap();
@@ -51,48 +76,86 @@ pub async fn run<'a>(
             }
         }
 
-        // process the pending_transactions
+        // skip the transactions which are already processed
+        let tx_hashes: Vec<H256> = pending_transactions.keys().copied().collect();
+        let tx_hashes_hex: Vec<String> = tx_hashes
+            .iter()
+            .map(|tx_hash| tx_hash.as_bytes().to_hex::<String>())
+            .collect();
+        let processed = redis.txs_exist(&tx_hashes_hex).await.unwrap_or_else(|err| {
+            error!("Failed to check the processed transactions: {:?}", err);
+            vec![false; tx_hashes.len()]
+        });
+
         let mut txs_to_remove: Vec<H256> = Vec::new();
-        for (key, tx_data) in pending_transactions.iter_mut() {
-            // remove and skip if transaction is already processing
-            let key_hex = key.as_bytes().to_hex::<String>();
-            if redis.get_tx_data(key_hex).await.is_ok() {
-                txs_to_remove.push(*key);
+        let mut txs_to_check: Vec<H256> = Vec::new();
+        for (tx_hash, processed) in tx_hashes.into_iter().zip(processed) {
+            if processed {
+                txs_to_remove.push(tx_hash);
             } else {
-                let res = handle_one_tx(key, tx_data, &eth_client, &mut txs_to_remove, &mut redis);
-                if let Err(err) = res.await {
-                    error!("{}", err);
-                }
+                txs_to_check.push(tx_hash);
             }
         }
 
-        for item in txs_to_remove {
-            let item_hex = item.as_bytes().to_hex::<String>();
-            let res: RedisResult<()> = redis.connection.hdel(PENDING_TRANSACTIONS, item_hex).await;
-            if let Err(error) = res {
-                error!("{}", CustomError::FailedUnstorePendingTx(error));
+        // fetch the statuses of all the pending transactions in one batch
+        match rb_eth_client.transaction_statuses(&txs_to_check).await {
+            Ok(statuses) => {
+                if let Some(block_number) = statuses.block_number {
+                    PENDING_TRANSACTIONS_CURRENT_ETH_BLOCK_HEIGHT.set(block_number.as_u64());
+                }
+
+                for (key, status) in txs_to_check.iter().zip(statuses.statuses) {
+                    let tx_data = pending_transactions
+                        .get_mut(key)
+                        .expect("The pending transaction is present");
+                    let res = handle_one_tx(
+                        key,
+                        tx_data,
+                        status,
+                        &rb_eth_client,
+                        bridge_proxy_address,
+                        &transfer_tokens_event,
+                        &mut txs_to_remove,
+                        &mut redis,
+                    );
+                    if let Err(err) = res.await {
+                        error!("{}", err);
+                    }
+                }
             }
-            pending_transactions.remove(&item);
+            Err(err) => error!("{}", CustomError::FailedFetchTxStatus(err)),
         }
 
-        if let Ok(current_eth_block_height) =
-            eth_client::methods::get_last_block_number(eth_rpc_url.as_str()).await
-        {
-            PENDING_TRANSACTIONS_CURRENT_ETH_BLOCK_HEIGHT.set(current_eth_block_height);
+        let txs_to_remove_hex: Vec<String> = txs_to_remove
+            .iter()
+            .map(|tx_hash| tx_hash.as_bytes().to_hex::<String>())
+            .collect();
+        if let Err(error) = redis.remove_pending_txs(&txs_to_remove_hex).await {
+            error!("{}", CustomError::FailedUnstorePendingTx(error));
+        }
+        for item in txs_to_remove {
+            pending_transactions.remove(&item);
         }
 
-        tokio::time::sleep(core::time::Duration::from_secs(1)).await;
+        // the statuses can change only in a new block
+        wait_for_new_head(
+            &mut chain_head,
+            Duration::from_secs(MAX_WAIT_FOR_NEW_BLOCK_SEC),
+        )
+        .await;
     }
 }
 
 async fn handle_one_tx(
     key: &H256,
     tx_data: &mut PendingTransactionData,
+    status: web3::error::Result<TransactionStatus>,
     eth_client: &RainbowBridgeEthereumClient<'_>,
+    bridge_proxy_address: Address,
+    transfer_tokens_event: &web3::ethabi::Event,
     transactions_to_remove: &mut Vec<H256>,
     redis: &mut AsyncRedisWrapper,
 ) -> Result<(), CustomError> {
-    let status = eth_client.transaction_status(*key).await;
     let status = status.map_err(|err| CustomError::FailedFetchTxStatus(err))?;
     match status {
         TransactionStatus::Pending => {
@@ -108,14 +171,65 @@ async fn handle_one_tx(
             return Err(CustomError::FailedTxStatus(format!("{:?}", key)));
         }
         TransactionStatus::Success(block_number) => {
+            let hex_key = key.as_bytes().to_hex::<String>();
+
+            // The emitted event must transfer the tokens requested on NEAR, otherwise the unlock is
+            // blocked until the transfer is investigated
+            if let Some(transfer) = &tx_data.transfer {
+                let receipt = eth_client
+                    .transaction_receipt(*key)
+                    .await
+                    .map_err(CustomError::FailedFetchReceipt)?
+                    .ok_or_else(|| CustomError::ReceiptNotFound(format!("{:?}", key)))?;
+
+                if let Err(err) = verify_receipt_event(
+                    transfer_tokens_event,
+                    &receipt.logs,
+                    bridge_proxy_address,
+                    U256::from(tx_data.nonce),
+                    transfer,
+                ) {
+                    TRANSFER_EVENT_MISMATCH_COUNT.inc();
+                    FAIL_TRANSACTIONS_COUNT.inc();
+                    transactions_to_remove.push(*key);
+                    let data = TxData {
+                        block: u64::try_from(block_number).unwrap(),
+                        nonce: tx_data.nonce,
+                        transfer: tx_data.transfer.clone(),
+                        ..Default::default()
+                    };
+                    store_failed_tx(&hex_key, data, err.to_string(), redis).await;
+                    return Err(CustomError::TransferEventMismatch(err));
+                }
+            }
+
             let proof = eth_client.get_proof(key).await;
             let proof = proof.map_err(|err| CustomError::FailedFetchProof(err.to_string()))?;
             let data = TxData {
                 block: u64::try_from(block_number).unwrap(),
                 proof,
                 nonce: tx_data.nonce,
+                transfer: tx_data.transfer.clone(),
+                ..Default::default()
             };
-            let hex_key = key.as_bytes().to_hex::<String>();
+
+            if let Err(err) = verify_transfer_proof(
+                transfer_tokens_event,
+                &data.proof,
+                bridge_proxy_address,
+                U256::from(data.nonce),
+                data.transfer.as_ref(),
+            ) {
+                // The proof of another transfer can't unlock this one, other proofs are built again
+                // on the next block
+                if err.is_event_mismatch() {
+                    FAIL_TRANSACTIONS_COUNT.inc();
+                    transactions_to_remove.push(*key);
+                    store_failed_tx(&hex_key, data, err.to_string(), redis).await;
+                }
+                return Err(CustomError::InvalidProof(err));
+            }
+
             redis.store_tx(hex_key, data).await.unwrap();
             SUCCESS_TRANSACTIONS_COUNT.inc();
             transactions_to_remove.push(*key);
@@ -124,15 +238,38 @@ async fn handle_one_tx(
     Ok(())
 }
 
+async fn store_failed_tx(
+    hex_key: &str,
+    tx_data: TxData,
+    reason: String,
+    redis: &mut AsyncRedisWrapper,
+) {
+    let failed_tx_data = FailedTxData {
+        tx_data,
+        reason,
+        near_tx_hash: None,
+        failed_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+    if let Err(err) = redis.store_failed_tx(hex_key, &failed_tx_data).await {
+        error!("{}", CustomError::FailedStoreFailedTx(err));
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::async_redis_wrapper;
     use crate::async_redis_wrapper::{AsyncRedisWrapper, TRANSACTIONS};
+    use crate::bridge_contract::BridgeContract;
     use crate::logs::init_logger;
     use crate::pending_transactions_worker::run;
     use crate::test_utils::{get_rb_index_path_str, get_settings, remove_all};
-    use eth_client::test_utils::get_eth_rpc_url;
+    use eth_client::chain_head::ChainHeadService;
+    use eth_client::test_utils::get_eth_client;
     use redis::AsyncCommands;
+    use std::time::Duration;
     use tokio::time::timeout;
 
     #[tokio::test]
@@ -151,6 +288,7 @@ pub mod tests {
                 .unwrap()
                 .as_secs(),
             nonce: 605226883 as u128,
+            ..Default::default()
         };
 
         let _res: () = redis
@@ -163,11 +301,23 @@ pub mod tests {
             .await
             .unwrap();
 
+        let (chain_head_service, chain_head) =
+            ChainHeadService::new(get_eth_client(), None, Duration::from_secs(3))
+                .await
+                .unwrap();
+        tokio::spawn(chain_head_service.run());
+
         let worker = run(
-            get_eth_rpc_url(),
+            get_eth_client(),
             get_rb_index_path_str(),
             redis.clone(),
-            30,
+            None,
+            settings.clone(),
+            std::sync::Arc::new(tokio::sync::Mutex::new(BridgeContract::new(
+                web3::types::Address::zero(),
+                eth_client::fast_bridge::FAST_BRIDGE_ABI.to_string(),
+            ))),
+            chain_head,
         );
 
         let timeout_duration = std::time::Duration::from_secs(10);
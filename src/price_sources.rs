@@ -0,0 +1,147 @@
+use crate::config::{NearTokenInfo, PriceSource, Settings, TwapQuote};
+use crate::errors::CustomError;
+use eth_client::methods::EthClient;
+use eth_client::price_feeds::UniswapV3TwapParams;
+
+const NEAR_COIN_ID: &str = "near";
+
+pub async fn eth_price_usd(
+    settings: &Settings,
+    eth_client: &EthClient,
+) -> Result<f64, CustomError> {
+    match &settings.eth_price_source {
+        PriceSource::Coingecko => match eth_client::methods::eth_price_usd().await {
+            Ok(price) => price.ok_or(CustomError::FailedFetchEthPriceInvalidCoinId),
+            Err(error) => Err(CustomError::FailedFetchEthereumPrice(error)),
+        },
+        PriceSource::UniswapV3Twap {
+            quote: TwapQuote::Eth,
+            ..
+        } => Err(CustomError::InvalidPriceSource(
+            "ETH price can't be quoted in ETH".to_string(),
+        )),
+        price_source => on_chain_price(eth_client, price_source).await,
+    }
+}
+
+pub async fn token_price_usd(
+    settings: &Settings,
+    eth_client: &EthClient,
+    token_info: &NearTokenInfo,
+) -> Result<f64, CustomError> {
+    price_usd(
+        settings,
+        eth_client,
+        &token_info.price_source,
+        &token_info.exchange_id,
+    )
+    .await
+}
+
+pub async fn near_price_usd(
+    settings: &Settings,
+    eth_client: &EthClient,
+) -> Result<f64, CustomError> {
+    price_usd(
+        settings,
+        eth_client,
+        &settings.near_price_source,
+        NEAR_COIN_ID,
+    )
+    .await
+}
+
+async fn price_usd(
+    settings: &Settings,
+    eth_client: &EthClient,
+    price_source: &PriceSource,
+    coin_id: &str,
+) -> Result<f64, CustomError> {
+    match price_source {
+        PriceSource::Coingecko => {
+            match eth_client::methods::token_price_usd(coin_id.to_string()).await {
+                Ok(price) => price.ok_or(CustomError::FailedGetTokenPriceInvalidCoinId),
+                Err(error) => Err(CustomError::FailedGetTokenPrice(error)),
+            }
+        }
+        PriceSource::UniswapV3Twap {
+            quote: TwapQuote::Eth,
+            ..
+        } => Ok(on_chain_price(eth_client, price_source).await?
+            * eth_price_usd(settings, eth_client).await?),
+        price_source => on_chain_price(eth_client, price_source).await,
+    }
+}
+
+// Returns the price in the quote currency of the source (USD for Chainlink USD feeds)
+async fn on_chain_price(
+    eth_client: &EthClient,
+    price_source: &PriceSource,
+) -> Result<f64, CustomError> {
+    let price = match price_source {
+        PriceSource::Coingecko => {
+            return Err(CustomError::InvalidPriceSource(
+                "Coingecko is not an on-chain price source".to_string(),
+            ))
+        }
+        PriceSource::Chainlink {
+            aggregator,
+            max_staleness_secs,
+        } => {
+            eth_client::price_feeds::chainlink_price_usd(
+                eth_client,
+                *aggregator,
+                *max_staleness_secs,
+            )
+            .await
+        }
+        PriceSource::UniswapV3Twap {
+            pool,
+            twap_interval_secs,
+            base_is_token0,
+            token0_decimals,
+            token1_decimals,
+            quote: _,
+        } => {
+            eth_client::price_feeds::uniswap_v3_twap(
+                eth_client,
+                &UniswapV3TwapParams {
+                    pool: *pool,
+                    twap_interval_secs: *twap_interval_secs,
+                    base_is_token0: *base_is_token0,
+                    token0_decimals: token0_decimals.clone().into(),
+                    token1_decimals: token1_decimals.clone().into(),
+                },
+            )
+            .await
+        }
+    };
+
+    price.map_err(CustomError::FailedFetchOnChainPrice)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::config::{Decimals, PriceSource, TwapQuote};
+    use crate::price_sources::eth_price_usd;
+    use crate::test_utils::get_settings;
+    use eth_client::test_utils::get_eth_client;
+
+    #[tokio::test]
+    async fn eth_price_quoted_in_eth_test() {
+        let mut settings = get_settings();
+        settings.eth_price_source = PriceSource::UniswapV3Twap {
+            pool: web3::types::Address::zero(),
+            twap_interval_secs: 1800,
+            base_is_token0: true,
+            token0_decimals: Decimals::try_from(18).unwrap(),
+            token1_decimals: Decimals::try_from(6).unwrap(),
+            quote: TwapQuote::Eth,
+        };
+
+        assert!(matches!(
+            eth_price_usd(&settings, &get_eth_client()).await,
+            Err(crate::errors::CustomError::InvalidPriceSource(_))
+        ));
+    }
+}
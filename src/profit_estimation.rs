@@ -1,4 +1,19 @@
 use crate::config::Decimals;
+use crate::errors::CustomError;
+use eth_client::methods::FeeData;
+use std::str::FromStr;
+
+const ETHER_DECIMALS: u32 = 18;
+const NEAR_DECIMALS: u32 = 24;
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferExecutionCost {
+    /// Cost in case the base fee doesn't change before the transaction inclusion
+    pub expected_usd: f64,
+    /// Cost in case the transaction pays the whole `max_fee_per_gas`
+    pub worst_case_usd: f64,
+}
 
 pub fn format_token_units(token_amount: web3::types::U256, decimals: Decimals) -> String {
     let one_token_amount = 10u128.pow(decimals.clone().into());
@@ -11,38 +26,184 @@ pub fn format_token_units(token_amount: web3::types::U256, decimals: Decimals) -
     )
 }
 
+fn to_integer(value: web3::types::U256) -> Option<rug::Integer> {
+    rug::Integer::from_str(&value.to_string()).ok()
+}
+
+fn price_to_rational(price_usd: f64) -> Result<rug::Rational, CustomError> {
+    if price_usd < 0. {
+        return Err(CustomError::InvalidPrice(price_usd));
+    }
+    rug::Rational::from_f64(price_usd).ok_or(CustomError::InvalidPrice(price_usd))
+}
+
+fn token_units(amount: web3::types::U256, decimals: u32) -> Option<rug::Rational> {
+    Some(rug::Rational::from((
+        to_integer(amount)?,
+        rug::Integer::from(rug::Integer::u_pow_u(10, decimals)),
+    )))
+}
+
+/// Exact transfer execution cost for the fee parameters the transaction will be signed with
+pub fn estimate_transfer_execution_usd(
+    estimated_gas: web3::types::U256,
+    fee_data: &FeeData,
+    ether_price_usd: f64,
+) -> Result<TransferExecutionCost, CustomError> {
+    let ether_price = price_to_rational(ether_price_usd)?;
+    let gas = to_integer(estimated_gas).ok_or(CustomError::FailedEstimateExecutionCost(
+        format!("invalid gas amount: {}", estimated_gas),
+    ))?;
+
+    let max_usd = price_to_rational(f64::MAX)?;
+    let cost_usd = |fee_per_gas: web3::types::U256| -> Result<f64, CustomError> {
+        let fee_per_gas =
+            to_integer(fee_per_gas).ok_or(CustomError::FailedEstimateExecutionCost(format!(
+                "invalid fee per gas: {}",
+                fee_per_gas
+            )))?;
+        let cost_ether = rug::Rational::from((
+            gas.clone() * fee_per_gas,
+            rug::Integer::from(rug::Integer::u_pow_u(10, ETHER_DECIMALS)),
+        ));
+        let cost_usd = cost_ether * &ether_price;
+        if cost_usd <= max_usd {
+            Ok(cost_usd.to_f64())
+        } else {
+            Err(CustomError::FailedEstimateExecutionCost(format!(
+                "cost is out of range: gas = {}, fee data = {:?}, ether price = {}",
+                estimated_gas, fee_data, ether_price_usd
+            )))
+        }
+    };
+
+    Ok(TransferExecutionCost {
+        expected_usd: cost_usd(fee_data.expected_fee_per_gas())?,
+        worst_case_usd: cost_usd(fee_data.worst_case_fee_per_gas())?,
+    })
+}
+
+/// Cost of the `lp_unlock` call on NEAR
+pub fn estimate_near_unlock_usd(
+    gas: u64,
+    gas_price_yocto_near: u128,
+    near_price_usd: f64,
+) -> Result<f64, CustomError> {
+    let near_price = price_to_rational(near_price_usd)?;
+    let cost_near = rug::Rational::from((
+        rug::Integer::from(gas) * rug::Integer::from(gas_price_yocto_near),
+        rug::Integer::from(rug::Integer::u_pow_u(10, NEAR_DECIMALS)),
+    ));
+    let cost_usd = (cost_near * near_price).to_f64();
+    if cost_usd.is_finite() {
+        Ok(cost_usd)
+    } else {
+        Err(CustomError::FailedEstimateExecutionCost(format!(
+            "NEAR unlock cost is out of range: gas = {}, gas price = {}, NEAR price = {}",
+            gas, gas_price_yocto_near, near_price_usd
+        )))
+    }
+}
+
+/// Expected time until the light client on NEAR knows the block with our transfer
+pub fn expected_unlock_delay_secs(
+    current_eth_block: u64,
+    eth_last_block_number_on_near: u64,
+    blocks_for_tx_finalization: u64,
+    eth_block_time_secs: u64,
+) -> u64 {
+    current_eth_block
+        .saturating_sub(eth_last_block_number_on_near)
+        .saturating_add(blocks_for_tx_finalization)
+        .saturating_mul(eth_block_time_secs)
+}
+
+/// Cost of the capital locked on Ethereum until the tokens are unlocked on NEAR
+pub fn estimate_capital_cost_usd(
+    token_amount: web3::types::U256,
+    token_decimals: Decimals,
+    token_price_usd: f64,
+    annual_rate: f64,
+    lock_secs: u64,
+) -> Result<f64, CustomError> {
+    let amount = token_units(token_amount, token_decimals.clone().into()).ok_or(
+        CustomError::ErrorInFeeAmountParsing(token_amount, token_decimals.into()),
+    )?;
+    let token_price = price_to_rational(token_price_usd)?;
+    let annual_rate = rug::Rational::from_f64(annual_rate)
+        .filter(|rate| *rate >= 0)
+        .ok_or(CustomError::InvalidCapitalCostRate(annual_rate))?;
+
+    let cost_usd =
+        (amount * token_price * annual_rate * rug::Rational::from((lock_secs, SECONDS_PER_YEAR)))
+            .to_f64();
+    if cost_usd.is_finite() {
+        Ok(cost_usd)
+    } else {
+        Err(CustomError::FailedEstimateExecutionCost(format!(
+            "Capital cost is out of range: amount = {}, token price = {}, annual rate = {}, lock secs = {}",
+            token_amount, token_price_usd, annual_rate, lock_secs
+        )))
+    }
+}
+
 pub fn get_profit_usd(
     fee_token_price_usd: f64,
     fee_token_amount: web3::types::U256,
     fee_token_decimals: Decimals,
     estimated_transfer_execution_price_usd: f64,
-) -> Result<f64, crate::errors::CustomError> {
-    let fee_amount = if let Ok(val) = rug::Float::parse(format_token_units(
-        fee_token_amount,
-        fee_token_decimals.clone(),
-    )) {
-        val
-    } else {
-        return Err(crate::errors::CustomError::ErrorInFeeAmountParsing(
-            fee_token_amount,
-            fee_token_decimals.into(),
-        ));
-    };
+) -> Result<f64, CustomError> {
+    let fee_amount = token_units(fee_token_amount, fee_token_decimals.clone().into()).ok_or(
+        CustomError::ErrorInFeeAmountParsing(fee_token_amount, fee_token_decimals.into()),
+    )?;
 
-    let readable_fee_token_amount = rug::Float::with_val(128, fee_amount);
+    let fee_token_price = price_to_rational(fee_token_price_usd)?;
+    let execution_price = price_to_rational(estimated_transfer_execution_price_usd)?;
 
-    Ok(readable_fee_token_amount
-        .mul_sub(
-            &rug::Float::with_val(64, fee_token_price_usd),
-            &rug::Float::with_val(64, estimated_transfer_execution_price_usd),
-        )
-        .to_f64())
+    Ok((fee_amount * fee_token_price - execution_price).to_f64())
+}
+
+/// Profit in percent of the transferred amount value, zero for a transfer without value
+pub fn get_profit_percent(
+    profit_usd: f64,
+    token_price_usd: f64,
+    token_amount: web3::types::U256,
+    token_decimals: Decimals,
+) -> Result<f64, CustomError> {
+    let amount = token_units(token_amount, token_decimals.clone().into()).ok_or(
+        CustomError::ErrorInFeeAmountParsing(token_amount, token_decimals.into()),
+    )?;
+    let amount_usd = amount * price_to_rational(token_price_usd)?;
+    if amount_usd == 0 {
+        return Ok(0.);
+    }
+
+    let profit =
+        rug::Rational::from_f64(profit_usd).ok_or(CustomError::InvalidPrice(profit_usd))?;
+
+    Ok((profit * 100 / amount_usd).to_f64())
 }
 
 #[cfg(test)]
 pub mod tests {
     use crate::config::Decimals;
-    use crate::profit_estimation::{format_token_units, get_profit_usd};
+    use crate::errors::CustomError;
+    use crate::profit_estimation::{
+        estimate_capital_cost_usd, estimate_near_unlock_usd, estimate_transfer_execution_usd,
+        expected_unlock_delay_secs, format_token_units, get_profit_percent, get_profit_usd,
+    };
+    use eth_client::methods::FeeData;
+    use web3::types::U256;
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn fee_data(base_fee_per_gas: U256, max_priority_fee_per_gas: U256) -> FeeData {
+        FeeData {
+            base_fee_per_gas,
+            max_priority_fee_per_gas,
+            max_fee_per_gas: base_fee_per_gas * U256::from(2) + max_priority_fee_per_gas,
+        }
+    }
 
     #[test]
     fn smoke_get_profit_test() {
@@ -61,7 +222,6 @@ pub mod tests {
     }
 
     #[test]
-    #[ignore]
     fn super_cheap_token_test() {
         const EPS: f64 = 0.0001;
         let token_price_usd = 1. / 1_000_000_000.;
@@ -216,4 +376,208 @@ pub mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn get_profit_usd_invalid_price_test() {
+        for price in [f64::NAN, f64::INFINITY, -1.] {
+            assert!(matches!(
+                get_profit_usd(price, U256::one(), Decimals::try_from(0).unwrap(), 0.),
+                Err(CustomError::InvalidPrice(_))
+            ));
+            assert!(matches!(
+                get_profit_usd(1., U256::one(), Decimals::try_from(0).unwrap(), price),
+                Err(CustomError::InvalidPrice(_))
+            ));
+        }
+    }
+
+    #[test]
+    // data from some random transaction https://etherscan.io/tx/0xd4e7e8c91f43f13202f647efb726e867f2ae44a8d633fe5ad2549de03f1496c6
+    fn estimate_transfer_execution_test() {
+        let estimated_gas = U256::from(116_855);
+        let fee_data = fee_data(U256::from(12_588_907_561u64), U256::from(500_000_000));
+        let ether_price = 1208.69;
+
+        let cost = estimate_transfer_execution_usd(estimated_gas, &fee_data, ether_price).unwrap();
+
+        assert!(cost.expected_usd > 1.8 && cost.expected_usd < 1.9);
+        assert!(cost.worst_case_usd > 3.6 && cost.worst_case_usd < 3.7);
+        assert!(cost.expected_usd <= cost.worst_case_usd);
+    }
+
+    #[test]
+    fn estimate_transfer_execution_exact_test() {
+        // 100_000 gas * 10 gwei = 0.001 ETH
+        let cost = estimate_transfer_execution_usd(
+            U256::from(100_000),
+            &FeeData {
+                base_fee_per_gas: U256::from(9 * GWEI),
+                max_priority_fee_per_gas: U256::from(GWEI),
+                max_fee_per_gas: U256::from(20 * GWEI),
+            },
+            2000.,
+        )
+        .unwrap();
+
+        assert_eq!(cost.expected_usd, 2.);
+        assert_eq!(cost.worst_case_usd, 4.);
+    }
+
+    #[test]
+    fn estimate_transfer_execution_extreme_gas_price_test() {
+        let cost = estimate_transfer_execution_usd(
+            U256::from(100_000_000),
+            &fee_data(U256::from(40_000_000 * GWEI), U256::from(GWEI)),
+            10_000.,
+        )
+        .unwrap();
+        assert!(cost.expected_usd > 4e10);
+
+        let cost = estimate_transfer_execution_usd(
+            U256::from(u64::MAX),
+            &FeeData {
+                base_fee_per_gas: U256::MAX,
+                max_priority_fee_per_gas: U256::MAX,
+                max_fee_per_gas: U256::MAX,
+            },
+            1.,
+        )
+        .unwrap();
+        assert!(cost.expected_usd > 1e78);
+
+        assert!(matches!(
+            estimate_transfer_execution_usd(
+                U256::MAX,
+                &FeeData {
+                    base_fee_per_gas: U256::MAX,
+                    max_priority_fee_per_gas: U256::MAX,
+                    max_fee_per_gas: U256::MAX,
+                },
+                f64::MAX,
+            ),
+            Err(CustomError::FailedEstimateExecutionCost(_))
+        ));
+    }
+
+    #[test]
+    fn estimate_transfer_execution_extreme_token_price_test() {
+        let fee_data = fee_data(U256::from(30 * GWEI), U256::from(2 * GWEI));
+
+        let cost = estimate_transfer_execution_usd(U256::from(100_000), &fee_data, 0.).unwrap();
+        assert_eq!(cost.expected_usd, 0.);
+        assert_eq!(cost.worst_case_usd, 0.);
+
+        let cost = estimate_transfer_execution_usd(U256::from(100_000), &fee_data, 1e-12).unwrap();
+        assert!(cost.expected_usd > 0. && cost.expected_usd < 1e-12);
+
+        let cost = estimate_transfer_execution_usd(U256::from(1), &fee_data, 1e300).unwrap();
+        assert!(cost.expected_usd > 1e282);
+
+        assert!(matches!(
+            estimate_transfer_execution_usd(U256::from(100_000), &fee_data, f64::NAN),
+            Err(CustomError::InvalidPrice(_))
+        ));
+        assert!(matches!(
+            estimate_transfer_execution_usd(U256::from(100_000), &fee_data, -1.),
+            Err(CustomError::InvalidPrice(_))
+        ));
+    }
+
+    #[test]
+    fn estimate_transfer_execution_zero_gas_test() {
+        let cost = estimate_transfer_execution_usd(
+            U256::zero(),
+            &fee_data(U256::from(30 * GWEI), U256::from(2 * GWEI)),
+            2000.,
+        )
+        .unwrap();
+        assert_eq!(cost.expected_usd, 0.);
+        assert_eq!(cost.worst_case_usd, 0.);
+    }
+
+    #[test]
+    fn estimate_near_unlock_test() {
+        const EPS: f64 = 0.0001;
+        // 300 Tgas * 100_000_000 yoctoNEAR = 0.03 NEAR
+        let cost = estimate_near_unlock_usd(300_000_000_000_000, 100_000_000, 2.).unwrap();
+        assert!((cost - 0.06).abs() < EPS);
+
+        assert_eq!(estimate_near_unlock_usd(0, 100_000_000, 2.).unwrap(), 0.);
+        assert!(estimate_near_unlock_usd(u64::MAX, u128::MAX, 1.).is_ok());
+        assert!(matches!(
+            estimate_near_unlock_usd(u64::MAX, u128::MAX, f64::MAX),
+            Err(CustomError::FailedEstimateExecutionCost(_))
+        ));
+        assert!(matches!(
+            estimate_near_unlock_usd(300_000_000_000_000, 100_000_000, f64::NAN),
+            Err(CustomError::InvalidPrice(_))
+        ));
+    }
+
+    #[test]
+    fn expected_unlock_delay_test() {
+        assert_eq!(expected_unlock_delay_secs(1100, 1000, 10, 12), 110 * 12);
+        assert_eq!(expected_unlock_delay_secs(1000, 1100, 10, 12), 10 * 12);
+        assert_eq!(expected_unlock_delay_secs(u64::MAX, 0, 10, 12), u64::MAX);
+    }
+
+    #[test]
+    fn estimate_capital_cost_test() {
+        const EPS: f64 = 0.0001;
+        // 1_000_000 USDC locked for a year at 5%
+        let cost = estimate_capital_cost_usd(
+            U256::from(1_000_000_000_000u64),
+            Decimals::try_from(6).unwrap(),
+            1.,
+            0.05,
+            365 * 24 * 60 * 60,
+        )
+        .unwrap();
+        assert!((cost - 50_000.).abs() < EPS);
+
+        // 1_000 USDC locked for an hour at 5%
+        let cost = estimate_capital_cost_usd(
+            U256::from(1_000_000_000u64),
+            Decimals::try_from(6).unwrap(),
+            1.,
+            0.05,
+            60 * 60,
+        )
+        .unwrap();
+        assert!((cost - 50. / (365. * 24.)).abs() < EPS);
+
+        assert!(estimate_capital_cost_usd(
+            U256::MAX,
+            Decimals::try_from(0).unwrap(),
+            f64::MAX,
+            f64::MAX,
+            u64::MAX,
+        )
+        .is_err());
+
+        assert!(matches!(
+            estimate_capital_cost_usd(U256::one(), Decimals::try_from(0).unwrap(), 1., -0.05, 1),
+            Err(CustomError::InvalidCapitalCostRate(_))
+        ));
+    }
+
+    #[test]
+    fn get_profit_percent_test() {
+        const EPS: f64 = 0.0001;
+        let decimals = Decimals::try_from(6).unwrap();
+
+        let percent = get_profit_percent(2., 0.5, U256::from(400_000_000), decimals.clone());
+        assert!((percent.unwrap() - 1.).abs() < EPS);
+
+        let percent = get_profit_percent(-1., 1., U256::from(200_000_000), decimals.clone());
+        assert!((percent.unwrap() + 0.5).abs() < EPS);
+
+        let percent = get_profit_percent(1., 1., U256::zero(), decimals.clone());
+        assert_eq!(percent.unwrap(), 0.);
+
+        assert!(matches!(
+            get_profit_percent(f64::NAN, 1., U256::one(), decimals),
+            Err(CustomError::InvalidPrice(_))
+        ));
+    }
 }
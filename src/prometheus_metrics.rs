@@ -1,5 +1,5 @@
-use prometheus::core::{AtomicU64, GenericGauge};
-use prometheus::Registry;
+use prometheus::core::{AtomicU64, GenericGauge, GenericGaugeVec};
+use prometheus::{Opts, Registry};
 
 use lazy_static::lazy_static;
 use warp::Filter;
@@ -51,12 +51,36 @@ lazy_static! {
     )
     .expect("metric can't be created");
 
+    pub static ref TRANSFER_EVENT_MISMATCH_COUNT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "transfer_event_mismatch_count",
+        "The total number of transfers whose event on Ethereum doesn't match the transfer message on NEAR"
+    )
+    .expect("metric can't be created");
+
     pub static ref SKIP_TRANSACTIONS_COUNT:  GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
         "skip_transactions_count",
         "The total number of skipped transactions (relayer decided don't process these transactions)"
     )
     .expect("metric can't be created");
 
+    pub static ref DEFERRED_TRANSACTIONS_COUNT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "deferred_transactions_count",
+        "The number of transactions deferred until they become profitable"
+    )
+    .expect("metric can't be created");
+
+    pub static ref BRIDGE_IMPLEMENTATION_CHANGES_COUNT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "bridge_implementation_changes_count",
+        "The total number of detected bridge proxy upgrades"
+    )
+    .expect("metric can't be created");
+
+    pub static ref BRIDGE_TRANSFER_SUBMISSION_PAUSED: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "bridge_transfer_submission_paused",
+        "1 if the transfer submission is paused after the bridge proxy upgrade"
+    )
+    .expect("metric can't be created");
+
     pub static ref CONNECTION_ERRORS: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
         "connection_errors",
         "The total number of connection error"
@@ -69,6 +93,39 @@ lazy_static! {
     )
     .expect("metric can't be created");
 
+    pub static ref RESCHEDULED_UNLOCKS_COUNT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "rescheduled_unlocks_count",
+        "The total number of failed unlocks rescheduled for another attempt"
+    )
+    .expect("metric can't be created");
+
+    pub static ref FAILED_UNLOCKS_COUNT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "failed_unlocks_count",
+        "The total number of transfers moved out of the unlock queue after a permanent failure"
+    )
+    .expect("metric can't be created");
+
+    pub static ref WAITING_UNLOCKS_COUNT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "waiting_unlocks_count",
+        "The number of transfers waiting for the light client on Near to reach their block"
+    )
+    .expect("metric can't be created");
+
+    pub static ref LIGHT_CLIENT_BLOCKS_PER_HOUR: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "light_client_blocks_per_hour",
+        "The estimated advance rate of the light client on Near"
+    )
+    .expect("metric can't be created");
+
+    pub static ref UNLOCK_ESTIMATED_TIME: GenericGaugeVec<AtomicU64> = GenericGaugeVec::<AtomicU64>::new(
+        Opts::new(
+            "unlock_estimated_time",
+            "The estimated UNIX time when the transfer can be unlocked"
+        ),
+        &["tx_hash"]
+    )
+    .expect("metric can't be created");
+
     pub static ref UNLOCK_TOKENS_CURRENT_NEAR_BLOCK_HEIGHT: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
         "unlock_tokens_current_near_block_height",
         "The current block height on near in unlock tokens worker"
@@ -86,6 +143,12 @@ lazy_static! {
         "The current block height on eth in near events processor worker"
     )
     .expect("metric can't be created");
+
+    pub static ref STALE_CHAIN_HEAD: GenericGauge<AtomicU64> = GenericGauge::<AtomicU64>::new(
+        "stale_chain_head",
+        "1 if the shared Ethereum chain head is too old and the latest block is read over RPC"
+    )
+    .expect("metric can't be created");
 }
 
 fn register_custom_metrics() {
@@ -117,10 +180,26 @@ fn register_custom_metrics() {
         .register(Box::new(FAIL_TRANSACTIONS_COUNT.clone()))
         .expect("fail_transactions_count can't be registered");
 
+    REGISTRY
+        .register(Box::new(TRANSFER_EVENT_MISMATCH_COUNT.clone()))
+        .expect("transfer_event_mismatch_count can't be registered");
+
     REGISTRY
         .register(Box::new(SKIP_TRANSACTIONS_COUNT.clone()))
         .expect("skip_transactions_count can't be registered");
 
+    REGISTRY
+        .register(Box::new(DEFERRED_TRANSACTIONS_COUNT.clone()))
+        .expect("deferred_transactions_count can't be registered");
+
+    REGISTRY
+        .register(Box::new(BRIDGE_IMPLEMENTATION_CHANGES_COUNT.clone()))
+        .expect("bridge_implementation_changes_count can't be registered");
+
+    REGISTRY
+        .register(Box::new(BRIDGE_TRANSFER_SUBMISSION_PAUSED.clone()))
+        .expect("bridge_transfer_submission_paused can't be registered");
+
     REGISTRY
         .register(Box::new(CONNECTION_ERRORS.clone()))
         .expect("connection_errors can't be registered");
@@ -129,6 +208,26 @@ fn register_custom_metrics() {
         .register(Box::new(BALANCE_ERRORS.clone()))
         .expect("balance_errors can't be registered");
 
+    REGISTRY
+        .register(Box::new(RESCHEDULED_UNLOCKS_COUNT.clone()))
+        .expect("rescheduled_unlocks_count can't be registered");
+
+    REGISTRY
+        .register(Box::new(FAILED_UNLOCKS_COUNT.clone()))
+        .expect("failed_unlocks_count can't be registered");
+
+    REGISTRY
+        .register(Box::new(WAITING_UNLOCKS_COUNT.clone()))
+        .expect("waiting_unlocks_count can't be registered");
+
+    REGISTRY
+        .register(Box::new(LIGHT_CLIENT_BLOCKS_PER_HOUR.clone()))
+        .expect("light_client_blocks_per_hour can't be registered");
+
+    REGISTRY
+        .register(Box::new(UNLOCK_ESTIMATED_TIME.clone()))
+        .expect("unlock_estimated_time can't be registered");
+
     REGISTRY
         .register(Box::new(UNLOCK_TOKENS_CURRENT_NEAR_BLOCK_HEIGHT.clone()))
         .expect("unlock_tokens_current_near_block_height can't be registered");
@@ -144,6 +243,10 @@ fn register_custom_metrics() {
             NEAR_EVENTS_PROCESSOR_CURRENT_ETH_BLOCK_HEIGHT.clone(),
         ))
         .expect("near_events_processor_current_eth_block_height can't be registered");
+
+    REGISTRY
+        .register(Box::new(STALE_CHAIN_HEAD.clone()))
+        .expect("stale_chain_head can't be registered");
 }
 
 async fn metrics_handler() -> Result<impl Reply, Rejection> {
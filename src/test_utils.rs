@@ -1,12 +1,40 @@
-use crate::config::{Decimals, NearTokenInfo, Settings};
+use crate::config::{Decimals, NearTokenInfo, PriceSource, Settings};
 use dotenv::dotenv;
 use redis::AsyncCommands;
 use std::env;
 use std::path::Path;
+use std::sync::{Mutex, Once};
 use std::time::Duration;
 
 pub const NEAR_CONTRACT_ADDRESS: &str = "fast-bridge2.olga24912_3.testnet";
 
+// The variables interpolated in `config.json.example` and their values if they aren't set
+const CONFIG_ENV_VARS: &[(&str, &str)] = &[
+    ("FAST_BRIDGE_INFURA_PROJECT_ID", "test-project-id"),
+    ("FAST_BRIDGE_ETHERSCAN_API_KEY", "test-api-key"),
+    (
+        "FAST_BRIDGE_ETH_PRIVATE_KEY",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+    ),
+];
+
+/// Held by the tests which change the environment variables
+pub static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Sets the variables required by `config.json.example`, the values from `.env` take precedence
+pub fn init_config_env() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        dotenv().ok();
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        for (name, value) in CONFIG_ENV_VARS {
+            if env::var_os(name).is_none() {
+                env::set_var(name, value);
+            }
+        }
+    });
+}
+
 pub fn get_valid_till() -> u64 {
     (std::time::SystemTime::now()
         .duration_since(std::time::SystemTime::UNIX_EPOCH)
@@ -38,17 +66,24 @@ pub fn get_rb_index_path_str() -> String {
 }
 
 pub fn get_settings() -> Settings {
+    init_config_env();
     let config_path = "config.json.example";
     let mut settings = crate::config::Settings::init(config_path.to_string()).unwrap();
     settings.near_tokens_whitelist.mapping.insert(
         near_client::test_utils::NEAR_TOKEN_ADDRESS.parse().unwrap(),
         NearTokenInfo {
+            enabled: true,
             exchange_id: "wrapped-near".to_string(),
+            price_source: PriceSource::Coingecko,
             fixed_fee: 0.into(),
             percent_fee: 0.0,
+            fee_tiers: vec![],
             decimals: Decimals::try_from(6).unwrap(),
             eth_address: eth_client::test_utils::ETH_TOKEN_ADDRESS.parse().unwrap(),
+            min_transfer_amount: None,
             max_transfer_amount: Option::<u64>::None,
+            profit_threshold: None,
+            min_profit_percent: None,
         },
     );
     settings
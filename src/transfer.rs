@@ -1,21 +1,28 @@
 use crate::config::{NearTokenInfo, Settings};
-use crate::errors::CustomError;
+use crate::errors::{CustomError, RejectionReason};
 use crate::logs::EVENT_PROCESSOR_TARGET;
+use eth_client::fast_bridge::{TransferTokensCall, TRANSFER_TOKENS_METHOD};
+use eth_client::methods::{EthClient, FeeData};
 use fast_bridge_common::TransferMessage;
+use near_client::methods::NearClient;
 use near_sdk::AccountId;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use web3::types::{H160, U256};
+use web3::types::U256;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_transfer(
     relay_key_on_eth: impl web3::signing::Key,
     transfer_event: fast_bridge_common::Event,
     eth_erc20_fast_bridge_contract_abi: &[u8],
-    eth1_rpc_url: reqwest::Url,
+    eth_client: &EthClient,
+    near_client: &NearClient,
     eth_erc20_fast_bridge_proxy_contract_addr: web3::types::Address,
     profit_threshold: Option<f64>,
     settings: &Settings,
     near_relay_account_id: String,
     transaction_count: web3::types::U256,
+    eth_last_block_number_on_near: u64,
+    current_eth_block_height: u64,
 ) -> Result<web3::types::H256, CustomError> {
     let (nonce, method_name, method_args, transfer_message) =
         get_transfer_data(transfer_event, near_relay_account_id)?;
@@ -24,81 +31,99 @@ pub async fn execute_transfer(
         &transfer_message,
         settings.min_time_before_unlock_in_sec,
         settings.min_blocks_before_unlock,
-        eth1_rpc_url.clone(),
-    )
-    .await?;
-
-    check_transfer_amount(&transfer_message, &settings)?;
-
-    let estimated_gas = eth_client::methods::estimate_gas(
-        eth1_rpc_url.clone(),
-        relay_key_on_eth.address(),
-        eth_erc20_fast_bridge_proxy_contract_addr,
-        eth_erc20_fast_bridge_contract_abi,
-        method_name.as_str(),
-        method_args.clone(),
-        settings.rpc_timeout_secs,
-    )
-    .await;
-
-    let estimated_gas = estimated_gas.map_err(|err| CustomError::FailedEstimateGas(err))?;
+        current_eth_block_height,
+    )?;
 
     if transfer_message.fee.token != transfer_message.transfer.token_near {
         return Err(CustomError::InvalidFeeToken);
     }
 
-    let token_info = get_near_token_info(&settings, transfer_message.transfer.token_near)?;
+    let token_info = get_near_token_info(&settings, transfer_message.transfer.token_near.clone())?;
 
     if token_info.eth_address != transfer_message.transfer.token_eth.0.into() {
         return Err(CustomError::InvalidEthTokenAddress);
     }
 
+    let mut rejection_reasons = check_transfer_amount(&transfer_message, &token_info);
+
     let min_fee_allowed = estimate_min_fee(&token_info, transfer_message.transfer.amount.0)
         .ok_or(CustomError::FailedFeeCalculation)?;
 
     if transfer_message.fee.amount.0 < min_fee_allowed {
-        return Err(CustomError::NotEnoughFeeToken(
+        rejection_reasons.push(RejectionReason::NotEnoughFeeToken(
             transfer_message.fee.amount.0,
             min_fee_allowed,
         ));
     }
 
-    if let Some(profit_threshold) = profit_threshold {
+    if !rejection_reasons.is_empty() {
+        return Err(CustomError::TransferRejected(rejection_reasons));
+    }
+
+    let estimated_gas = eth_client
+        .estimate_gas(
+            relay_key_on_eth.address(),
+            eth_erc20_fast_bridge_proxy_contract_addr,
+            eth_erc20_fast_bridge_contract_abi,
+            method_name,
+            method_args.clone(),
+        )
+        .await;
+
+    let estimated_gas = estimated_gas.map_err(|err| CustomError::FailedEstimateGas(err))?;
+
+    let fee_data = crate::fee_oracle::get_fee_data(
+        settings,
+        eth_client,
+        transfer_message.valid_till_block_height,
+    )
+    .await?;
+
+    let profit_threshold = token_info.profit_threshold.or(profit_threshold);
+
+    if profit_threshold.is_some() || token_info.min_profit_percent.is_some() {
         let profit = estimate_profit(
-            eth1_rpc_url.as_str(),
+            settings,
+            eth_client,
+            near_client,
             token_info.clone(),
             transfer_message.fee.amount.0.into(),
+            transfer_message.transfer.amount.0.into(),
             estimated_gas,
+            &fee_data,
+            eth_last_block_number_on_near,
+            current_eth_block_height,
         )
         .await?;
 
         tracing::info!(
             target: EVENT_PROCESSOR_TARGET,
-            "Profit for nonce {:?} is {}, threshold: {}",
+            "Profit for nonce {:?} is {} ({}% of the amount), threshold: {:?}, min percent: {:?}",
             nonce,
-            profit,
-            profit_threshold
+            profit.usd,
+            profit.percent_of_amount,
+            profit_threshold,
+            token_info.min_profit_percent
         );
 
-        if profit < profit_threshold {
-            return Err(CustomError::TxNotProfitable(profit, profit_threshold));
+        let rejection_reasons = check_profit(&profit, profit_threshold, &token_info);
+        if !rejection_reasons.is_empty() {
+            return Err(CustomError::TransferRejected(rejection_reasons));
         }
     }
 
-    let tx_hash = eth_client::methods::change(
-        eth1_rpc_url,
-        eth_erc20_fast_bridge_proxy_contract_addr,
-        eth_erc20_fast_bridge_contract_abi,
-        &method_name,
-        method_args,
-        relay_key_on_eth,
-        true,
-        Some(transaction_count),
-        Some(estimated_gas),
-        settings.max_priority_fee_per_gas,
-        settings.rpc_timeout_secs,
-    )
-    .await;
+    let tx_hash = eth_client
+        .change_with_fee_data(
+            eth_erc20_fast_bridge_proxy_contract_addr,
+            eth_erc20_fast_bridge_contract_abi,
+            method_name,
+            method_args,
+            relay_key_on_eth,
+            Some(fee_data),
+            Some(transaction_count),
+            Some(estimated_gas),
+        )
+        .await;
 
     Ok(tx_hash.map_err(|err| CustomError::FailedExecuteTransferTokens(err))?)
 }
@@ -107,7 +132,7 @@ fn estimate_min_fee(token_info: &NearTokenInfo, token_amount: u128) -> Option<u1
     Some(
         rug::Float::with_val(128, token_amount)
             .mul_add(
-                &rug::Float::with_val(64, token_info.percent_fee),
+                &rug::Float::with_val(64, token_info.percent_fee_for(token_amount)),
                 &rug::Float::with_val(64, token_info.fixed_fee.0),
             )
             .to_integer()?
@@ -117,31 +142,69 @@ fn estimate_min_fee(token_info: &NearTokenInfo, token_amount: u128) -> Option<u1
 
 fn check_transfer_amount(
     transfer_message: &TransferMessage,
-    settings: &Settings,
-) -> Result<(), CustomError> {
-    let transfer_amount = transfer_message.transfer.amount;
-    if let Some(token_info) = settings
-        .near_tokens_whitelist
-        .get_token_info(transfer_message.transfer.token_near.clone())
-    {
-        if let Some(max_transfer_amount) = token_info.max_transfer_amount {
-            if transfer_amount > max_transfer_amount {
-                return Err(CustomError::ExceedingMaxAllowableTokenAmount(
-                    transfer_amount,
-                    max_transfer_amount,
-                ));
-            }
+    token_info: &NearTokenInfo,
+) -> Vec<RejectionReason> {
+    let transfer_amount = transfer_message.transfer.amount.0;
+    let mut rejection_reasons = vec![];
+
+    if !token_info.enabled {
+        rejection_reasons.push(RejectionReason::TokenDisabled);
+    }
+
+    if let Some(min_transfer_amount) = token_info.min_transfer_amount {
+        if transfer_amount < min_transfer_amount.0 {
+            rejection_reasons.push(RejectionReason::BelowMinTransferAmount(
+                transfer_amount,
+                min_transfer_amount.0,
+            ));
         }
     }
 
-    Ok(())
+    if let Some(max_transfer_amount) = token_info.max_transfer_amount {
+        if transfer_amount > max_transfer_amount.0 {
+            rejection_reasons.push(RejectionReason::ExceedingMaxAllowableTokenAmount(
+                transfer_amount,
+                max_transfer_amount.0,
+            ));
+        }
+    }
+
+    rejection_reasons
+}
+
+fn check_profit(
+    profit: &Profit,
+    profit_threshold: Option<f64>,
+    token_info: &NearTokenInfo,
+) -> Vec<RejectionReason> {
+    let mut rejection_reasons = vec![];
+
+    if let Some(profit_threshold) = profit_threshold {
+        if profit.usd < profit_threshold {
+            rejection_reasons.push(RejectionReason::TxNotProfitable(
+                profit.usd,
+                profit_threshold,
+            ));
+        }
+    }
+
+    if let Some(min_profit_percent) = token_info.min_profit_percent {
+        if profit.percent_of_amount < min_profit_percent {
+            rejection_reasons.push(RejectionReason::ProfitPercentTooLow(
+                profit.percent_of_amount,
+                min_profit_percent,
+            ));
+        }
+    }
+
+    rejection_reasons
 }
 
-async fn check_time_before_unlock(
+pub(crate) fn check_time_before_unlock(
     transfer_message: &TransferMessage,
     min_time_before_unlock: Option<u64>,
     min_blocks_before_unlock: Option<u64>,
-    eth1_rpc_url: reqwest::Url,
+    current_eth_block_height: u64,
 ) -> Result<(), CustomError> {
     if let Some(min_time_before_unlock) = min_time_before_unlock {
         let transaction_unlock_time_ns = transfer_message.valid_till as u128;
@@ -158,11 +221,6 @@ async fn check_time_before_unlock(
 
     if let Some(min_blocks_before_unlock) = min_blocks_before_unlock {
         if let Some(transaction_block_height) = transfer_message.valid_till_block_height {
-            let current_eth_block_height =
-                eth_client::methods::get_last_block_number(eth1_rpc_url.as_str())
-                    .await
-                    .map_err(|err| CustomError::FailedFetchLastBlockNumber(err))?;
-
             if current_eth_block_height + min_blocks_before_unlock > transaction_block_height {
                 return Err(CustomError::NotEnoughTimeBeforeUnlock);
             }
@@ -172,47 +230,143 @@ async fn check_time_before_unlock(
     Ok(())
 }
 
+struct Profit {
+    usd: f64,
+    percent_of_amount: f64,
+}
+
 async fn estimate_profit(
-    eth1_rpc_url: &str,
+    settings: &Settings,
+    eth_client: &EthClient,
+    near_client: &NearClient,
     token_info: NearTokenInfo,
     fee_amount: U256,
+    transfer_amount: U256,
     estimated_gas: U256,
-) -> Result<f64, CustomError> {
-    let gas_price_in_wei = eth_client::methods::gas_price_wei(eth1_rpc_url)
-        .await
-        .map_err(|err| CustomError::FailedFetchGasPrice(err))?;
+    fee_data: &FeeData,
+    eth_last_block_number_on_near: u64,
+    current_eth_block_height: u64,
+) -> Result<Profit, CustomError> {
+    let eth_price_in_usd = crate::price_sources::eth_price_usd(settings, eth_client).await?;
+
+    let estimated_transfer_execution_price =
+        crate::profit_estimation::estimate_transfer_execution_usd(
+            estimated_gas,
+            fee_data,
+            eth_price_in_usd,
+        )?;
+
+    tracing::info!(
+        target: EVENT_PROCESSOR_TARGET,
+        "Estimated transfer execution: expected {}$, worst case {}$ (gas: {}, fee data: {:?})",
+        estimated_transfer_execution_price.expected_usd,
+        estimated_transfer_execution_price.worst_case_usd,
+        estimated_gas,
+        fee_data
+    );
 
-    let eth_price_in_usd = match eth_client::methods::eth_price_usd().await {
-        Ok(price) => price.ok_or(CustomError::FailedFetchEthPriceInvalidCoinId)?,
-        Err(error) => return Err(CustomError::FailedFetchEthereumPrice(error)),
-    };
+    let fee_token_usd =
+        crate::price_sources::token_price_usd(settings, eth_client, &token_info).await?;
 
-    let estimated_transfer_execution_price = eth_client::methods::estimate_transfer_execution_usd(
-        estimated_gas,
-        gas_price_in_wei,
-        eth_price_in_usd,
+    let unlock_price = estimate_unlock_price(settings, eth_client, near_client).await?;
+
+    let capital_cost = estimate_capital_cost(
+        settings,
+        &token_info,
+        transfer_amount,
+        fee_token_usd,
+        eth_last_block_number_on_near,
+        current_eth_block_height,
+    )?;
+
+    tracing::info!(
+        target: EVENT_PROCESSOR_TARGET,
+        "Estimated NEAR unlock cost: {}$, capital cost: {}$",
+        unlock_price,
+        capital_cost
     );
 
-    let fee_token_usd = match eth_client::methods::token_price_usd(token_info.exchange_id).await {
-        Ok(price) => price.ok_or(CustomError::FailedGetTokenPriceInvalidCoinId)?,
-        Err(error) => return Err(CustomError::FailedGetTokenPrice(error)),
+    let execution_price = if settings.worst_case_profit_estimation {
+        estimated_transfer_execution_price.worst_case_usd
+    } else {
+        estimated_transfer_execution_price.expected_usd
     };
 
-    crate::profit_estimation::get_profit_usd(
+    let profit_usd = crate::profit_estimation::get_profit_usd(
         fee_token_usd,
         fee_amount,
-        token_info.decimals,
-        estimated_transfer_execution_price,
-    )
+        token_info.decimals.clone(),
+        execution_price + unlock_price + capital_cost,
+    )?;
+
+    Ok(Profit {
+        usd: profit_usd,
+        percent_of_amount: crate::profit_estimation::get_profit_percent(
+            profit_usd,
+            fee_token_usd,
+            transfer_amount,
+            token_info.decimals,
+        )?,
+    })
+}
+
+async fn estimate_unlock_price(
+    settings: &Settings,
+    eth_client: &EthClient,
+    near_client: &NearClient,
+) -> Result<f64, CustomError> {
+    let gas_price = near_client
+        .get_gas_price()
+        .await
+        .map_err(CustomError::FailedFetchNearGasPrice)?;
+
+    let near_price_in_usd = crate::price_sources::near_price_usd(settings, eth_client).await?;
+
+    let unlock_gas = settings
+        .unlock_tokens_worker
+        .expected_gas_burnt
+        .unwrap_or(settings.unlock_tokens_worker.gas);
+
+    crate::profit_estimation::estimate_near_unlock_usd(unlock_gas, gas_price, near_price_in_usd)
 }
 
-type MethodArgs = (H160, H160, U256, U256, String, U256);
+fn estimate_capital_cost(
+    settings: &Settings,
+    token_info: &NearTokenInfo,
+    transfer_amount: U256,
+    token_price_usd: f64,
+    eth_last_block_number_on_near: u64,
+    current_eth_block_height: u64,
+) -> Result<f64, CustomError> {
+    let capital_cost_settings = match &settings.capital_cost {
+        Some(capital_cost_settings) => capital_cost_settings,
+        None => return Ok(0.),
+    };
+
+    let lock_secs = if eth_last_block_number_on_near == 0 {
+        capital_cost_settings.default_unlock_delay_secs
+    } else {
+        crate::profit_estimation::expected_unlock_delay_secs(
+            current_eth_block_height,
+            eth_last_block_number_on_near,
+            settings.unlock_tokens_worker.blocks_for_tx_finalization,
+            capital_cost_settings.eth_block_time_secs,
+        )
+    };
+
+    crate::profit_estimation::estimate_capital_cost_usd(
+        transfer_amount,
+        token_info.decimals.clone(),
+        token_price_usd,
+        capital_cost_settings.annual_rate,
+        lock_secs,
+    )
+}
 
 fn get_transfer_data(
     transfer_event: fast_bridge_common::Event,
     near_relay_account_id: String,
-) -> Result<(U256, String, MethodArgs, TransferMessage), CustomError> {
-    let method_name = "transferTokens".to_string();
+) -> Result<(U256, &'static str, TransferTokensCall, TransferMessage), CustomError> {
     match transfer_event {
         fast_bridge_common::Event::FastBridgeInitTransferEvent {
             nonce,
@@ -228,16 +382,16 @@ fn get_transfer_data(
                     .valid_till_block_height
                     .ok_or(CustomError::InvalidValidTillBlockHeight)?,
             );
-            let method_args = (
+            let method_args = TransferTokensCall {
                 token,
                 recipient,
                 nonce,
                 amount,
-                near_relay_account_id,
+                unlock_recipient: near_relay_account_id,
                 valid_till_block_height,
-            );
+            };
 
-            Ok((nonce, method_name, method_args, transfer_message))
+            Ok((nonce, TRANSFER_TOKENS_METHOD, method_args, transfer_message))
         }
         _ => Err(CustomError::ReceivedInvalidEvent),
     }
@@ -257,16 +411,22 @@ fn get_near_token_info(
 #[cfg(test)]
 pub mod tests {
     use crate::async_redis_wrapper::AsyncRedisWrapper;
+    use crate::config::{FeeTier, NearTokenInfo};
+    use crate::errors::{CustomError, RejectionReason};
     use crate::logs::init_logger;
     use crate::test_utils::get_settings;
-    use crate::transfer::execute_transfer;
+    use crate::transfer::{
+        check_profit, check_time_before_unlock, check_transfer_amount, estimate_min_fee,
+        execute_transfer, Profit,
+    };
     use crate::utils::get_tx_count;
     use eth_client::test_utils::{
-        get_eth_erc20_fast_bridge_contract_abi, get_eth_erc20_fast_bridge_proxy_contract_address,
-        get_eth_rpc_url, get_eth_token, get_recipient, get_relay_eth_key,
+        get_eth_client, get_eth_erc20_fast_bridge_contract_abi,
+        get_eth_erc20_fast_bridge_proxy_contract_address, get_eth_token, get_recipient,
+        get_relay_eth_key,
     };
     use fast_bridge_common::{EthAddress, TransferDataEthereum, TransferDataNear, TransferMessage};
-    use near_client::test_utils::{get_near_signer, get_near_token};
+    use near_client::test_utils::{get_near_client, get_near_signer, get_near_token};
     use near_sdk::json_types::U128;
     use rand::Rng;
     use web3::signing::Key;
@@ -275,9 +435,9 @@ pub mod tests {
     async fn smoke_execute_transfer_test() {
         init_logger();
 
-        let eth1_rpc_url = get_eth_rpc_url();
+        let eth_client = get_eth_client();
         let relay_key_on_eth = std::sync::Arc::new(get_relay_eth_key());
-        let eth_erc20_fast_bridge_contract_abi = get_eth_erc20_fast_bridge_contract_abi().await;
+        let eth_erc20_fast_bridge_contract_abi = get_eth_erc20_fast_bridge_contract_abi();
         let profit_threshold = 0f64;
         let settings = std::sync::Arc::new(tokio::sync::Mutex::new(get_settings()));
 
@@ -312,16 +472,124 @@ pub mod tests {
             relay_key_on_eth.clone().as_ref(),
             transfer_message,
             eth_erc20_fast_bridge_contract_abi.as_bytes(),
-            eth1_rpc_url.clone(),
+            &eth_client,
+            &get_near_client(),
             get_eth_erc20_fast_bridge_proxy_contract_address(),
             Some(profit_threshold),
             &settings.lock().await.clone(),
             near_relay_account_id,
-            get_tx_count(&mut redis, eth1_rpc_url, relay_key_on_eth.address())
+            get_tx_count(&mut redis, &eth_client, relay_key_on_eth.address())
                 .await
                 .unwrap(),
+            0,
+            eth_client.get_last_block_number().await.unwrap(),
         )
         .await
         .unwrap();
     }
+
+    fn get_token_info() -> NearTokenInfo {
+        get_settings()
+            .near_tokens_whitelist
+            .get_token_info(get_near_token())
+            .unwrap()
+    }
+
+    fn get_transfer_message(amount: u128) -> TransferMessage {
+        TransferMessage {
+            valid_till: crate::test_utils::get_valid_till(),
+            transfer: TransferDataEthereum {
+                token_near: get_near_token(),
+                token_eth: EthAddress(get_eth_token().into()),
+                amount: U128::from(amount),
+            },
+            fee: TransferDataNear {
+                token: get_near_token(),
+                amount: U128::from(0),
+            },
+            recipient: EthAddress(get_recipient().into()),
+            valid_till_block_height: Some(0),
+            aurora_sender: None,
+        }
+    }
+
+    #[test]
+    fn estimate_min_fee_tiers_test() {
+        let mut token_info = get_token_info();
+        token_info.fixed_fee = U128::from(10);
+        token_info.percent_fee = 0.01;
+        token_info.fee_tiers = vec![FeeTier {
+            min_amount: U128::from(10_000),
+            percent_fee: 0.001,
+        }];
+
+        assert_eq!(estimate_min_fee(&token_info, 1_000), Some(20));
+        assert_eq!(estimate_min_fee(&token_info, 9_000), Some(100));
+        assert_eq!(estimate_min_fee(&token_info, 10_000), Some(20));
+    }
+
+    #[test]
+    fn check_transfer_amount_test() {
+        let mut token_info = get_token_info();
+        token_info.min_transfer_amount = Some(U128::from(100));
+        token_info.max_transfer_amount = Some(U128::from(1_000));
+
+        assert!(check_transfer_amount(&get_transfer_message(100), &token_info).is_empty());
+        assert_eq!(
+            check_transfer_amount(&get_transfer_message(99), &token_info),
+            vec![RejectionReason::BelowMinTransferAmount(99, 100)]
+        );
+        assert_eq!(
+            check_transfer_amount(&get_transfer_message(1_001), &token_info),
+            vec![RejectionReason::ExceedingMaxAllowableTokenAmount(
+                1_001, 1_000
+            )]
+        );
+
+        token_info.enabled = false;
+        assert_eq!(
+            check_transfer_amount(&get_transfer_message(99), &token_info),
+            vec![
+                RejectionReason::TokenDisabled,
+                RejectionReason::BelowMinTransferAmount(99, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn check_profit_test() {
+        let mut token_info = get_token_info();
+        let profit = Profit {
+            usd: 1.,
+            percent_of_amount: 0.5,
+        };
+
+        assert!(check_profit(&profit, None, &token_info).is_empty());
+        assert!(check_profit(&profit, Some(1.), &token_info).is_empty());
+
+        token_info.min_profit_percent = Some(1.);
+        assert_eq!(
+            check_profit(&profit, Some(2.), &token_info),
+            vec![
+                RejectionReason::TxNotProfitable(1., 2.),
+                RejectionReason::ProfitPercentTooLow(0.5, 1.)
+            ]
+        );
+    }
+
+    #[test]
+    fn check_time_before_unlock_test() {
+        let mut transfer_message = get_transfer_message(1);
+        transfer_message.valid_till_block_height = Some(100);
+
+        assert!(check_time_before_unlock(&transfer_message, None, Some(10), 90).is_ok());
+        assert!(matches!(
+            check_time_before_unlock(&transfer_message, None, Some(10), 91),
+            Err(CustomError::NotEnoughTimeBeforeUnlock)
+        ));
+        assert!(matches!(
+            check_time_before_unlock(&transfer_message, Some(4 * 60 * 60), None, 0),
+            Err(CustomError::NotEnoughTimeBeforeUnlock)
+        ));
+    }
 }
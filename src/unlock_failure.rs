@@ -0,0 +1,132 @@
+//! Classification of the failed `lp_unlock` calls
+//!
+//! The failure of the call doesn't always mean the transfer can't be unlocked: the light client can
+//! lag behind the block checked by the worker, the attached gas can run out, or the proof can be built
+//! for a block which isn't in the light client history anymore. Such unlocks are retried or their
+//! proofs are regenerated; only the other failures remove the transfer from the queue.
+
+use near_client::errors::NearClientError;
+
+/// The messages of the bridge and the prover contracts when the proof isn't verified
+const PROOF_REJECTED_MESSAGES: &[&str] = &[
+    "Failed to verify the proof",
+    "Proof verification failed",
+    "block hash",
+    "Block hash",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnlockFailure {
+    /// The light client on NEAR hasn't reached the block of the proof, the unlock is retried later
+    LightClientLag,
+    /// The attached gas is exceeded, the unlock is retried alone with the raised gas
+    GasExceeded,
+    /// The proof block is outside the light client history or the transaction is moved by a reorg,
+    /// the proof is regenerated
+    ProofOutdated,
+    /// The batch failed in a receipt which can't be attributed to this unlock and the transfer is
+    /// still pending on the bridge, the unlock is retried later
+    Unconfirmed(String),
+    /// The unlock can't succeed, the transfer is moved out of the queue
+    Permanent(String),
+}
+
+impl UnlockFailure {
+    /// Classifies the failure of the `lp_unlock` call for the proof which is accepted once the light
+    /// client reaches `required_block`, `eth_last_block_number_on_near` is the last block of the light
+    /// client read after the failure
+    pub fn classify(
+        err: &NearClientError,
+        required_block: u64,
+        eth_last_block_number_on_near: u64,
+    ) -> Self {
+        let message = match err {
+            NearClientError::GasExceeded => return UnlockFailure::GasExceeded,
+            NearClientError::ContractPanic(message)
+            | NearClientError::ExecutionFailure(message) => message,
+            err => return UnlockFailure::Permanent(err.to_string()),
+        };
+
+        if contains_any(message, PROOF_REJECTED_MESSAGES) {
+            if required_block > eth_last_block_number_on_near {
+                return UnlockFailure::LightClientLag;
+            }
+            return UnlockFailure::ProofOutdated;
+        }
+
+        UnlockFailure::Permanent(err.to_string())
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, UnlockFailure::Permanent(_))
+    }
+}
+
+impl std::fmt::Display for UnlockFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnlockFailure::LightClientLag => write!(f, "the light client lags behind the proof"),
+            UnlockFailure::GasExceeded => write!(f, "the attached gas is exceeded"),
+            UnlockFailure::ProofOutdated => write!(f, "the proof is outdated"),
+            UnlockFailure::Unconfirmed(reason) => {
+                write!(
+                    f,
+                    "the batch failed and the unlock isn't confirmed: {}",
+                    reason
+                )
+            }
+            UnlockFailure::Permanent(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+fn contains_any(message: &str, patterns: &[&str]) -> bool {
+    patterns.iter().any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::unlock_failure::UnlockFailure;
+    use near_client::errors::NearClientError;
+    use near_primitives::errors::{
+        ActionError, ActionErrorKind, FunctionCallErrorSer, TxExecutionError,
+    };
+    use near_vm_errors::HostError;
+
+    fn panic(message: &str) -> NearClientError {
+        NearClientError::ContractPanic(format!("Smart contract panicked: {}", message))
+    }
+
+    #[test]
+    fn classify_test() {
+        let classify = |err: &NearClientError, required_block| {
+            UnlockFailure::classify(err, required_block, 1_000)
+        };
+
+        let gas_exceeded = TxExecutionError::ActionError(ActionError {
+            index: Some(0),
+            kind: ActionErrorKind::FunctionCallError(FunctionCallErrorSer::HostError(
+                HostError::GasExceeded,
+            )),
+        });
+        assert_eq!(
+            classify(&gas_exceeded.into(), 990),
+            UnlockFailure::GasExceeded
+        );
+
+        let proof_rejected = panic("Failed to verify the proof");
+        assert_eq!(
+            classify(&proof_rejected, 1_001),
+            UnlockFailure::LightClientLag
+        );
+        assert_eq!(classify(&proof_rejected, 990), UnlockFailure::ProofOutdated);
+
+        assert_eq!(
+            classify(&panic("Already unlocked"), 990),
+            UnlockFailure::Permanent(
+                "The contract panicked: Smart contract panicked: Already unlocked".to_string()
+            )
+        );
+        assert!(classify(&NearClientError::Expired, 990).is_permanent());
+    }
+}
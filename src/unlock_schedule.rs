@@ -0,0 +1,270 @@
+//! Unlock queue ordered by the light client block
+//!
+//! A transfer can be unlocked when the light client on NEAR reaches `tx_data.block +
+//! blocks_for_tx_finalization`. The waiting transfers are kept in memory ordered by this block, so
+//! they aren't read from Redis until the light client reaches it. The advance rate of the light client
+//! is measured to estimate the unlock time of every waiting transfer.
+
+use crate::prometheus_metrics::{
+    LIGHT_CLIENT_BLOCKS_PER_HOUR, UNLOCK_ESTIMATED_TIME, WAITING_UNLOCKS_COUNT,
+};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The advance rate of the light client is measured over this period
+const RATE_WINDOW: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The blocks reached by the light client and the time they are observed
+#[derive(Debug, Default)]
+pub struct LightClientProgress {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl LightClientProgress {
+    pub fn observe(&mut self, at: Instant, block: u64) {
+        match self.samples.back() {
+            Some(&(_, last_block)) if last_block == block => return,
+            // The light client is reset
+            Some(&(_, last_block)) if last_block > block => self.samples.clear(),
+            _ => {}
+        }
+        self.samples.push_back((at, block));
+
+        while self.samples.len() > 2
+            && at.duration_since(self.samples[0].0) > RATE_WINDOW
+            && at.duration_since(self.samples[1].0) >= RATE_WINDOW
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Blocks per second from the first observed block till `now`, so a stalled light client slows
+    /// down the estimate
+    pub fn rate(&self, now: Instant) -> Option<f64> {
+        let (first_at, first_block) = *self.samples.front()?;
+        let (_, last_block) = *self.samples.back()?;
+        let elapsed = now.saturating_duration_since(first_at).as_secs_f64();
+
+        if last_block <= first_block || elapsed == 0. {
+            return None;
+        }
+        Some((last_block - first_block) as f64 / elapsed)
+    }
+
+    /// The time left until the light client reaches `block`
+    pub fn time_to_block(&self, block: u64, now: Instant) -> Option<Duration> {
+        let (_, last_block) = *self.samples.back()?;
+        if block <= last_block {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate(now)?;
+        Some(Duration::from_secs_f64((block - last_block) as f64 / rate))
+    }
+}
+
+/// The transfers waiting for the light client
+#[derive(Debug, Default)]
+pub struct UnlockSchedule {
+    /// Ordered by the required block
+    waiting: BTreeSet<(u64, String)>,
+    required_blocks: HashMap<String, u64>,
+    progress: LightClientProgress,
+}
+
+impl UnlockSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the light client block and drops the transfers which are due, so the worker doesn't
+    /// wait for the block reached already
+    pub fn observe(&mut self, eth_last_block_number_on_near: u64) {
+        self.progress
+            .observe(Instant::now(), eth_last_block_number_on_near);
+
+        let due: Vec<String> = self
+            .waiting
+            .iter()
+            .take_while(|(required_block, _)| *required_block <= eth_last_block_number_on_near)
+            .map(|(_, tx_hash)| tx_hash.clone())
+            .collect();
+        for tx_hash in due {
+            self.remove(&tx_hash);
+        }
+    }
+
+    /// The transfer isn't read again until the light client reaches `required_block`
+    pub fn insert(&mut self, tx_hash: String, required_block: u64) {
+        self.remove(&tx_hash);
+        self.waiting.insert((required_block, tx_hash.clone()));
+        self.required_blocks.insert(tx_hash, required_block);
+    }
+
+    pub fn remove(&mut self, tx_hash: &str) {
+        if let Some(required_block) = self.required_blocks.remove(tx_hash) {
+            self.waiting.remove(&(required_block, tx_hash.to_string()));
+            let _ = UNLOCK_ESTIMATED_TIME.remove_label_values(&[tx_hash]);
+        }
+    }
+
+    /// Forgets the transfers which aren't in the queue anymore
+    pub fn retain(&mut self, tx_hashes: &HashSet<&str>) {
+        let removed: Vec<String> = self
+            .required_blocks
+            .keys()
+            .filter(|tx_hash| !tx_hashes.contains(tx_hash.as_str()))
+            .cloned()
+            .collect();
+        for tx_hash in removed {
+            self.remove(&tx_hash);
+        }
+    }
+
+    pub fn is_waiting(&self, tx_hash: &str, eth_last_block_number_on_near: u64) -> bool {
+        self.required_blocks
+            .get(tx_hash)
+            .map_or(false, |&required_block| {
+                required_block > eth_last_block_number_on_near
+            })
+    }
+
+    /// The block which makes the next transfer unlockable
+    pub fn next_required_block(&self) -> Option<u64> {
+        self.waiting
+            .iter()
+            .next()
+            .map(|(required_block, _)| *required_block)
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// The estimated UNIX time when the light client reaches `required_block`
+    pub fn estimated_unlock_time(&self, required_block: u64) -> Option<u64> {
+        let time_to_block = self
+            .progress
+            .time_to_block(required_block, Instant::now())?;
+        Some(
+            SystemTime::now()
+                .checked_add(time_to_block)?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs(),
+        )
+    }
+
+    /// Updates the estimated unlock times of the waiting transfers
+    pub fn report(&self, eth_last_block_number_on_near: u64) {
+        WAITING_UNLOCKS_COUNT.set(self.len() as u64);
+        if let Some(rate) = self.progress.rate(Instant::now()) {
+            LIGHT_CLIENT_BLOCKS_PER_HOUR.set((rate * 3600.) as u64);
+        }
+
+        for (required_block, tx_hash) in &self.waiting {
+            if let Some(unlock_time) = self.estimated_unlock_time(*required_block) {
+                UNLOCK_ESTIMATED_TIME
+                    .with_label_values(&[tx_hash])
+                    .set(unlock_time);
+            }
+        }
+
+        if let Some(next_required_block) = self.next_required_block() {
+            tracing::info!(
+                "{} transfers wait for the light client, last ETH block on NEAR = {}, \
+                 the next unlock at block {} (estimated unlock time: {:?})",
+                self.len(),
+                eth_last_block_number_on_near,
+                next_required_block,
+                self.estimated_unlock_time(next_required_block)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::unlock_schedule::{LightClientProgress, UnlockSchedule};
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn light_client_progress_test() {
+        let start = Instant::now();
+        let mut progress = LightClientProgress::default();
+        assert_eq!(progress.rate(start), None);
+
+        progress.observe(start, 1_000);
+        assert_eq!(progress.rate(start + Duration::from_secs(60)), None);
+        assert_eq!(progress.time_to_block(900, start), Some(Duration::ZERO));
+        assert_eq!(progress.time_to_block(1_100, start), None);
+
+        // The light client jumps by 100 blocks every 10 minutes
+        progress.observe(start + Duration::from_secs(600), 1_100);
+        progress.observe(start + Duration::from_secs(600), 1_100);
+        let now = start + Duration::from_secs(600);
+        assert_eq!(progress.rate(now), Some(1. / 6.));
+        let time_to_block = progress.time_to_block(1_150, now).unwrap();
+        assert_eq!(time_to_block.as_secs_f64().round(), 300.);
+
+        // The stalled light client slows down the estimate
+        assert_eq!(
+            progress.rate(start + Duration::from_secs(1_200)),
+            Some(1. / 12.)
+        );
+
+        // The reset light client is measured again
+        progress.observe(now, 500);
+        assert_eq!(progress.rate(now + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn unlock_schedule_test() {
+        let mut schedule = UnlockSchedule::new();
+        assert_eq!(schedule.next_required_block(), None);
+
+        schedule.insert("0x2".to_string(), 120);
+        schedule.insert("0x1".to_string(), 110);
+        schedule.insert("0x3".to_string(), 130);
+        assert_eq!(schedule.next_required_block(), Some(110));
+        assert!(schedule.is_waiting("0x1", 100));
+        assert!(!schedule.is_waiting("0x1", 110));
+        assert!(!schedule.is_waiting("0x4", 100));
+
+        // The rescheduled transfer is moved
+        schedule.insert("0x1".to_string(), 140);
+        assert_eq!(schedule.next_required_block(), Some(120));
+        assert_eq!(schedule.len(), 3);
+
+        schedule.retain(&HashSet::from(["0x1", "0x3"]));
+        assert_eq!(schedule.next_required_block(), Some(130));
+        assert!(!schedule.is_waiting("0x2", 100));
+
+        schedule.remove("0x3");
+        schedule.remove("0x1");
+        assert!(schedule.is_empty());
+    }
+
+    #[test]
+    fn observe_due_test() {
+        let mut schedule = UnlockSchedule::new();
+        schedule.insert("0x1".to_string(), 110);
+        schedule.insert("0x2".to_string(), 120);
+        schedule.insert("0x3".to_string(), 130);
+
+        // The due transfers don't keep the worker waiting for the reached block
+        schedule.observe(120);
+        assert_eq!(schedule.next_required_block(), Some(130));
+        assert_eq!(schedule.len(), 1);
+        assert!(!schedule.is_waiting("0x1", 120));
+        assert!(schedule.is_waiting("0x3", 120));
+
+        schedule.observe(130);
+        assert!(schedule.is_empty());
+        assert_eq!(schedule.next_required_block(), None);
+    }
+}
@@ -1,170 +1,901 @@
+use crate::async_redis_wrapper::{FailedTxData, TxData, UnlockTxData};
+use crate::bridge_contract::SafeBridgeContract;
+use crate::config::UnlockTokensWorkerSettings;
+use crate::ethereum::proof::header_block;
+use crate::ethereum::receipt_proof::verify_transfer_proof;
+use crate::ethereum::transactions::TransactionStatus;
+use crate::ethereum::RainbowBridgeEthereumClient;
 use crate::prometheus_metrics::{
-    UNLOCKED_TRANSACTIONS_COUNT, UNLOCK_TOKENS_CURRENT_NEAR_BLOCK_HEIGHT,
+    FAILED_UNLOCKS_COUNT, RESCHEDULED_UNLOCKS_COUNT, UNLOCKED_TRANSACTIONS_COUNT,
+    UNLOCK_TOKENS_CURRENT_NEAR_BLOCK_HEIGHT,
 };
+use crate::unlock_failure::UnlockFailure;
+use crate::unlock_schedule::UnlockSchedule;
 use crate::{
-    async_redis_wrapper::AsyncRedisWrapper, config::SafeSettings, errors::CustomError,
-    last_block::SafeStorage,
-};
-use near_primitives::{
-    hash::CryptoHash,
-    views::{ExecutionStatusView::Failure, FinalExecutionStatus},
+    async_redis_wrapper::AsyncRedisWrapper,
+    config::SafeSettings,
+    errors::CustomError,
+    last_block::{block_hash_safe, last_block_number, SafeStorage},
 };
+use borsh::{BorshDeserialize, BorshSerialize};
+use eth_client::methods::EthClient;
+use futures_util::StreamExt;
+use near_client::errors::NearClientError;
+use near_client::methods::{ActionResult, NearClient};
+use near_client::signer::NearSigner;
+use near_jsonrpc_primitives::types::query::QueryResponseKind;
+use near_primitives::transaction::{FunctionCallAction, SignedTransaction};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+/// The transaction which can be unlocked
+struct UnlockRequest {
+    tx_hash: String,
+    tx_data: TxData,
+}
 
-async fn unlock_tokens(
-    server_addr: url::Url,
-    account: near_crypto::InMemorySigner,
+/// The signed NEAR transaction with the `lp_unlock` calls of the transfers
+struct UnlockBatch {
+    signed_transaction: SignedTransaction,
+    /// The transfers with the indexes of their calls
+    requests: Vec<(usize, UnlockRequest)>,
+    actions_count: usize,
+    /// The transaction is stored and possibly sent before
+    resumed: bool,
+}
+
+/// Signs the `lp_unlock` calls of the transfers in one transaction and stores it before it is sent,
+/// so the transaction is resumed after a timeout or a restart instead of sending a new one
+async fn sign_batch(
+    near_signer: &NearSigner,
     contract_account_id: String,
-    proof: fast_bridge_common::Proof,
-    nonce: u128,
+    requests: Vec<UnlockRequest>,
     gas: u64,
-) -> Result<(FinalExecutionStatus, CryptoHash), CustomError> {
-    tracing::info!("Start lp unlock for token with nonce={}", nonce);
-    let result = near_client::methods::change(
-        server_addr,
-        account,
-        contract_account_id,
-        "lp_unlock".to_string(),
-        near_sdk::serde_json::json!({
-            "proof": proof,
-        }),
-        gas,
-        0,
-    )
-    .await
-    .map_err(|err| CustomError::FailedExecuteUnlockTokens(err.to_string()))?;
+    redis: &mut AsyncRedisWrapper,
+) -> Result<UnlockBatch, CustomError> {
+    let calls = requests
+        .iter()
+        .map(|request| FunctionCallAction {
+            method_name: "lp_unlock".to_string(),
+            args: near_sdk::serde_json::json!({
+                "proof": request.tx_data.proof,
+            })
+            .to_string()
+            .into_bytes(),
+            gas,
+            deposit: 0,
+        })
+        .collect();
 
-    for receipt_outcome in result.receipts_outcome {
-        if let Failure(tx_error) = receipt_outcome.outcome.status {
-            return Ok((
-                FinalExecutionStatus::Failure(tx_error),
-                result.transaction.hash,
-            ));
-        }
+    let signed_transaction = near_signer
+        .sign_function_calls(contract_account_id, calls)
+        .await
+        .map_err(CustomError::FailedExecuteUnlockTokens)?;
+
+    let actions_count = requests.len();
+    let unlock_tx_data = |action_index| UnlockTxData {
+        near_tx_hash: signed_transaction.get_hash().to_string(),
+        signed_transaction: encode_signed_transaction(&signed_transaction),
+        action_index,
+        actions_count,
+    };
+    let unlock_txs: Vec<(String, UnlockTxData)> = requests
+        .iter()
+        .enumerate()
+        .map(|(action_index, request)| (request.tx_hash.clone(), unlock_tx_data(action_index)))
+        .collect();
+    redis
+        .store_unlock_txs(&unlock_txs)
+        .await
+        .map_err(CustomError::FailedStoreUnlockTx)?;
+
+    Ok(UnlockBatch {
+        signed_transaction,
+        requests: requests.into_iter().enumerate().collect(),
+        actions_count,
+        resumed: false,
+    })
+}
+
+/// Groups the stored unlock transactions by the NEAR transaction
+fn resumed_batches(
+    unlock_txs: Vec<(UnlockRequest, UnlockTxData)>,
+) -> Vec<Result<UnlockBatch, (Vec<UnlockRequest>, CustomError)>> {
+    let mut groups: BTreeMap<String, Vec<(UnlockRequest, UnlockTxData)>> = BTreeMap::new();
+    for (request, unlock_tx_data) in unlock_txs {
+        groups
+            .entry(unlock_tx_data.signed_transaction.clone())
+            .or_default()
+            .push((request, unlock_tx_data));
     }
-    Ok((result.status, result.transaction.hash))
+
+    groups
+        .into_iter()
+        .map(|(signed_transaction, group)| {
+            let actions_count = group[0].1.actions_count;
+            let requests: Vec<(usize, UnlockRequest)> = group
+                .into_iter()
+                .map(|(request, unlock_tx_data)| (unlock_tx_data.action_index, request))
+                .collect();
+
+            match decode_signed_transaction(&signed_transaction) {
+                Ok(signed_transaction) => Ok(UnlockBatch {
+                    signed_transaction,
+                    requests,
+                    actions_count,
+                    resumed: true,
+                }),
+                Err(err) => Err((
+                    requests.into_iter().map(|(_, request)| request).collect(),
+                    err,
+                )),
+            }
+        })
+        .collect()
 }
 
-async fn handle_one_tx(
-    account: near_crypto::InMemorySigner,
-    gas: u64,
-    unlock_tokens_worker_settings: crate::config::UnlockTokensWorkerSettings,
+fn encode_signed_transaction(signed_transaction: &SignedTransaction) -> String {
+    near_primitives::serialize::to_base64(
+        signed_transaction
+            .try_to_vec()
+            .expect("Failed to serialize the signed transaction"),
+    )
+}
+
+fn decode_signed_transaction(encoded: &str) -> Result<SignedTransaction, CustomError> {
+    let bytes = near_primitives::serialize::from_base64(encoded)
+        .map_err(|err| CustomError::FailedDecodeUnlockTx(err.to_string()))?;
+    SignedTransaction::try_from_slice(&bytes)
+        .map_err(|err| CustomError::FailedDecodeUnlockTx(err.to_string()))
+}
+
+/// Returns the transaction if its block is finalized by the light client on NEAR, otherwise the
+/// transaction is scheduled for the required block
+async fn get_unlock_request(
+    unlock_tokens_worker_settings: &UnlockTokensWorkerSettings,
+    eth_last_block_number_on_near: u64,
     tx_hash: String,
-    storage: SafeStorage,
-    mut redis: AsyncRedisWrapper,
-) -> Result<(), String> {
+    schedule: &mut UnlockSchedule,
+    redis: &mut AsyncRedisWrapper,
+) -> Result<Option<UnlockRequest>, String> {
     tracing::info!(
         "Start processing transaction for lp unlock (tx_hash={})",
         tx_hash
     );
 
-    let eth_last_block_number_on_near = storage.lock().await.eth_last_block_number_on_near;
     let tx_data = redis
         .get_tx_data(tx_hash.clone())
         .await
         .map_err(|err| format!("{}", CustomError::FailedGetTxData(err)))?;
-    let unlock_tokens_execution_condition = tx_data.block
-        + unlock_tokens_worker_settings.blocks_for_tx_finalization
-        <= eth_last_block_number_on_near;
+    let required_block = tx_data.block + unlock_tokens_worker_settings.blocks_for_tx_finalization;
 
-    if !unlock_tokens_execution_condition {
+    if required_block > eth_last_block_number_on_near {
         tracing::info!(
-            "Skip tx(nonce={}, tx_hash={}); \n\
+            "Schedule tx(nonce={}, tx_hash={}); \n\
                           Current last ETH block on NEAR = {}, \n\
                           ETH block with tx = {}, \n\
-                          Waiting for block = {}",
+                          Waiting for block = {}, \n\
+                          Estimated unlock time = {:?}",
             tx_data.nonce,
             tx_hash,
             eth_last_block_number_on_near,
             tx_data.block,
-            tx_data.block + unlock_tokens_worker_settings.blocks_for_tx_finalization
+            required_block,
+            schedule.estimated_unlock_time(required_block)
         );
-        return Ok(());
+        schedule.insert(tx_hash, required_block);
+        return Ok(None);
     }
 
-    let (tx_execution_status, near_tx_hash) = unlock_tokens(
-        unlock_tokens_worker_settings.server_addr.clone(),
-        account.clone(),
-        unlock_tokens_worker_settings.contract_account_id.clone(),
-        tx_data.proof,
-        tx_data.nonce,
-        gas,
+    if tx_data.retry_after > unix_timestamp() {
+        tracing::info!(
+            "Skip tx(nonce={}, tx_hash={}) until {}, failed unlock attempts: {}",
+            tx_data.nonce,
+            tx_hash,
+            tx_data.retry_after,
+            tx_data.unlock_attempts
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(UnlockRequest { tx_hash, tx_data }))
+}
+
+/// Sends the new transaction or resumes the stored one, every `lp_unlock` call is handled separately
+async fn handle_batch(
+    near_signer: &NearSigner,
+    eth_client: &RainbowBridgeEthereumClient<'_>,
+    unlock_tokens_worker_settings: &UnlockTokensWorkerSettings,
+    light_client_account_id: &str,
+    eth_last_block_number_on_near: u64,
+    batch: UnlockBatch,
+    mut redis: AsyncRedisWrapper,
+) {
+    let near_tx_hash = batch.signed_transaction.get_hash();
+    let nonces: Vec<u128> = batch
+        .requests
+        .iter()
+        .map(|(_, request)| request.tx_data.nonce)
+        .collect();
+    let tx_hashes: Vec<String> = batch
+        .requests
+        .iter()
+        .map(|(_, request)| request.tx_hash.clone())
+        .collect();
+
+    let result = if batch.resumed {
+        tracing::info!(
+            "Resume lp unlock for tokens with nonces={:?}, NEAR tx_hash: {}",
+            nonces,
+            near_tx_hash
+        );
+        near_signer.resume(batch.signed_transaction).await
+    } else {
+        tracing::info!(
+            "Start lp unlock for tokens with nonces={:?}, NEAR tx_hash: {}",
+            nonces,
+            near_tx_hash
+        );
+        near_signer.send(batch.signed_transaction).await
+    };
+
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(err) if err.is_rejected() => {
+            // The transaction can't be executed, the transfers are unlocked by a new one
+            tracing::warn!(
+                "{}; NEAR tx_hash: {}",
+                CustomError::FailedExecuteUnlockTokens(err),
+                near_tx_hash
+            );
+            remove_unlock_txs(&mut redis, &tx_hashes).await;
+            return;
+        }
+        Err(err) => {
+            tracing::error!(
+                "{}; NEAR tx_hash: {}, the transaction is resumed on the next loop",
+                CustomError::FailedExecuteUnlockTokens(err),
+                near_tx_hash
+            );
+            return;
+        }
+    };
+
+    let results = near_client::methods::action_results(&outcome, batch.actions_count);
+    let mut light_client_block = None;
+    for (action_index, request) in &batch.requests {
+        let result = match results.get(*action_index) {
+            // The unconfirmed unlock succeeded if the bridge doesn't keep the transfer pending
+            Some(ActionResult::Unconfirmed(err)) => match is_transfer_pending(
+                near_signer.near_client(),
+                &unlock_tokens_worker_settings.contract_account_id,
+                request.tx_data.nonce,
+            )
+            .await
+            {
+                Ok(false) => ActionResult::Success,
+                Ok(true) => ActionResult::Unconfirmed(err.clone()),
+                Err(check_err) => {
+                    tracing::error!(
+                        "{}, the unlock (nonce: {}) is checked again on the next loop; NEAR tx_hash: {}",
+                        check_err,
+                        request.tx_data.nonce,
+                        near_tx_hash
+                    );
+                    continue;
+                }
+            },
+            Some(result) => result.clone(),
+            None => continue,
+        };
+
+        match result {
+            ActionResult::Success => {
+                unstore_tx(&mut redis, &request.tx_hash).await;
+                tracing::info!(
+                    "Tokens unlocked (nonce: {}). NEAR tx_hash = {}",
+                    request.tx_data.nonce,
+                    near_tx_hash
+                );
+                UNLOCKED_TRANSACTIONS_COUNT.inc();
+            }
+            ActionResult::Failure(ref err) | ActionResult::Unconfirmed(ref err) => {
+                // The proof can be regenerated for a later block than the checked one, the light
+                // client is read again to compare it with the block of the proof header
+                if light_client_block.is_none() {
+                    light_client_block = Some(
+                        current_light_client_block(
+                            near_signer.near_client(),
+                            light_client_account_id,
+                            eth_last_block_number_on_near,
+                        )
+                        .await,
+                    );
+                }
+                let proof_block = header_block(&request.tx_data.proof.header_data)
+                    .map_or(request.tx_data.block, |(block_number, _)| block_number);
+                let failure = match UnlockFailure::classify(
+                    err,
+                    proof_block + unlock_tokens_worker_settings.blocks_for_tx_finalization,
+                    light_client_block.unwrap_or(eth_last_block_number_on_near),
+                ) {
+                    // Another unlock of the batch could fail, this one is retried
+                    UnlockFailure::Permanent(reason)
+                        if matches!(result, ActionResult::Unconfirmed(_)) =>
+                    {
+                        UnlockFailure::Unconfirmed(reason)
+                    }
+                    failure => failure,
+                };
+                tracing::error!(
+                    "Failed transaction (nonce: {}): {}; NEAR tx_hash: {}",
+                    request.tx_data.nonce,
+                    err,
+                    near_tx_hash
+                );
+                handle_failure(
+                    eth_client,
+                    unlock_tokens_worker_settings,
+                    request,
+                    failure,
+                    Some(near_tx_hash.to_string()),
+                    &mut redis,
+                )
+                .await;
+            }
+            ActionResult::Reverted => {
+                remove_unlock_txs(&mut redis, &[request.tx_hash.clone()]).await;
+                tracing::warn!(
+                    "The unlock is reverted (nonce: {}), it is sent again; NEAR tx_hash: {}",
+                    request.tx_data.nonce,
+                    near_tx_hash
+                );
+            }
+            ActionResult::Pending => tracing::info!(
+                "The unlock is not executed yet (nonce: {}), tx status: {:?}; NEAR tx_hash: {}",
+                request.tx_data.nonce,
+                outcome.status,
+                near_tx_hash
+            ),
+        }
+    }
+}
+
+/// Checks that the bridge still keeps the transfer pending, it's removed once the transfer is unlocked
+async fn is_transfer_pending(
+    near_client: &NearClient,
+    contract_account_id: &str,
+    nonce: u128,
+) -> Result<bool, CustomError> {
+    let response = near_client
+        .view(
+            contract_account_id.to_string(),
+            "get_pending_transfer".to_string(),
+            serde_json::json!({ "id": nonce.to_string() }),
+        )
+        .await
+        .map_err(CustomError::FailedExecuteGetPendingTransfer)?;
+
+    if let QueryResponseKind::CallResult(result) = response.kind {
+        let transfer: serde_json::Value =
+            serde_json::from_slice(&result.result).map_err(|err| {
+                CustomError::FailedExecuteGetPendingTransfer(NearClientError::UnexpectedResponse(
+                    err.to_string(),
+                ))
+            })?;
+        Ok(!transfer.is_null())
+    } else {
+        Err(CustomError::FailedExecuteGetPendingTransfer(
+            NearClientError::UnexpectedResponse("unexpected response".to_string()),
+        ))
+    }
+}
+
+/// The last block of the light client on NEAR, `known_block` if it can't be read
+async fn current_light_client_block(
+    near_client: &NearClient,
+    light_client_account_id: &str,
+    known_block: u64,
+) -> u64 {
+    match last_block_number(near_client, light_client_account_id.to_string()).await {
+        Ok(Some(block_number)) => block_number.max(known_block),
+        Ok(None) => known_block,
+        Err(err) => {
+            tracing::error!("{}", err);
+            known_block
+        }
+    }
+}
+
+/// Checks that the light client on NEAR stores the block of the proof, the proof of a block replaced
+/// by a reorg fails on-chain
+async fn is_proof_block_known(
+    near_client: &NearClient,
+    light_client_account_id: &str,
+    proof: &fast_bridge_common::Proof,
+) -> Result<bool, CustomError> {
+    let (block_number, block_hash) = match header_block(&proof.header_data) {
+        Ok(block) => block,
+        Err(err) => {
+            tracing::warn!(
+                "{}",
+                CustomError::FailedDecodeProofHeader(format!("{:?}", err))
+            );
+            return Ok(false);
+        }
+    };
+
+    let stored_block_hash = block_hash_safe(
+        near_client,
+        light_client_account_id.to_string(),
+        block_number,
     )
-    .await
-    .map_err(|err| format!("{}", err))?;
+    .await?;
 
-    match tx_execution_status {
-        FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started => {
-            return Err(format!(
-                "Tx status (nonce: {}): {:?}; NEAR tx_hash: {}",
-                tx_data.nonce, tx_execution_status, near_tx_hash
-            ));
+    if stored_block_hash != Some(block_hash) {
+        tracing::warn!(
+            "The proof block {} has hash {:?}, the light client stores {:?}",
+            block_number,
+            block_hash,
+            stored_block_hash
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Keeps the requests with the valid proofs of the blocks known to the light client, the other
+/// transfers are sent back for the proof regeneration or moved out of the queue if the proof is of
+/// another transfer
+async fn check_proofs(
+    near_client: &NearClient,
+    light_client_account_id: &str,
+    bridge_proxy_address: web3::types::Address,
+    transfer_tokens_event: &web3::ethabi::Event,
+    eth_client: &RainbowBridgeEthereumClient<'_>,
+    unlock_tokens_worker_settings: &UnlockTokensWorkerSettings,
+    requests: Vec<UnlockRequest>,
+    redis: &mut AsyncRedisWrapper,
+) -> Vec<UnlockRequest> {
+    let mut verified_requests = vec![];
+    for request in requests {
+        let verification = verify_transfer_proof(
+            transfer_tokens_event,
+            &request.tx_data.proof,
+            bridge_proxy_address,
+            web3::types::U256::from(request.tx_data.nonce),
+            request.tx_data.transfer.as_ref(),
+        );
+        match verification {
+            Ok(_) => verified_requests.push(request),
+            Err(err) => {
+                let failure = match err.is_event_mismatch() {
+                    true => UnlockFailure::Permanent(err.to_string()),
+                    false => UnlockFailure::ProofOutdated,
+                };
+                tracing::error!(
+                    "{}, the transfer (nonce: {}) isn't unlocked",
+                    CustomError::InvalidProof(err),
+                    request.tx_data.nonce
+                );
+                handle_failure(
+                    eth_client,
+                    unlock_tokens_worker_settings,
+                    &request,
+                    failure,
+                    None,
+                    redis,
+                )
+                .await;
+            }
+        }
+    }
+    let requests = verified_requests;
+
+    let checks = futures_util::future::join_all(requests.iter().map(|request| {
+        is_proof_block_known(near_client, light_client_account_id, &request.tx_data.proof)
+    }))
+    .await;
+
+    let mut known_requests = vec![];
+    for (request, check) in requests.into_iter().zip(checks) {
+        match check {
+            Ok(true) => known_requests.push(request),
+            Ok(false) => {
+                handle_failure(
+                    eth_client,
+                    unlock_tokens_worker_settings,
+                    &request,
+                    UnlockFailure::ProofOutdated,
+                    None,
+                    redis,
+                )
+                .await
+            }
+            Err(err) => tracing::error!(
+                "{}, the unlock (nonce: {}) is checked again on the next loop",
+                err,
+                request.tx_data.nonce
+            ),
+        }
+    }
+    known_requests
+}
+
+/// Reschedules the unlock or moves the transfer out of the queue if the failure is permanent or the
+/// attempts are exhausted
+async fn handle_failure(
+    eth_client: &RainbowBridgeEthereumClient<'_>,
+    unlock_tokens_worker_settings: &UnlockTokensWorkerSettings,
+    request: &UnlockRequest,
+    failure: UnlockFailure,
+    near_tx_hash: Option<String>,
+    redis: &mut AsyncRedisWrapper,
+) {
+    let mut tx_data = request.tx_data.clone();
+    tx_data.unlock_attempts += 1;
+    let attempts_exhausted =
+        tx_data.unlock_attempts >= unlock_tokens_worker_settings.max_unlock_attempts;
+
+    let failure = match failure {
+        UnlockFailure::ProofOutdated if !attempts_exhausted => {
+            match regenerate_proof(eth_client, &request.tx_hash, &mut tx_data).await {
+                Ok(()) => UnlockFailure::ProofOutdated,
+                Err(err) => UnlockFailure::Permanent(err),
+            }
+        }
+        UnlockFailure::GasExceeded => {
+            let gas = tx_data
+                .unlock_gas
+                .unwrap_or(unlock_tokens_worker_settings.gas);
+            match raised_gas(gas, unlock_tokens_worker_settings.max_gas_per_tx) {
+                Some(raised_gas) => {
+                    tx_data.unlock_gas = Some(raised_gas);
+                    UnlockFailure::GasExceeded
+                }
+                None => UnlockFailure::Permanent(format!(
+                    "The attached gas {} is exceeded and can't be raised above `max_gas_per_tx` {}",
+                    gas, unlock_tokens_worker_settings.max_gas_per_tx
+                )),
+            }
         }
-        FinalExecutionStatus::Failure(_) => {
-            unstore_tx(&mut redis, &tx_hash).await;
+        failure => failure,
+    };
+
+    if failure.is_permanent() || attempts_exhausted {
+        tracing::error!(
+            "The transfer (nonce: {}, tx_hash: {}) is moved out of the unlock queue after {} attempts: {}",
+            tx_data.nonce,
+            request.tx_hash,
+            tx_data.unlock_attempts,
+            failure
+        );
+        let failed_tx_data = FailedTxData {
+            tx_data,
+            reason: failure.to_string(),
+            near_tx_hash,
+            failed_at: unix_timestamp(),
+        };
+        if let Err(err) = redis
+            .store_failed_tx(&request.tx_hash, &failed_tx_data)
+            .await
+        {
+            tracing::error!("{}", CustomError::FailedStoreFailedTx(err));
+        }
+        FAILED_UNLOCKS_COUNT.inc();
+        return;
+    }
+
+    tx_data.retry_after = unix_timestamp()
+        + unlock_tokens_worker_settings.unlock_retry_delay_secs * tx_data.unlock_attempts as u64;
+    tracing::warn!(
+        "The unlock (nonce: {}, tx_hash: {}) is rescheduled after {} at {}, attempt {}",
+        tx_data.nonce,
+        request.tx_hash,
+        failure,
+        tx_data.retry_after,
+        tx_data.unlock_attempts
+    );
+    if let Err(err) = redis.update_tx(&request.tx_hash, &tx_data).await {
+        tracing::error!("{}", CustomError::FailedUpdateTransaction(err));
+    }
+    RESCHEDULED_UNLOCKS_COUNT.inc();
+}
+
+/// Builds the proof again for the block which includes the transaction now. Returns the error only
+/// if the transaction is failed, the proof is regenerated on the next attempt after other errors.
+async fn regenerate_proof(
+    eth_client: &RainbowBridgeEthereumClient<'_>,
+    tx_hash: &str,
+    tx_data: &mut TxData,
+) -> Result<(), String> {
+    let hash = web3::types::H256::from_str(tx_hash)
+        .map_err(|err| format!("Invalid transaction hash {}: {}", tx_hash, err))?;
+
+    let status = match eth_client.transaction_statuses(&[hash]).await {
+        Ok(mut statuses) => statuses.statuses.pop(),
+        Err(err) => Some(Err(err)),
+    };
+    let block_number = match status {
+        Some(Ok(TransactionStatus::Success(block_number))) => block_number.as_u64(),
+        Some(Ok(TransactionStatus::Failure(block_number))) => {
             return Err(format!(
-                "Failed transaction (nonce: {}): {:?}; NEAR tx_hash: {}",
-                tx_data.nonce, tx_execution_status, near_tx_hash
-            ));
+                "The transaction is failed in the block {}",
+                block_number
+            ))
+        }
+        // Dropped by a reorg
+        Some(Ok(TransactionStatus::Pending)) | None => return Ok(()),
+        Some(Err(err)) => {
+            tracing::error!("{}", CustomError::FailedFetchTxStatus(err));
+            return Ok(());
         }
-        FinalExecutionStatus::SuccessValue(_) => {
-            unstore_tx(&mut redis, &tx_hash).await;
+    };
+
+    match eth_client.get_proof(&hash).await {
+        Ok(proof) => {
+            tx_data.proof = proof;
+            tx_data.block = block_number;
             tracing::info!(
-                "Tokens unlocked (nonce: {}). NEAR tx_hash = {}",
-                tx_data.nonce,
-                near_tx_hash
+                "The proof of the transaction {} is regenerated for the block {}",
+                tx_hash,
+                tx_data.block
             );
-            UNLOCKED_TRANSACTIONS_COUNT.inc();
         }
+        Err(err) => tracing::error!("{}", CustomError::FailedFetchProof(err.to_string())),
     }
     Ok(())
 }
 
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Returns when the light client on NEAR reaches `required_block`, never returns without the block
+async fn wait_for_light_client(
+    light_client_updates: &mut tokio::sync::watch::Receiver<u64>,
+    required_block: Option<u64>,
+) {
+    let required_block = match required_block {
+        Some(required_block) => required_block,
+        None => return std::future::pending().await,
+    };
+
+    while *light_client_updates.borrow_and_update() < required_block {
+        if light_client_updates.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+    }
+}
+
+/// The number of `lp_unlock` calls in one NEAR transaction
+fn unlocks_per_tx(unlock_tokens_worker_settings: &UnlockTokensWorkerSettings, gas: u64) -> usize {
+    let gas_limited = (unlock_tokens_worker_settings.max_gas_per_tx / gas.max(1)) as usize;
+    unlock_tokens_worker_settings
+        .max_unlocks_per_tx
+        .min(gas_limited)
+        .max(1)
+}
+
+/// The gas of the `lp_unlock` call retried after `gas` is exceeded, `None` if it can't be raised
+fn raised_gas(gas: u64, max_gas_per_tx: u64) -> Option<u64> {
+    if gas >= max_gas_per_tx {
+        return None;
+    }
+    Some(gas.saturating_mul(2).min(max_gas_per_tx))
+}
+
 async fn unstore_tx(connection: &mut AsyncRedisWrapper, tx_hash: &String) {
     let unstore_tx_status = connection.unstore_tx(tx_hash.to_string()).await;
     if let Err(error) = unstore_tx_status {
         tracing::error!("{}", CustomError::FailedUnstoreTransaction(error))
     }
+    remove_unlock_txs(connection, &[tx_hash.to_string()]).await;
 }
 
+async fn remove_unlock_txs(connection: &mut AsyncRedisWrapper, tx_hashes: &[String]) {
+    if let Err(error) = connection.remove_unlock_txs(tx_hashes).await {
+        tracing::error!("{}", CustomError::FailedUnstoreUnlockTxs(error))
+    }
+}
+
+/// Packs up to `max_unlocks_per_tx` unlocks in one NEAR transaction and sends up to
+/// `max_concurrent_unlocks` transactions in parallel, the nonces of the relayer access keys are
+/// managed by `near_signer`. The transactions are stored before they are sent and are resumed on
+/// the next loop or after a restart if the outcome isn't received. The transfers waiting for the light
+/// client are scheduled by their block, the worker wakes up before the interval when the light client
+/// reaches the next one.
+#[allow(clippy::too_many_arguments)]
 pub async fn unlock_tokens_worker(
-    account: near_crypto::InMemorySigner,
+    near_signer: NearSigner,
+    eth_client: EthClient,
+    rainbow_bridge_index_js_path: String,
+    receipt_quorum: Option<usize>,
     gas: u64,
     settings: SafeSettings,
     storage: SafeStorage,
+    bridge_contract: SafeBridgeContract,
     mut redis: AsyncRedisWrapper,
 ) {
+    let rb_eth_client = RainbowBridgeEthereumClient::new(
+        &eth_client,
+        &rainbow_bridge_index_js_path,
+        receipt_quorum,
+    )
+    .unwrap();
+    let mut schedule = UnlockSchedule::new();
+    let mut light_client_updates = storage
+        .lock()
+        .await
+        .subscribe_eth_last_block_number_on_near();
+
     loop {
-        let unlock_tokens_settings = settings.lock().await.unlock_tokens_worker.clone();
+        let (unlock_tokens_settings, light_client_account_id, bridge_proxy_address) = {
+            let settings = settings.lock().await;
+            (
+                settings.unlock_tokens_worker.clone(),
+                settings
+                    .last_block_number_worker
+                    .contract_account_id
+                    .clone(),
+                settings.eth.bridge_proxy_address,
+            )
+        };
         let interval_secs = unlock_tokens_settings.request_interval_secs;
         tracing::trace!("unlock_tokens_worker: sleep for {} secs", interval_secs);
 
         let mut interval = crate::utils::request_interval(interval_secs).await;
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-        interval.tick().await;
-
-        match redis.get_tx_hashes().await {
-            Ok(queue) => {
-                for tx_hash in queue {
-                    let res = handle_one_tx(
-                        account.clone(),
-                        gas,
-                        unlock_tokens_settings.clone(),
-                        tx_hash,
-                        storage.clone(),
-                        redis.clone(),
-                    )
-                    .await;
-                    if let Err(err) = res {
-                        tracing::error!(err);
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = wait_for_light_client(
+                &mut light_client_updates,
+                schedule.next_required_block(),
+            ) => {}
+        }
+
+        // The proofs are checked with the ABI reloaded after the bridge upgrade
+        let transfer_tokens_event = match bridge_contract.lock().await.transfer_tokens_event() {
+            Ok(transfer_tokens_event) => transfer_tokens_event,
+            Err(err) => {
+                tracing::error!(err);
+                continue;
+            }
+        };
+
+        let queue = match redis.get_tx_hashes().await {
+            Ok(queue) => queue,
+            Err(error) => {
+                tracing::error!("{}", CustomError::FailedGetTxHashesQueue(error));
+                vec![]
+            }
+        };
+        let unlock_txs = match redis.get_unlock_txs(&queue).await {
+            Ok(unlock_txs) => unlock_txs,
+            Err(error) => {
+                tracing::error!("{}", CustomError::FailedGetUnlockTxs(error));
+                continue;
+            }
+        };
+
+        let eth_last_block_number_on_near = storage.lock().await.eth_last_block_number_on_near;
+        schedule.observe(eth_last_block_number_on_near);
+        schedule.retain(&queue.iter().map(String::as_str).collect::<HashSet<_>>());
+
+        let mut requests = vec![];
+        let mut resumed = vec![];
+        for (tx_hash, unlock_tx_data) in queue.into_iter().zip(unlock_txs) {
+            match unlock_tx_data {
+                Some(unlock_tx_data) => {
+                    schedule.remove(&tx_hash);
+                    match redis.get_tx_data(tx_hash.clone()).await {
+                        Ok(tx_data) => {
+                            resumed.push((UnlockRequest { tx_hash, tx_data }, unlock_tx_data))
+                        }
+                        Err(err) => tracing::error!("{}", CustomError::FailedGetTxData(err)),
                     }
                 }
+                None if schedule.is_waiting(&tx_hash, eth_last_block_number_on_near) => {}
+                None => match get_unlock_request(
+                    &unlock_tokens_settings,
+                    eth_last_block_number_on_near,
+                    tx_hash,
+                    &mut schedule,
+                    &mut redis,
+                )
+                .await
+                {
+                    Ok(Some(request)) => requests.push(request),
+                    Ok(None) => {}
+                    Err(err) => tracing::error!(err),
+                },
+            }
+        }
+        schedule.report(eth_last_block_number_on_near);
+
+        let mut batches = vec![];
+        for batch in resumed_batches(resumed) {
+            match batch {
+                Ok(batch) => batches.push(batch),
+                Err((batch_requests, err)) => {
+                    tracing::error!("{}, the transfers are unlocked by a new transaction", err);
+                    let tx_hashes: Vec<String> = batch_requests
+                        .iter()
+                        .map(|request| request.tx_hash.clone())
+                        .collect();
+                    remove_unlock_txs(&mut redis, &tx_hashes).await;
+                    requests.extend(batch_requests);
+                }
             }
-            Err(error) => tracing::error!("{}", CustomError::FailedGetTxHashesQueue(error)),
         }
 
+        let mut requests = check_proofs(
+            near_signer.near_client(),
+            &light_client_account_id,
+            bridge_proxy_address,
+            &transfer_tokens_event,
+            &rb_eth_client,
+            &unlock_tokens_settings,
+            requests,
+            &mut redis,
+        )
+        .await;
+
+        // The unlocks which exceeded the gas are retried alone with the raised gas
+        let (retried_requests, mut requests): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .partition(|request| request.tx_data.unlock_gas.is_some());
+        let mut unsigned_batches: Vec<(Vec<UnlockRequest>, u64)> = retried_requests
+            .into_iter()
+            .map(|request| {
+                let unlock_gas = request.tx_data.unlock_gas.unwrap_or(gas);
+                (vec![request], unlock_gas)
+            })
+            .collect();
+
+        let unlocks_per_tx = unlocks_per_tx(&unlock_tokens_settings, gas);
+        while !requests.is_empty() {
+            let rest = requests.split_off(unlocks_per_tx.min(requests.len()));
+            unsigned_batches.push((std::mem::replace(&mut requests, rest), gas));
+        }
+
+        for (batch_requests, gas) in unsigned_batches {
+            match sign_batch(
+                &near_signer,
+                unlock_tokens_settings.contract_account_id.clone(),
+                batch_requests,
+                gas,
+                &mut redis,
+            )
+            .await
+            {
+                Ok(batch) => batches.push(batch),
+                Err(err) => tracing::error!("{}", err),
+            }
+        }
+
+        futures_util::stream::iter(batches)
+            .for_each_concurrent(
+                unlock_tokens_settings.max_concurrent_unlocks.max(1),
+                |batch| {
+                    handle_batch(
+                        &near_signer,
+                        &rb_eth_client,
+                        &unlock_tokens_settings,
+                        &light_client_account_id,
+                        eth_last_block_number_on_near,
+                        batch,
+                        redis.clone(),
+                    )
+                },
+            )
+            .await;
+
         if let Ok(current_block_height) =
-            near_client::methods::get_last_near_block_height(unlock_tokens_settings.server_addr)
-                .await
+            near_signer.near_client().get_last_near_block_height().await
         {
             UNLOCK_TOKENS_CURRENT_NEAR_BLOCK_HEIGHT.set(current_block_height);
         }
@@ -173,29 +904,98 @@ pub async fn unlock_tokens_worker(
 
 #[cfg(test)]
 pub mod tests {
-    use crate::async_redis_wrapper::{AsyncRedisWrapper, TRANSACTIONS};
-    use crate::config::default_rpc_timeout_secs;
+    use crate::async_redis_wrapper::{AsyncRedisWrapper, TxData, UnlockTxData, TRANSACTIONS};
+    use crate::bridge_contract::BridgeContract;
     use crate::last_block::Storage;
     use crate::logs::init_logger;
     use crate::test_utils::{get_rb_index_path_str, get_settings, remove_all};
-    use crate::unlock_tokens::unlock_tokens_worker;
+    use crate::unlock_tokens::{
+        encode_signed_transaction, raised_gas, resumed_batches, unlock_tokens_worker,
+        unlocks_per_tx, UnlockRequest,
+    };
     use crate::{async_redis_wrapper, ethereum};
-    use eth_client::test_utils::get_eth_rpc_url;
-    use near_client::test_utils::get_near_signer;
+    use eth_client::test_utils::get_eth_client;
+    use near_client::signer::NearSigner;
+    use near_client::test_utils::{get_near_client, get_near_signer};
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::Transaction;
     use std::str::FromStr;
     use tokio::time::timeout;
 
+    fn unlock_tx(
+        tx_hash: &str,
+        nonce: u128,
+        signed_transaction: &str,
+        action_index: usize,
+    ) -> (UnlockRequest, UnlockTxData) {
+        (
+            UnlockRequest {
+                tx_hash: tx_hash.to_string(),
+                tx_data: TxData {
+                    nonce,
+                    ..Default::default()
+                },
+            },
+            UnlockTxData {
+                near_tx_hash: String::new(),
+                signed_transaction: signed_transaction.to_string(),
+                action_index,
+                actions_count: 2,
+            },
+        )
+    }
+
+    #[test]
+    fn resumed_batches_test() {
+        let signer =
+            InMemorySigner::from_seed("relayer.testnet".parse().unwrap(), KeyType::ED25519, "1");
+        let signed_transaction = Transaction {
+            signer_id: signer.account_id.clone(),
+            public_key: signer.public_key.clone(),
+            nonce: 7,
+            receiver_id: "bridge.testnet".parse().unwrap(),
+            block_hash: CryptoHash::default(),
+            actions: vec![],
+        }
+        .sign(&signer);
+        let encoded = encode_signed_transaction(&signed_transaction);
+
+        let batches = resumed_batches(vec![
+            unlock_tx("0x1", 1, &encoded, 1),
+            unlock_tx("0x2", 2, "not base64", 0),
+            unlock_tx("0x3", 3, &encoded, 0),
+        ]);
+        assert_eq!(batches.len(), 2);
+
+        let (requests, _) = batches
+            .iter()
+            .find_map(|batch| batch.as_ref().err())
+            .unwrap();
+        assert_eq!(requests[0].tx_hash, "0x2");
+
+        let batch = batches
+            .iter()
+            .find_map(|batch| batch.as_ref().ok())
+            .unwrap();
+        assert!(batch.resumed);
+        assert_eq!(batch.actions_count, 2);
+        assert_eq!(batch.signed_transaction, signed_transaction);
+        let indexes: Vec<(usize, u128)> = batch
+            .requests
+            .iter()
+            .map(|(action_index, request)| (*action_index, request.tx_data.nonce))
+            .collect();
+        assert_eq!(indexes, vec![(1, 1), (0, 3)]);
+    }
+
     async fn add_transaction(mut redis: AsyncRedisWrapper) {
         remove_all(redis.clone(), TRANSACTIONS).await;
-        let eth_rpc_url = get_eth_rpc_url();
         let rb_index_path_str = get_rb_index_path_str();
 
-        let eth_client = ethereum::RainbowBridgeEthereumClient::new(
-            eth_rpc_url,
-            &rb_index_path_str,
-            default_rpc_timeout_secs(),
-        )
-        .unwrap();
+        let eth_client =
+            ethereum::RainbowBridgeEthereumClient::new(&get_eth_client(), &rb_index_path_str, None)
+                .unwrap();
 
         let tx_hash = web3::types::H256::from_str(
             "ac8b251f1b4eeaacbdfbc2fa1711c201fdb628f5670680997194f17bc9de1baf",
@@ -207,6 +1007,7 @@ pub mod tests {
             block: 8249153 as u64,
             proof,
             nonce: 605226883 as u128,
+            ..Default::default()
         };
         redis
             .store_tx(
@@ -217,6 +1018,36 @@ pub mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn unlocks_per_tx_test() {
+        let mut settings = get_settings().unlock_tokens_worker;
+        settings.max_gas_per_tx = 300_000_000_000_000;
+
+        settings.max_unlocks_per_tx = 1;
+        assert_eq!(unlocks_per_tx(&settings, 50_000_000_000_000), 1);
+
+        settings.max_unlocks_per_tx = 10;
+        assert_eq!(unlocks_per_tx(&settings, 50_000_000_000_000), 6);
+        assert_eq!(unlocks_per_tx(&settings, 20_000_000_000_000), 10);
+        // The call which doesn't fit the budget is sent alone
+        assert_eq!(unlocks_per_tx(&settings, 300_000_000_000_000), 1);
+    }
+
+    #[test]
+    fn raised_gas_test() {
+        let max_gas_per_tx = 300_000_000_000_000;
+
+        assert_eq!(
+            raised_gas(50_000_000_000_000, max_gas_per_tx),
+            Some(100_000_000_000_000)
+        );
+        assert_eq!(
+            raised_gas(200_000_000_000_000, max_gas_per_tx),
+            Some(max_gas_per_tx)
+        );
+        assert_eq!(raised_gas(max_gas_per_tx, max_gas_per_tx), None);
+    }
+
     #[tokio::test]
     async fn smoke_unlock_tokens_worker_test() {
         init_logger();
@@ -231,10 +1062,17 @@ pub mod tests {
         storage.lock().await.eth_last_block_number_on_near = 8249163;
 
         let worker = unlock_tokens_worker(
-            signer,
+            NearSigner::new(get_near_client(), vec![signer]).unwrap(),
+            get_eth_client(),
+            get_rb_index_path_str(),
+            None,
             230_000_000_000_000u64,
             settings.clone(),
             storage.clone(),
+            std::sync::Arc::new(tokio::sync::Mutex::new(BridgeContract::new(
+                web3::types::Address::zero(),
+                eth_client::fast_bridge::FAST_BRIDGE_ABI.to_string(),
+            ))),
             redis,
         );
 
@@ -1,18 +1,23 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use eth_client::chain_head::ChainHeadService;
 use eth_client::methods::get_contract_abi;
+use eth_client::methods::{EthClient, RetryPolicy};
 use fast_bridge_common::{EthAddress, TransferDataEthereum, TransferDataNear};
 use fast_bridge_service_lib::async_redis_wrapper::{self, AsyncRedisWrapper};
 use fast_bridge_service_lib::async_redis_wrapper::{
     subscribe, EVENTS, NEW_EVENTS, PENDING_TRANSACTIONS, TRANSACTIONS,
 };
+use fast_bridge_service_lib::bridge_contract::BridgeContract;
 
 use fast_bridge_service_lib::config::{
-    Decimals, NearNetwork, NearTokenInfo, SafeSettings, Settings,
+    Decimals, NearNetwork, NearTokenInfo, PriceSource, SafeSettings, Settings,
 };
 use fast_bridge_service_lib::last_block::{last_block_number_worker, SafeStorage, Storage};
 use fast_bridge_service_lib::logs::init_logger;
 use fast_bridge_service_lib::unlock_tokens::unlock_tokens_worker;
+use near_client::methods::NearClient;
 use near_client::read_private_key::read_private_key_from_file;
+use near_client::signer::NearSigner;
 use near_crypto::InMemorySigner;
 use near_primitives::views::FinalExecutionStatus;
 use redis::AsyncCommands;
@@ -90,13 +95,17 @@ async fn main_integration_test() {
     handle_pending_transaction(settings.clone(), redis.clone()).await;
 
     let storage = std::sync::Arc::new(tokio::sync::Mutex::new(Storage::new()));
-    let _last_block_worker = last_block_number_worker(settings.clone(), storage.clone()).await;
+    let _last_block_worker =
+        last_block_number_worker(settings.clone(), get_near_client(), storage.clone()).await;
     wait_correct_last_block_number(storage.clone(), redis.clone()).await;
 
     let init_block = get_finality_block_height().await;
 
     let worker = unlock_tokens_worker(
-        near_relay_signer,
+        NearSigner::new(get_near_client(), vec![near_relay_signer]).unwrap(),
+        get_eth_client(),
+        get_rb_index_path_str(),
+        None,
         230_000_000_000_000u64,
         settings.clone(),
         storage.clone(),
@@ -179,6 +188,14 @@ fn get_eth_rpc_url() -> Url {
     url::Url::parse(&format!("https://goerli.infura.io/v3/{}", &api_key_string)).unwrap()
 }
 
+fn get_near_client() -> NearClient {
+    NearClient::new(&[get_near_endpoint_url()], 30).unwrap()
+}
+
+fn get_eth_client() -> EthClient {
+    EthClient::new(&[get_eth_rpc_url()], 30, RetryPolicy::default()).unwrap()
+}
+
 fn get_rb_index_path_str() -> String {
     let path_to_rainbow_bridge_rep_str = env::var("PATH_TO_RAINBOW_BRIDGE_REP").unwrap();
     let path_to_rainbow_bridge_rep = Path::new(&path_to_rainbow_bridge_rep_str);
@@ -193,12 +210,18 @@ fn get_settings() -> Settings {
     settings.near_tokens_whitelist.mapping.insert(
         near_addr(NEAR_TOKEN_ADDRESS),
         NearTokenInfo {
+            enabled: true,
             exchange_id: "wrapped-near".to_owned(),
+            price_source: PriceSource::Coingecko,
             fixed_fee: 0.into(),
             percent_fee: 0.0,
+            fee_tiers: vec![],
             decimals: Decimals::try_from(6).unwrap(),
             eth_address: ETH_TOKEN_ADDRESS.parse().unwrap(),
+            min_transfer_amount: None,
             max_transfer_amount: None,
+            profit_threshold: None,
+            min_profit_percent: None,
         },
     );
 
@@ -251,21 +274,20 @@ async fn check_unlock_event(mut stream: tokio::sync::mpsc::Receiver<String>) {
 }
 
 async fn mint_near_tokens(signer: InMemorySigner) {
-    let server_addr = get_near_endpoint_url();
     let contract_account_id = NEAR_TOKEN_ADDRESS.to_string();
     let method_name = "mint".to_string();
     let args = json!({"account_id": signer.account_id, "amount": format!("{}", TRANSFER_TOKEN_AMOUNT + FEE_TOKEN_AMOUNT)});
-    let response = near_client::methods::change(
-        server_addr,
-        signer,
-        contract_account_id,
-        method_name,
-        args,
-        4_000_000_000_000,
-        0,
-    )
-    .await
-    .unwrap();
+    let response = get_near_client()
+        .change(
+            &signer,
+            contract_account_id,
+            method_name,
+            args,
+            4_000_000_000_000,
+            0,
+        )
+        .await
+        .unwrap();
 
     if let FinalExecutionStatus::SuccessValue(_) = response.status {
         println!("Tokens on NEAR MINT successfully");
@@ -275,21 +297,20 @@ async fn mint_near_tokens(signer: InMemorySigner) {
 }
 
 async fn increase_fast_bridge_token_balance(signer: InMemorySigner) {
-    let server_addr = get_near_endpoint_url();
     let contract_account_id = NEAR_TOKEN_ADDRESS.to_string();
     let method_name = "ft_transfer_call".to_string();
     let args = json!({"receiver_id": near_addr(NEAR_CONTRACT_ADDRESS), "amount": format!("{}", TRANSFER_TOKEN_AMOUNT + FEE_TOKEN_AMOUNT), "msg": ""});
-    let response = near_client::methods::change(
-        server_addr,
-        signer,
-        contract_account_id,
-        method_name,
-        args,
-        300_000_000_000_000,
-        1,
-    )
-    .await
-    .unwrap();
+    let response = get_near_client()
+        .change(
+            &signer,
+            contract_account_id,
+            method_name,
+            args,
+            300_000_000_000_000,
+            1,
+        )
+        .await
+        .unwrap();
 
     if let FinalExecutionStatus::SuccessValue(_) = response.status {
         println!("Tokens on NEAR moved to the Bridge Contract successfully");
@@ -312,7 +333,6 @@ pub struct TransferMessage {
 }
 
 async fn init_token_transfer(signer: InMemorySigner) -> near_primitives::hash::CryptoHash {
-    let server_addr = get_near_endpoint_url();
     let contract_account_id = NEAR_CONTRACT_ADDRESS.to_string();
     let method_name = "init_transfer".to_string();
 
@@ -332,17 +352,17 @@ async fn init_token_transfer(signer: InMemorySigner) -> near_primitives::hash::C
     };
 
     let args = json!({ "msg": near_sdk::base64::encode(transfer_message.try_to_vec().unwrap()) });
-    let response = near_client::methods::change(
-        server_addr,
-        signer,
-        contract_account_id,
-        method_name,
-        args,
-        300_000_000_000_000,
-        0,
-    )
-    .await
-    .unwrap();
+    let response = get_near_client()
+        .change(
+            &signer,
+            contract_account_id,
+            method_name,
+            args,
+            300_000_000_000_000,
+            0,
+        )
+        .await
+        .unwrap();
 
     if let FinalExecutionStatus::SuccessValue(_) = response.status {
         println!("Tokens transfer init successfully");
@@ -392,13 +412,26 @@ async fn process_events(
     eth_contract_address: std::sync::Arc<web3::types::Address>,
     near_relay_account_id: String,
 ) {
+    let (chain_head_service, chain_head) =
+        ChainHeadService::new(get_eth_client(), None, Duration::from_secs(3))
+            .await
+            .unwrap();
+    tokio::spawn(chain_head_service.run());
+
     let worker = fast_bridge_service_lib::near_event_processor::process_near_events_worker(
         settings.clone(),
+        get_eth_client(),
+        get_near_client(),
         eth_keypair.clone(),
         redis.clone(),
-        eth_contract_abi.clone(),
+        std::sync::Arc::new(tokio::sync::Mutex::new(BridgeContract::new(
+            eth_addr(ETH_CONTRACT_IMPLEMENTATION_ADDRESS),
+            eth_contract_abi.to_string(),
+        ))),
         eth_contract_address.clone(),
         near_relay_account_id,
+        std::sync::Arc::new(tokio::sync::Mutex::new(Storage::new())),
+        chain_head,
     );
     let timeout_duration = std::time::Duration::from_secs(120);
     let _result = timeout(timeout_duration, worker).await;
@@ -410,11 +443,19 @@ async fn process_events(
 
 async fn handle_pending_transaction(settings: SafeSettings, redis: AsyncRedisWrapper) {
     let locked_settings = settings.lock().await.clone();
+    let (chain_head_service, chain_head) =
+        ChainHeadService::new(get_eth_client(), None, Duration::from_secs(3))
+            .await
+            .unwrap();
+    tokio::spawn(chain_head_service.run());
+
     let worker = fast_bridge_service_lib::pending_transactions_worker::run(
-        locked_settings.eth.rpc_url,
+        get_eth_client(),
         locked_settings.eth.rainbow_bridge_index_js_path.clone(),
         redis.clone(),
-        locked_settings.rpc_timeout_secs,
+        locked_settings.eth.receipt_quorum,
+        locked_settings.eth.bridge_proxy_address,
+        chain_head,
     );
 
     let timeout_duration = std::time::Duration::from_secs(30);
@@ -433,7 +474,7 @@ async fn handle_pending_transaction(settings: SafeSettings, redis: AsyncRedisWra
 }
 
 async fn mint_eth_tokens() {
-    let eth1_endpoint = get_eth_rpc_url();
+    let eth_client = get_eth_client();
 
     let token = eth_addr(ETH_TOKEN_ADDRESS);
 
@@ -450,27 +491,26 @@ async fn mint_eth_tokens() {
         secp256k1::SecretKey::from_str(&(env::var("FAST_BRIDGE_ETH_PRIVATE_KEY").unwrap())[..64])
             .unwrap();
 
-    let res = eth_client::methods::change(
-        eth1_endpoint,
-        token,
-        contract_abi.as_bytes(),
-        &method_name,
-        amount,
-        &priv_key,
-        true,
-        None,
-        None,
-        None,
-        30,
-    )
-    .await
-    .unwrap();
+    let res = eth_client
+        .change(
+            token,
+            contract_abi.as_bytes(),
+            &method_name,
+            amount,
+            &priv_key,
+            true,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
 
     println!("transaction hash: {:?}", res);
 }
 
 async fn increase_allowance() {
-    let eth1_endpoint = get_eth_rpc_url();
+    let eth_client = get_eth_client();
 
     let token = eth_addr(ETH_TOKEN_ADDRESS);
 
@@ -489,21 +529,20 @@ async fn increase_allowance() {
         secp256k1::SecretKey::from_str(&(env::var("FAST_BRIDGE_ETH_PRIVATE_KEY").unwrap())[..64])
             .unwrap();
 
-    let res = eth_client::methods::change(
-        eth1_endpoint,
-        token,
-        contract_abi.as_bytes(),
-        &method_name,
-        method_args,
-        &priv_key,
-        true,
-        None,
-        None,
-        None,
-        30,
-    )
-    .await
-    .unwrap();
+    let res = eth_client
+        .change(
+            token,
+            contract_abi.as_bytes(),
+            &method_name,
+            method_args,
+            &priv_key,
+            true,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
 
     println!("transaction hash: {:?}", res);
 }